@@ -270,9 +270,9 @@ mod unnamed {
                 let shell_res = example_client.get_mdata_shell(*data.name(), data.tag());
                 let entries_res = example_client.list_mdata_entries(*data.name(), data.tag());
 
-                if let (Ok(shell), Ok(entries)) = (shell_res, entries_res) {
+                if let (Ok(shell), Ok((entries, None))) = (shell_res, entries_res) {
                     assert_eq!(shell, data.shell());
-                    assert_eq!(entries, *data.entries());
+                    assert_eq!(entries, data.entries());
                     print_color("OK\n", color::GREEN);
                 } else {
                     test_success = false;
@@ -298,9 +298,9 @@ mod unnamed {
                 let res_shell = example_client.get_mdata_shell(*data.name(), data.tag());
                 let res_entries = example_client.list_mdata_entries(*data.name(), data.tag());
 
-                if let (Ok(shell), Ok(entries)) = (res_shell, res_entries) {
+                if let (Ok(shell), Ok((entries, None))) = (res_shell, res_entries) {
                     assert_eq!(shell, data.shell());
-                    assert_eq!(entries, *data.entries());
+                    assert_eq!(entries, data.entries());
                     print_color("OK\n", color::GREEN);
                 } else {
                     test_success = false;
@@ -7,8 +7,8 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use routing::{
-    Authority, Client, ClientError, Event, FullId, ImmutableData, MessageId, MutableData, Response,
-    Value, XorName,
+    Authority, Client, ClientError, EntriesRange, Event, FullId, ImmutableData, MessageId,
+    MutableData, Response, Value, XorName,
 };
 use rust_sodium::crypto;
 use std::collections::BTreeMap;
@@ -165,11 +165,15 @@ impl ExampleClient {
         &mut self,
         name: XorName,
         tag: u64,
-    ) -> Result<BTreeMap<Vec<u8>, Value>, ClientError> {
+    ) -> Result<(BTreeMap<Vec<u8>, Value>, Option<Vec<u8>>), ClientError> {
         let msg_id = MessageId::new();
-        unwrap!(self
-            .client
-            .list_mdata_entries(Authority::NaeManager(name), name, tag, msg_id,));
+        unwrap!(self.client.list_mdata_entries(
+            Authority::NaeManager(name),
+            name,
+            tag,
+            EntriesRange::new(),
+            msg_id,
+        ));
         recv_response!(self, ListMDataEntries, name, msg_id)
     }
 
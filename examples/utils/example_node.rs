@@ -9,8 +9,8 @@
 use lru_time_cache::LruCache;
 use maidsafe_utilities::serialisation::{deserialise, serialise};
 use routing::{
-    Authority, ClientError, Event, EventStream, ImmutableData, MessageId, MutableData, Node,
-    Prefix, Request, Response, XorName,
+    Authority, ClientError, EntriesRange, Event, EventStream, ImmutableData, MessageId,
+    MutableData, Node, Prefix, Request, Response, XorName,
 };
 use std::collections::HashMap;
 use std::time::Duration;
@@ -109,17 +109,26 @@ impl ExampleNode {
             Request::PutIData { data, msg_id } => {
                 self.handle_put_idata_request(src, dst, data, msg_id)
             }
-            Request::GetMDataShell { name, tag, msg_id } => {
+            Request::GetMDataShell {
+                name, tag, msg_id, ..
+            } => {
                 self.handle_get_mdata_shell_request(src, dst, name, tag, msg_id)
             }
-            Request::ListMDataEntries { name, tag, msg_id } => {
-                self.handle_list_mdata_entries_request(src, dst, name, tag, msg_id)
+            Request::ListMDataEntries {
+                name,
+                tag,
+                range,
+                msg_id,
+            } => self.handle_list_mdata_entries_request(src, dst, name, tag, &range, msg_id),
+            Request::ListMDataPermissions { name, tag, msg_id } => {
+                self.handle_list_mdata_permissions_request(src, dst, name, tag, msg_id)
             }
             Request::GetMDataValue {
                 name,
                 tag,
                 key,
                 msg_id,
+                ..
             } => self.handle_get_mdata_value_request(src, dst, name, tag, &key, msg_id),
             _ => {
                 warn!(
@@ -256,12 +265,14 @@ impl ExampleNode {
         dst: Authority<XorName>,
         name: XorName,
         tag: u64,
+        range: &EntriesRange,
         msg_id: MessageId,
     ) {
         match (src, dst) {
             (src @ Authority::Client { .. }, dst @ Authority::NaeManager(_)) => {
                 let res = if let Some(data) = self.mdata_store.get(&(name, tag)) {
-                    Ok(data.entries().clone())
+                    data.check_read_permission(&src)
+                        .map(|()| data.entries_range(range))
                 } else {
                     trace!(
                         "{:?} ListMDataEntries request failed for {:?}.",
@@ -279,6 +290,35 @@ impl ExampleNode {
         }
     }
 
+    fn handle_list_mdata_permissions_request(
+        &mut self,
+        src: Authority<XorName>,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) {
+        match (src, dst) {
+            (src @ Authority::Client { .. }, dst @ Authority::NaeManager(_)) => {
+                let res = if let Some(data) = self.mdata_store.get(&(name, tag)) {
+                    Ok((data.permissions().clone(), data.expired_permissions()))
+                } else {
+                    trace!(
+                        "{:?} ListMDataPermissions request failed for {:?}.",
+                        self.get_debug_name(),
+                        (name, tag)
+                    );
+                    Err(ClientError::NoSuchData)
+                };
+
+                unwrap!(self
+                    .node
+                    .send_list_mdata_permissions_response(dst, src, res, msg_id))
+            }
+            (src, dst) => unreachable!("Wrong Src and Dest Authority {:?} - {:?}", src, dst),
+        }
+    }
+
     fn handle_get_mdata_value_request(
         &mut self,
         src: Authority<XorName>,
@@ -294,7 +334,10 @@ impl ExampleNode {
                     .mdata_store
                     .get(&(name, tag))
                     .ok_or(ClientError::NoSuchData)
-                    .and_then(|data| data.get(key).cloned().ok_or(ClientError::NoSuchEntry))
+                    .and_then(|data| {
+                        data.check_read_permission(&src)?;
+                        data.get(key).cloned().ok_or(ClientError::NoSuchEntry)
+                    })
                     .map_err(|error| {
                         trace!(
                             "{:?} GetMDataValue request failed for {:?}.",
@@ -0,0 +1,274 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Account registration and login.
+//!
+//! The account data of a user is encrypted and stored in a session packet: a `MutableData` with
+//! the type tag `TYPE_TAG_SESSION_PACKET` and a single entry under `ACC_LOGIN_ENTRY_KEY`, which
+//! holds a serialised `AccountPacket`. Its location, its owner key and the encryption key are all
+//! derived from the user's credentials, so nothing but the credentials is needed to log in.
+
+use crate::client_error::ClientError;
+use crate::common_types::AccountPacket;
+use crate::data::{EntryAction, EntryActions, MutableData, Value};
+use crate::rust_sodium::crypto::{pwhash, secretbox, sign};
+use crate::xor_name::XorName;
+use crate::{ACC_LOGIN_ENTRY_KEY, TYPE_TAG_SESSION_PACKET};
+use maidsafe_utilities::serialisation;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+use std::iter;
+use tiny_keccak::sha3_256;
+
+/// Location and keys of a session packet, derived from the user's credentials.
+///
+/// The packet's location only depends on the `locator`, while the signing and the encryption key
+/// depend on both the `locator` and the `password`. Everything is derived with a deliberately
+/// slow password hash, salted with the hash of the `locator`, so that guessing credentials is
+/// expensive and can't be done for all users at once.
+#[derive(Clone)]
+pub struct AccountKeys {
+    name: XorName,
+    public_key: sign::PublicKey,
+    secret_key: sign::SecretKey,
+    enc_key: secretbox::Key,
+}
+
+impl AccountKeys {
+    /// Derives the location and keys of the session packet from the given credentials.
+    pub fn from_credentials(locator: &[u8], password: &[u8]) -> AccountKeys {
+        let salt = pwhash::Salt(sha3_256(locator));
+        let mut name = XorName([0; 32]);
+        derive(&mut name.0, locator, &salt);
+        let mut secrets = [0; 64];
+        derive(&mut secrets, password, &salt);
+        let mut seed = sign::Seed([0; 32]);
+        seed.0.copy_from_slice(&secrets[..32]);
+        let (public_key, secret_key) = sign::keypair_from_seed(&seed);
+        let mut enc_key = secretbox::Key([0; 32]);
+        enc_key.0.copy_from_slice(&secrets[32..]);
+
+        AccountKeys {
+            name,
+            public_key,
+            secret_key,
+            enc_key,
+        }
+    }
+
+    /// Returns the name of the session packet.
+    pub fn name(&self) -> &XorName {
+        &self.name
+    }
+
+    /// Returns the key owning the session packet.
+    pub fn public_key(&self) -> &sign::PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the secret key matching `public_key`.
+    pub fn secret_key(&self) -> &sign::SecretKey {
+        &self.secret_key
+    }
+
+    /// Creates a new session packet holding the encrypted `account` data. If `invitation` is
+    /// given, the packet is created as `AccountPacket::WithInvitation`, and the invitation must
+    /// have been claimed for it with `Client::claim_invitation` before the packet is put.
+    pub fn new_session_packet(&self, account: &[u8], invitation: Option<String>) -> MutableData {
+        let acc_pkt = self.encrypt(account);
+        let packet = match invitation {
+            Some(invitation_string) => AccountPacket::WithInvitation {
+                invitation_string,
+                acc_pkt,
+            },
+            None => AccountPacket::AccPkt(acc_pkt),
+        };
+        let value = Value {
+            content: unwrap!(serialisation::serialise(&packet)),
+            entry_version: 0,
+        };
+
+        let entries = iter::once((ACC_LOGIN_ENTRY_KEY.to_vec(), value)).collect();
+        let owners = iter::once(self.public_key).collect();
+        unwrap!(MutableData::new(
+            self.name,
+            TYPE_TAG_SESSION_PACKET,
+            BTreeMap::new(),
+            entries,
+            owners,
+        ))
+    }
+
+    /// Decrypts the account data from the login entry of a session packet.
+    pub fn decrypt_account(&self, value: &Value) -> Result<Vec<u8>, ClientError> {
+        match parse(value)? {
+            AccountPacket::WithInvitation { acc_pkt, .. } | AccountPacket::AccPkt(acc_pkt) => {
+                self.decrypt(&acc_pkt)
+            }
+        }
+    }
+
+    /// Returns the entry actions replacing an `AccountPacket::WithInvitation` in the login entry
+    /// with an `AccountPacket::AccPkt` holding the same account data, or `None` if the packet
+    /// doesn't contain an invitation. This should be done as soon as the account is registered,
+    /// so the invitation code doesn't leak.
+    pub fn strip_invitation_actions(
+        &self,
+        value: &Value,
+    ) -> Result<Option<BTreeMap<Vec<u8>, EntryAction>>, ClientError> {
+        let acc_pkt = match parse(value)? {
+            AccountPacket::WithInvitation { acc_pkt, .. } => acc_pkt,
+            AccountPacket::AccPkt(_) => return Ok(None),
+        };
+        // Make sure the credentials are right before touching the packet.
+        let _ = self.decrypt(&acc_pkt)?;
+
+        let content = unwrap!(serialisation::serialise(&AccountPacket::AccPkt(acc_pkt)));
+        let actions = EntryActions::new()
+            .update(ACC_LOGIN_ENTRY_KEY.to_vec(), content, value.entry_version + 1)
+            .into();
+        Ok(Some(actions))
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = secretbox::gen_nonce();
+        let ciphertext = secretbox::seal(plaintext, &nonce, &self.enc_key);
+        unwrap!(serialisation::serialise(&(nonce, ciphertext)))
+    }
+
+    fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let (nonce, ciphertext): (secretbox::Nonce, Vec<u8>) =
+            serialisation::deserialise(encrypted).map_err(|_| ClientError::DecryptionFailed)?;
+        secretbox::open(&ciphertext, &nonce, &self.enc_key)
+            .map_err(|_| ClientError::DecryptionFailed)
+    }
+}
+
+impl Debug for AccountKeys {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "AccountKeys {{ name: {}, .. }}", self.name)
+    }
+}
+
+/// Returns the invitation code of a session packet which is being put, if it has one. Vaults use
+/// this to check and claim the invitation before storing the packet.
+pub fn invitation(packet: &MutableData) -> Option<String> {
+    let value = packet.get(ACC_LOGIN_ENTRY_KEY)?;
+    match parse(value) {
+        Ok(AccountPacket::WithInvitation {
+            invitation_string, ..
+        }) => Some(invitation_string),
+        _ => None,
+    }
+}
+
+/// Maps the error of putting a session packet to the corresponding account error.
+pub fn registration_error(error: ClientError) -> ClientError {
+    match error {
+        ClientError::DataExists => ClientError::AccountExists,
+        error => error,
+    }
+}
+
+/// Maps the error of fetching the login entry of a session packet to the corresponding account
+/// error.
+pub fn login_error(error: ClientError) -> ClientError {
+    match error {
+        ClientError::NoSuchData | ClientError::NoSuchEntry => ClientError::NoSuchAccount,
+        error => error,
+    }
+}
+
+fn parse(value: &Value) -> Result<AccountPacket, ClientError> {
+    if value.content.is_empty() {
+        return Err(ClientError::NoSuchAccount);
+    }
+    serialisation::deserialise(&value.content)
+        .map_err(|_| ClientError::InvalidSchema(TYPE_TAG_SESSION_PACKET))
+}
+
+// Fills `key` with the slow hash of `input`.
+fn derive(key: &mut [u8], input: &[u8], salt: &pwhash::Salt) {
+    let _ = unwrap!(pwhash::derive_key(
+        key,
+        input,
+        salt,
+        pwhash::OPSLIMIT_INTERACTIVE,
+        pwhash::MEMLIMIT_INTERACTIVE,
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_login() {
+        let keys = AccountKeys::from_credentials(b"locator", b"password");
+        let account = b"account data".to_vec();
+        let mut packet = keys.new_session_packet(&account, Some("invite".to_owned()));
+        assert_eq!(packet.name(), keys.name());
+        assert_eq!(invitation(&packet), Some("invite".to_owned()));
+
+        // The same credentials always lead to the same packet and keys.
+        let same_keys = AccountKeys::from_credentials(b"locator", b"password");
+        assert_eq!(same_keys.public_key(), keys.public_key());
+        let value = unwrap!(packet.get(ACC_LOGIN_ENTRY_KEY)).clone();
+        assert_eq!(unwrap!(same_keys.decrypt_account(&value)), account);
+
+        // A wrong password finds the packet, but can't decrypt or modify it.
+        let wrong_keys = AccountKeys::from_credentials(b"locator", b"wrong");
+        assert_eq!(wrong_keys.name(), keys.name());
+        assert_ne!(wrong_keys.public_key(), keys.public_key());
+        assert_eq!(
+            wrong_keys.decrypt_account(&value),
+            Err(ClientError::DecryptionFailed)
+        );
+        assert_eq!(
+            wrong_keys.strip_invitation_actions(&value),
+            Err(ClientError::DecryptionFailed)
+        );
+
+        // The keys are salted with the locator, so the same password gives other keys elsewhere.
+        let other_keys = AccountKeys::from_credentials(b"other locator", b"password");
+        assert_ne!(other_keys.name(), keys.name());
+        assert_ne!(other_keys.public_key(), keys.public_key());
+        assert_eq!(
+            other_keys.decrypt_account(&value),
+            Err(ClientError::DecryptionFailed)
+        );
+
+        // Once registered, the invitation is stripped from the packet.
+        let actions = unwrap!(unwrap!(keys.strip_invitation_actions(&value)));
+        unwrap!(packet.mutate_entries(actions, *keys.public_key()));
+        assert_eq!(invitation(&packet), None);
+        let value = unwrap!(packet.get(ACC_LOGIN_ENTRY_KEY)).clone();
+        assert_eq!(unwrap!(keys.decrypt_account(&value)), account);
+        assert_eq!(unwrap!(keys.strip_invitation_actions(&value)), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            registration_error(ClientError::DataExists),
+            ClientError::AccountExists
+        );
+        assert_eq!(
+            registration_error(ClientError::InvitationAlreadyClaimed),
+            ClientError::InvitationAlreadyClaimed
+        );
+        assert_eq!(
+            login_error(ClientError::NoSuchData),
+            ClientError::NoSuchAccount
+        );
+        assert_eq!(
+            login_error(ClientError::AccessDenied),
+            ClientError::AccessDenied
+        );
+    }
+}
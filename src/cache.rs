@@ -6,16 +6,47 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::data::{ImmutableData, MutableData, Value};
 use crate::messages::{Request, Response};
+use crate::xor_name::XorName;
+#[cfg(feature = "use-mock-crust")]
+use fake_clock::FakeClock as Instant;
+use maidsafe_utilities::serialisation;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(feature = "use-mock-crust"))]
+use std::time::Instant;
+
+/// How long an `LruCache` keeps MutableData responses by default, in seconds.
+pub const DEFAULT_MDATA_TTL_SECS: u64 = 60;
 
 /// A cache that stores `Response`s keyed by `Requests`. Should be implemented
 /// by layers above routing.
+///
+/// Only messages for which `Request::is_cacheable` or `Response::is_cacheable` hold are ever
+/// passed to the cache. In particular, unpublished ImmutableData is never cached, as its owner
+/// can delete it, and only MutableData requests which set `allow_cached` are answered from it.
 pub trait Cache: Send {
     /// Retrieve cached response for the given request.
     fn get(&self, request: &Request) -> Option<Response>;
 
     /// Cache the given response.
     fn put(&self, response: Response);
+
+    /// Cache the given response to `request`. Responses which don't identify their data, like
+    /// `Response::GetMDataValue`, can only be cached this way. Defaults to `put`.
+    fn put_response_to(&self, _request: &Request, response: Response) {
+        self.put(response)
+    }
+
+    /// Drop the cached responses about the given MutableData which reflect a data version older
+    /// than `data_version`, or all of them if it is `None`, e.g. because the successful response
+    /// to a request mutating the data passed through the node. Does nothing by default.
+    fn invalidate_mdata(&self, _name: &XorName, _tag: u64, _data_version: Option<u64>) {}
 }
 
 /// A no-op implementation of the `Cache` trait. Throws everything away on put
@@ -28,3 +59,585 @@ impl Cache for NullCache {
     }
     fn put(&self, _: Response) {}
 }
+
+/// A bounded in-memory cache of ImmutableData and MutableData responses, which evicts the least
+/// recently used responses once either its byte budget or its entry count is exceeded.
+///
+/// Responses are keyed by the data they contain, so a request is answered from the cache whatever
+/// its message identifier. MutableData responses are only kept for a limited time, as the cache
+/// can't see every mutation. Caching a newer shell drops the responses reflecting older versions
+/// of the data, and so do the `Response::MDataChanged` notifications passing through the node,
+/// while a successful mutation of the data drops all of its responses. Pass it to
+/// `NodeBuilder::cache` to use it in a node.
+pub struct LruCache {
+    max_bytes: u64,
+    max_entries: usize,
+    mdata_ttl: Duration,
+    entries: RefCell<LruEntries<Key, Entry>>,
+    stats: Arc<CacheStats>,
+}
+
+impl LruCache {
+    /// Creates an empty cache holding at most `max_entries` responses, whose data is at most
+    /// `max_bytes` long in total.
+    pub fn new(max_bytes: u64, max_entries: usize) -> LruCache {
+        LruCache {
+            max_bytes,
+            max_entries,
+            mdata_ttl: Duration::from_secs(DEFAULT_MDATA_TTL_SECS),
+            entries: RefCell::new(LruEntries::new()),
+            stats: Default::default(),
+        }
+    }
+
+    /// Sets how long MutableData responses are kept, `DEFAULT_MDATA_TTL_SECS` by default.
+    pub fn mdata_ttl(mut self, ttl: Duration) -> LruCache {
+        self.mdata_ttl = ttl;
+        self
+    }
+
+    /// Returns the counters of the cache. They are shared with the cache, so they keep being
+    /// updated after it has been passed to the node.
+    pub fn stats(&self) -> Arc<CacheStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Returns true if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total length of the cached data.
+    pub fn bytes(&self) -> u64 {
+        self.entries.borrow().bytes()
+    }
+
+    fn insert(&self, key: Key, entry: Entry, size: u64) {
+        if size > self.max_bytes {
+            return;
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        entries.insert(key, entry, size);
+        while entries.bytes() > self.max_bytes || entries.len() > self.max_entries {
+            if entries.remove_lru().is_none() {
+                break;
+            }
+            increment(&self.stats.evictions);
+        }
+    }
+
+    fn put_mdata_shell(&self, shell: MutableData) {
+        let (name, tag) = (*shell.name(), shell.tag());
+        let key = Key::MDataShell(name, tag);
+        self.remove_if_expired(&key);
+        if let Some(&Entry::MDataShell(ref cached, _)) = self.entries.borrow_mut().get(&key) {
+            // Never go back to an older shell, which may be delivered after a newer one.
+            if (cached.data_version(), cached.version()) >= (shell.data_version(), shell.version())
+            {
+                return;
+            }
+        }
+
+        self.invalidate_mdata(&name, tag, Some(shell.data_version()));
+        let size = unwrap!(serialisation::serialise(&shell)).len() as u64;
+        let expiry = Instant::now() + self.mdata_ttl;
+        self.insert(key, Entry::MDataShell(shell, expiry), size);
+    }
+
+    // The value may be newer or older than the cached shell, so it isn't tied to the shell's data
+    // version: any newer version of the data seen drops it.
+    fn put_mdata_value(&self, name: XorName, tag: u64, key: Vec<u8>, value: Value) {
+        let size = (key.len() + value.content.len()) as u64;
+        let key = Key::MDataValue(name, tag, key);
+        self.remove_if_expired(&key);
+        if let Some(&Entry::MDataValue(ref cached, _)) = self.entries.borrow_mut().get(&key) {
+            if cached.entry_version >= value.entry_version {
+                return;
+            }
+        }
+
+        let expiry = Instant::now() + self.mdata_ttl;
+        self.insert(key, Entry::MDataValue(value, expiry), size);
+    }
+
+    fn remove_if_expired(&self, key: &Key) {
+        let mut entries = self.entries.borrow_mut();
+        if entries.get(key).map_or(false, Entry::is_expired) {
+            let _ = entries.remove(key);
+        }
+    }
+}
+
+impl Cache for LruCache {
+    fn get(&self, request: &Request) -> Option<Response> {
+        if !request.is_cacheable() {
+            return None;
+        }
+        let key = Key::of(request)?;
+        let msg_id = *request.message_id();
+        self.remove_if_expired(&key);
+        let response = match self.entries.borrow_mut().get(&key) {
+            Some(&Entry::IData(ref data)) => Some(Response::GetIData {
+                res: Ok(data.clone()),
+                msg_id,
+            }),
+            Some(&Entry::MDataShell(ref shell, _)) => Some(Response::GetMDataShell {
+                res: Ok(shell.clone()),
+                msg_id,
+            }),
+            Some(&Entry::MDataValue(ref value, _)) => Some(Response::GetMDataValue {
+                res: Ok(value.clone()),
+                msg_id,
+            }),
+            None => None,
+        };
+
+        if response.is_some() {
+            increment(&self.stats.hits);
+        } else {
+            increment(&self.stats.misses);
+        }
+        response
+    }
+
+    fn put(&self, response: Response) {
+        if !response.is_cacheable() {
+            return;
+        }
+        if let Response::GetIData { res: Ok(data), .. } = response {
+            let size = data.value().len() as u64;
+            self.insert(Key::IData(*data.name()), Entry::IData(data), size);
+        }
+    }
+
+    fn put_response_to(&self, request: &Request, response: Response) {
+        if !request.is_cacheable() || !response.is_cacheable() {
+            return;
+        }
+        match (request, response) {
+            (
+                &Request::GetMDataShell { name, tag, .. },
+                Response::GetMDataShell { res: Ok(shell), .. },
+            ) => {
+                if *shell.name() == name && shell.tag() == tag {
+                    self.put_mdata_shell(shell);
+                }
+            }
+            (
+                &Request::GetMDataValue {
+                    name, tag, ref key, ..
+                },
+                Response::GetMDataValue { res: Ok(value), .. },
+            ) => self.put_mdata_value(name, tag, key.clone(), value),
+            (_, response) => self.put(response),
+        }
+    }
+
+    fn invalidate_mdata(&self, name: &XorName, tag: u64, data_version: Option<u64>) {
+        self.entries.borrow_mut().remove_matching(|key, entry| {
+            key.mdata() == Some((name, tag))
+                && match (entry.data_version(), data_version) {
+                    (Some(cached), Some(newer)) => cached < newer,
+                    _ => true,
+                }
+        });
+    }
+}
+
+/// What a response cached by an `LruCache` is about.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Key {
+    IData(XorName),
+    MDataShell(XorName, u64),
+    MDataValue(XorName, u64, Vec<u8>),
+}
+
+impl Key {
+    fn of(request: &Request) -> Option<Key> {
+        match *request {
+            Request::GetIData { name, .. } => Some(Key::IData(name)),
+            Request::GetMDataShell { name, tag, .. } => Some(Key::MDataShell(name, tag)),
+            Request::GetMDataValue {
+                name, tag, ref key, ..
+            } => Some(Key::MDataValue(name, tag, key.clone())),
+            _ => None,
+        }
+    }
+
+    fn mdata(&self) -> Option<(&XorName, u64)> {
+        match *self {
+            Key::IData(_) => None,
+            Key::MDataShell(ref name, tag) | Key::MDataValue(ref name, tag, _) => Some((name, tag)),
+        }
+    }
+}
+
+/// The content of a response cached by an `LruCache`. MutableData is stored with the time it
+/// expires at.
+enum Entry {
+    IData(ImmutableData),
+    MDataShell(MutableData, Instant),
+    MDataValue(Value, Instant),
+}
+
+impl Entry {
+    fn data_version(&self) -> Option<u64> {
+        match *self {
+            Entry::MDataShell(ref shell, _) => Some(shell.data_version()),
+            Entry::IData(_) | Entry::MDataValue(..) => None,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        match *self {
+            Entry::IData(_) => false,
+            Entry::MDataShell(_, expiry) | Entry::MDataValue(_, expiry) => expiry <= Instant::now(),
+        }
+    }
+}
+
+/// Counters of an `LruCache` or a `DiskCache`.
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub(crate) hits: AtomicUsize,
+    pub(crate) misses: AtomicUsize,
+    pub(crate) evictions: AtomicUsize,
+}
+
+impl CacheStats {
+    /// Returns the number of requests answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of cacheable requests which weren't in the cache.
+    pub fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of responses evicted to stay within the limits of the cache.
+    pub fn evictions(&self) -> usize {
+        self.evictions.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) fn increment(counter: &AtomicUsize) {
+    let _ = counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The entries of a cache bounded by size, with the time each was last used. The times are a
+/// counter rather than a clock, so the least recently used entry is always the first of `by_use`.
+pub(crate) struct LruEntries<K, T> {
+    entries: HashMap<K, (T, u64, u64)>,
+    by_use: BTreeMap<u64, K>,
+    bytes: u64,
+    now: u64,
+}
+
+impl<K: Clone + Eq + Hash, T> LruEntries<K, T> {
+    pub fn new() -> LruEntries<K, T> {
+        LruEntries {
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            bytes: 0,
+            now: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the total size of the entries.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns the entry for `key`, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&T> {
+        let now = self.tick();
+        let (ref value, _, ref mut last_used) = *self.entries.get_mut(key)?;
+        let _ = self.by_use.remove(last_used);
+        let _ = self.by_use.insert(now, key.clone());
+        *last_used = now;
+        Some(value)
+    }
+
+    /// Inserts or replaces the entry for `key`, which counts as `size` bytes, as the most
+    /// recently used.
+    pub fn insert(&mut self, key: K, value: T, size: u64) {
+        let now = self.tick();
+        self.bytes += size;
+        if let Some((_, old_size, last_used)) = self.entries.insert(key.clone(), (value, size, now))
+        {
+            self.bytes -= old_size;
+            let _ = self.by_use.remove(&last_used);
+        }
+        let _ = self.by_use.insert(now, key);
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        let (value, size, last_used) = self.entries.remove(key)?;
+        let _ = self.by_use.remove(&last_used);
+        self.bytes -= size;
+        Some(value)
+    }
+
+    /// Removes the least recently used entry.
+    pub fn remove_lru(&mut self) -> Option<(K, T)> {
+        let key = self.by_use.values().next()?.clone();
+        self.remove(&key).map(|value| (key, value))
+    }
+
+    /// Removes the entries for which `f` returns true.
+    pub fn remove_matching<F: Fn(&K, &T) -> bool>(&mut self, f: F) {
+        let keys: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|&(key, &(ref value, _, _))| f(key, value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            let _ = self.remove(&key);
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.now += 1;
+        self.now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Action, EntryActions, PermissionSet, User};
+    use crate::id::FullId;
+    use crate::messages::MDataNotification;
+    use crate::types::MessageId;
+    use rand;
+    use std::collections::BTreeSet;
+
+    fn get(cache: &LruCache, data: &ImmutableData) -> Option<ImmutableData> {
+        let request = Request::GetIData {
+            name: *data.name(),
+            msg_id: MessageId::new(),
+        };
+        match cache.get(&request) {
+            Some(Response::GetIData { res: Ok(data), .. }) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn put(cache: &LruCache, data: &ImmutableData) {
+        cache.put(Response::GetIData {
+            res: Ok(data.clone()),
+            msg_id: MessageId::new(),
+        });
+    }
+
+    #[test]
+    fn limits() {
+        let data: Vec<_> = (0..4u8).map(|i| ImmutableData::new(vec![i; 10])).collect();
+
+        // At most two entries: using the first data makes the second one the least recent.
+        let cache = LruCache::new(1000, 2);
+        put(&cache, &data[0]);
+        put(&cache, &data[1]);
+        assert_eq!(get(&cache, &data[0]), Some(data[0].clone()));
+        put(&cache, &data[2]);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(get(&cache, &data[1]), None);
+        assert_eq!(get(&cache, &data[2]), Some(data[2].clone()));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (2, 1, 1));
+
+        // At most 25 bytes: data too large for the whole budget isn't cached at all.
+        let cache = LruCache::new(25, 10);
+        put(&cache, &ImmutableData::new(vec![0; 26]));
+        assert!(cache.is_empty());
+        for data in &data {
+            put(&cache, data);
+        }
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.bytes(), 20);
+        assert_eq!(cache.stats().evictions(), 2);
+        assert_eq!(get(&cache, &data[3]), Some(data[3].clone()));
+    }
+
+    fn value_request(data: &MutableData, key: &[u8], allow_cached: bool) -> Request {
+        Request::GetMDataValue {
+            name: *data.name(),
+            tag: data.tag(),
+            key: key.to_vec(),
+            msg_id: MessageId::new(),
+            allow_cached,
+        }
+    }
+
+    fn shell_request(data: &MutableData) -> Request {
+        Request::GetMDataShell {
+            name: *data.name(),
+            tag: data.tag(),
+            msg_id: MessageId::new(),
+            allow_cached: true,
+        }
+    }
+
+    fn put_mdata(cache: &LruCache, data: &MutableData, key: &[u8]) {
+        let request = shell_request(data);
+        cache.put_response_to(
+            &request,
+            Response::GetMDataShell {
+                res: Ok(data.shell()),
+                msg_id: *request.message_id(),
+            },
+        );
+        let request = value_request(data, key, true);
+        let value = unwrap!(data.get(key)).clone();
+        cache.put_response_to(
+            &request,
+            Response::GetMDataValue {
+                res: Ok(value),
+                msg_id: *request.message_id(),
+            },
+        );
+    }
+
+    fn get_value(cache: &LruCache, data: &MutableData, key: &[u8]) -> Option<Value> {
+        match cache.get(&value_request(data, key, true)) {
+            Some(Response::GetMDataValue { res: Ok(value), .. }) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn get_data_version(cache: &LruCache, data: &MutableData) -> Option<u64> {
+        match cache.get(&shell_request(data)) {
+            Some(Response::GetMDataShell { res: Ok(shell), .. }) => Some(shell.data_version()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn mdata_versions() {
+        let owners: BTreeSet<_> = vec![*FullId::new().public_id().signing_public_key()]
+            .into_iter()
+            .collect();
+        let mut data = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+        let actions = EntryActions::new().ins(b"a".to_vec(), b"1".to_vec(), 0);
+        data.mutate_entries_without_validation(actions.into());
+        let old_data = data.clone();
+        let cache = LruCache::new(1000, 10);
+        put_mdata(&cache, &data, b"a");
+        assert_eq!(get_value(&cache, &data, b"a"), data.get(b"a").cloned());
+        assert_eq!(get_data_version(&cache, &data), Some(1));
+
+        // Requests which don't opt in are never answered from the cache.
+        assert!(cache.get(&value_request(&data, b"a", false)).is_none());
+
+        // A newer shell drops the values cached with an older one, and an older shell doesn't
+        // replace the newer one.
+        let actions = EntryActions::new().ins(b"b".to_vec(), b"2".to_vec(), 0);
+        data.mutate_entries_without_validation(actions.into());
+        put_mdata(&cache, &data, b"b");
+        assert_eq!(get_value(&cache, &data, b"a"), None);
+        assert_eq!(get_value(&cache, &data, b"b"), data.get(b"b").cloned());
+        put_mdata(&cache, &old_data, b"a");
+        assert_eq!(get_data_version(&cache, &data), Some(2));
+
+        // Change notifications drop the responses reflecting older versions, mutations all of
+        // them. Values may be older than the cached shell, so any notification drops them.
+        cache.invalidate_mdata(data.name(), data.tag(), Some(2));
+        assert_eq!(get_data_version(&cache, &data), Some(2));
+        assert_eq!(get_value(&cache, &data, b"b"), None);
+        put_mdata(&cache, &data, b"b");
+        cache.invalidate_mdata(data.name(), data.tag(), Some(3));
+        assert_eq!(get_data_version(&cache, &data), None);
+        assert_eq!(get_value(&cache, &data, b"b"), None);
+        put_mdata(&cache, &data, b"b");
+        cache.invalidate_mdata(data.name(), data.tag(), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn mdata_permission_changes() {
+        let owner = *FullId::new().public_id().signing_public_key();
+        let owners: BTreeSet<_> = vec![owner].into_iter().collect();
+        let mut data = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+        let actions = EntryActions::new().ins(b"a".to_vec(), b"1".to_vec(), 0);
+        data.mutate_entries_without_validation(actions.into());
+        let cache = LruCache::new(1000, 10);
+        put_mdata(&cache, &data, b"a");
+
+        // Changing only the permissions bumps the data version, so the notification, which has
+        // no changed entries, still drops the shell with the old permissions.
+        let permissions = PermissionSet::new().allow(Action::Insert);
+        assert!(data.set_user_permissions_without_validation(User::Anyone, permissions, 1));
+        let notification = MDataNotification::new(&data, BTreeSet::new());
+        assert_eq!(notification.stale_below(), 2);
+        cache.invalidate_mdata(data.name(), data.tag(), Some(notification.stale_below()));
+        assert_eq!(get_data_version(&cache, &data), None);
+
+        put_mdata(&cache, &data, b"a");
+        assert_eq!(get_data_version(&cache, &data), Some(2));
+    }
+
+    #[test]
+    fn mdata_ttl() {
+        let owners: BTreeSet<_> = vec![*FullId::new().public_id().signing_public_key()]
+            .into_iter()
+            .collect();
+        let mut data = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+        let actions = EntryActions::new().ins(b"a".to_vec(), b"1".to_vec(), 0);
+        data.mutate_entries_without_validation(actions.into());
+        let idata = ImmutableData::new(vec![1; 10]);
+
+        // MutableData expires, while ImmutableData, which can't change, is kept.
+        let cache = LruCache::new(1000, 10).mdata_ttl(Duration::from_millis(100));
+        put_mdata(&cache, &data, b"a");
+        put(&cache, &idata);
+        assert_eq!(get_data_version(&cache, &data), Some(1));
+        sleep(100);
+        assert_eq!(get_data_version(&cache, &data), None);
+        assert_eq!(get_value(&cache, &data, b"a"), None);
+        assert_eq!(get(&cache, &idata), Some(idata));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[cfg(feature = "use-mock-crust")]
+    fn sleep(time: u64) {
+        use fake_clock::FakeClock;
+        FakeClock::advance_time(time);
+    }
+
+    #[cfg(not(feature = "use-mock-crust"))]
+    fn sleep(time: u64) {
+        use std::thread;
+        thread::sleep(Duration::from_millis(time));
+    }
+}
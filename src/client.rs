@@ -6,15 +6,21 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::account::AccountKeys;
 use crate::action::Action;
-use crate::cache::NullCache;
+use crate::cache::{Cache, NullCache};
 use crate::config_handler::{self, Config};
-use crate::data::{EntryAction, ImmutableData, MutableData, PermissionSet, User};
+use crate::data::{
+    AppendableData, EntriesRange, EntryAction, FileDownload, FileUpload, ImmutableData, MutableData,
+    PermissionSet, UnpubImmutableData, User,
+};
+use crate::dns;
 use crate::error::{InterfaceError, RoutingError};
 use crate::event::Event;
 #[cfg(feature = "use-mock-crust")]
 use crate::event_stream::{EventStepper, EventStream};
 use crate::id::{FullId, PublicId};
+use crate::invitation;
 use crate::messages::{Request, CLIENT_GET_PRIORITY, DEFAULT_PRIORITY};
 use crate::outbox::{EventBox, EventBuf};
 use crate::routing_table::Authority;
@@ -23,7 +29,10 @@ use crate::state_machine::{State, StateMachine};
 use crate::states::{Bootstrapping, BootstrappingTargetState};
 use crate::types::{MessageId, RoutingActionSender};
 use crate::xor_name::XorName;
-use crate::{BootstrapConfig, MIN_SECTION_SIZE};
+use crate::{
+    BootstrapConfig, ACC_LOGIN_ENTRY_KEY, MIN_SECTION_SIZE, TYPE_TAG_DNS_PACKET,
+    TYPE_TAG_SESSION_PACKET,
+};
 #[cfg(not(feature = "use-mock-crust"))]
 use crust::read_config_file as read_bootstrap_config_file;
 #[cfg(not(feature = "use-mock-crust"))]
@@ -31,6 +40,7 @@ use maidsafe_utilities::thread::{self, Joiner};
 #[cfg(not(feature = "use-mock-crust"))]
 use rust_sodium;
 use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
 use std::sync::mpsc::{channel, Receiver, Sender};
 #[cfg(feature = "use-mock-crust")]
 use std::sync::mpsc::{RecvError, TryRecvError};
@@ -63,6 +73,7 @@ impl Client {
         bootstrap_config: Option<BootstrapConfig>,
         config: Option<Config>,
         msg_expiry_dur: Duration,
+        cache: Box<Cache>,
     ) -> (RoutingActionSender, StateMachine) {
         let full_id = keys.unwrap_or_else(FullId::new);
         let pub_id = *full_id.public_id();
@@ -74,7 +85,7 @@ impl Client {
             move |action_sender, crust_service, timer, _outbox2| {
                 Bootstrapping::new(
                     action_sender,
-                    Box::new(NullCache),
+                    cache,
                     BootstrappingTargetState::Client { msg_expiry_dur },
                     crust_service,
                     full_id,
@@ -99,6 +110,48 @@ impl Client {
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
+    /// Transfers `amount` of credit from this client's account to the account `to`. The transfer
+    /// is applied at most once for a given `msg_id`, so the request can safely be retried with
+    /// the same one.
+    pub fn transfer_credit(
+        &mut self,
+        dst: Authority<XorName>,
+        to: XorName,
+        amount: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::TransferCredit { to, amount, msg_id };
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Gets the current balance of this client's account from MaidManager
+    pub fn get_balance(
+        &mut self,
+        dst: Authority<XorName>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetBalance(msg_id);
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Gets a page of up to `limit` credit transfers in and out of this client's account from
+    /// MaidManager, starting at the sequence number `start`. A limit of zero means no limit. The
+    /// response carries the sequence number the next page starts at, if there is one.
+    pub fn get_balance_history(
+        &mut self,
+        dst: Authority<XorName>,
+        start: u64,
+        limit: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetBalanceHistory {
+            start,
+            limit,
+            msg_id,
+        };
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
     /// Puts ImmutableData to the network
     pub fn put_idata(
         &mut self,
@@ -123,6 +176,132 @@ impl Client {
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
+    /// Fetches `len` bytes of ImmutableData starting at `offset`. The response carries a Merkle
+    /// proof which can be checked against the root returned by `ImmutableData::merkle_root`.
+    pub fn get_idata_range(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        offset: u64,
+        len: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetIDataRange {
+            name,
+            offset,
+            len,
+            msg_id,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Puts unpublished ImmutableData to the network
+    pub fn put_unpub_idata(
+        &mut self,
+        dst: Authority<XorName>,
+        data: UnpubImmutableData,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::PutUnpubIData { data, msg_id };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Fetches unpublished ImmutableData from the network by the given name.
+    pub fn get_unpub_idata(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetUnpubIData { name, msg_id };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Deletes unpublished ImmutableData from the network. `signature` is the owner's signature
+    /// of `UnpubImmutableData::deletion_signing_data(&name, nonce)`, where `nonce` is the one of
+    /// the stored data.
+    pub fn delete_unpub_idata(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        signature: sign::Signature,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::DeleteUnpubIData {
+            name,
+            signature,
+            msg_id,
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Splits `content` into `ImmutableData` chunks and starts putting them to `dst`, a batch at
+    /// a time. The data map describing the chunks is put last. `FileUpload::name` identifies the
+    /// content.
+    ///
+    /// Pass every successful `PutIData` response to `continue_put_file` to put the next batch,
+    /// until `FileUpload::is_done`. On a failed response the upload can be started again, as
+    /// chunks which are already stored are put again without harm.
+    pub fn put_file(
+        &mut self,
+        dst: Authority<XorName>,
+        content: &[u8],
+    ) -> Result<FileUpload, InterfaceError> {
+        let mut upload = FileUpload::new(content);
+        self.put_next_chunks(dst, &mut upload)?;
+        Ok(upload)
+    }
+
+    /// Records the successful `PutIData` response `msg_id` of `upload` and puts the next chunks.
+    pub fn continue_put_file(
+        &mut self,
+        dst: Authority<XorName>,
+        upload: &mut FileUpload,
+        msg_id: &MessageId,
+    ) -> Result<(), InterfaceError> {
+        if upload.chunk_stored(msg_id) {
+            self.put_next_chunks(dst, upload)?;
+        }
+        Ok(())
+    }
+
+    /// Starts getting the content whose data map is stored under `name`, fetching each chunk from
+    /// the section managing it, a batch at a time. Nested data maps are resolved along the way.
+    ///
+    /// Pass the chunk of every successful `GetIData` response to `FileDownload::chunk_received`,
+    /// which returns the content once complete and checks the integrity of every chunk. Pass the
+    /// message id of every failed `GetIData` response, or of a request which timed out, to
+    /// `FileDownload::chunk_failed`. Until the content is complete, call `continue_get_file`
+    /// after each response to request the next chunks, including the failed ones again.
+    pub fn get_file(&mut self, name: XorName) -> Result<FileDownload, InterfaceError> {
+        let mut download = FileDownload::new(name);
+        self.continue_get_file(&mut download)?;
+        Ok(download)
+    }
+
+    /// Requests the next chunks of `download`, including the ones whose requests failed.
+    pub fn continue_get_file(&mut self, download: &mut FileDownload) -> Result<(), InterfaceError> {
+        for (msg_id, name) in download.next_chunks() {
+            self.get_idata(Authority::NaeManager(name), name, msg_id)?;
+        }
+        Ok(())
+    }
+
+    fn put_next_chunks(
+        &mut self,
+        dst: Authority<XorName>,
+        upload: &mut FileUpload,
+    ) -> Result<(), InterfaceError> {
+        for (msg_id, data) in upload.next_chunks() {
+            self.put_idata(dst, data, msg_id)?;
+        }
+        Ok(())
+    }
+
     /// Fetches a latest version number of the provided MutableData
     pub fn get_mdata_version(
         &mut self,
@@ -144,7 +323,31 @@ impl Client {
         tag: u64,
         msg_id: MessageId,
     ) -> Result<(), InterfaceError> {
-        let request = Request::GetMDataShell { name, tag, msg_id };
+        let request = Request::GetMDataShell {
+            name,
+            tag,
+            msg_id,
+            allow_cached: false,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches the shell of the provided MutableData, which may be served from the caches of the
+    /// nodes relaying the request. The shell can then be older than the one held by `dst`.
+    pub fn get_cached_mdata_shell(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetMDataShell {
+            name,
+            tag,
+            msg_id,
+            allow_cached: true,
+        };
 
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
@@ -162,49 +365,70 @@ impl Client {
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
-    /// Fetches a list of entries (keys + values) of the provided MutableData
+    /// Fetches a page of entries (keys + values) of the provided MutableData. The response carries
+    /// the key the next page starts at, if there is one.
     /// Note: response to this request is unlikely to accumulate during churn.
     pub fn list_mdata_entries(
         &mut self,
         dst: Authority<XorName>,
         name: XorName,
         tag: u64,
+        range: EntriesRange,
         msg_id: MessageId,
     ) -> Result<(), InterfaceError> {
-        let request = Request::ListMDataEntries { name, tag, msg_id };
+        let request = Request::ListMDataEntries {
+            name,
+            tag,
+            range,
+            msg_id,
+        };
 
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
-    /// Fetches a list of keys of the provided MutableData
+    /// Fetches a page of keys of the provided MutableData. The response carries
+    /// the key the next page starts at, if there is one.
     /// Note: response to this request is unlikely to accumulate during churn.
     pub fn list_mdata_keys(
         &mut self,
         dst: Authority<XorName>,
         name: XorName,
         tag: u64,
+        range: EntriesRange,
         msg_id: MessageId,
     ) -> Result<(), InterfaceError> {
-        let request = Request::ListMDataKeys { name, tag, msg_id };
+        let request = Request::ListMDataKeys {
+            name,
+            tag,
+            range,
+            msg_id,
+        };
 
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
-    /// Fetches a list of values of the provided MutableData
+    /// Fetches a page of values of the provided MutableData. The response carries
+    /// the key the next page starts at, if there is one.
     /// Note: response to this request is unlikely to accumulate during churn.
     pub fn list_mdata_values(
         &mut self,
         dst: Authority<XorName>,
         name: XorName,
         tag: u64,
+        range: EntriesRange,
         msg_id: MessageId,
     ) -> Result<(), InterfaceError> {
-        let request = Request::ListMDataValues { name, tag, msg_id };
+        let request = Request::ListMDataValues {
+            name,
+            tag,
+            range,
+            msg_id,
+        };
 
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
-    /// Fetches a single value from the provided MutableData by the given key
+    /// Fetches a single value from the provided MutableData by the given key.
     pub fn get_mdata_value(
         &mut self,
         dst: Authority<XorName>,
@@ -218,6 +442,68 @@ impl Client {
             tag,
             key,
             msg_id,
+            allow_cached: false,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches a single value from the provided MutableData by the given key, which may be served
+    /// from the caches of the nodes relaying the request. The value can then be older than the
+    /// one held by `dst`.
+    pub fn get_cached_mdata_value(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        key: Vec<u8>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetMDataValue {
+            name,
+            tag,
+            key,
+            msg_id,
+            allow_cached: true,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches the previous values of a single entry of the provided MutableData
+    pub fn get_mdata_entry_history(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        key: Vec<u8>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetMDataEntryHistory {
+            name,
+            tag,
+            key,
+            msg_id,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches the entries of the provided MutableData inserted, updated or deleted after the
+    /// data version `since`
+    pub fn get_mdata_changes_since(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        since: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetMDataChangesSince {
+            name,
+            tag,
+            since,
+            msg_id,
         };
 
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
@@ -261,7 +547,27 @@ impl Client {
         self.send_request(dst, request, DEFAULT_PRIORITY)
     }
 
-    /// Lists all permissions for a given `MutableData`
+    /// Updates entries of several `MutableData` in bulk, all or nothing. All the `MutableData`
+    /// must be managed by the section `dst` refers to, otherwise the batch is refused with
+    /// `ClientError::InvalidOperation`.
+    pub fn mutate_mdata_batch(
+        &mut self,
+        dst: Authority<XorName>,
+        mutations: BTreeMap<(XorName, u64), BTreeMap<Vec<u8>, EntryAction>>,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::MutateMDataBatch {
+            mutations,
+            msg_id,
+            requester,
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Lists all permissions for a given `MutableData`. The response also reports the users
+    /// whose permissions have expired.
     pub fn list_mdata_permissions(
         &mut self,
         dst: Authority<XorName>,
@@ -293,7 +599,8 @@ impl Client {
         self.send_request(dst, request, CLIENT_GET_PRIORITY)
     }
 
-    /// Updates or inserts a permission set for a given user
+    /// Updates or inserts a permission set for a given user. If the data needs more than one
+    /// owner signature, `set_mdata_user_permissions_by_owners` has to be used instead.
     #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn set_mdata_user_permissions(
         &mut self,
@@ -305,6 +612,35 @@ impl Client {
         version: u64,
         msg_id: MessageId,
         requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        self.set_mdata_user_permissions_by_owners(
+            dst,
+            name,
+            tag,
+            user,
+            permissions,
+            version,
+            msg_id,
+            requester,
+            BTreeMap::new(),
+        )
+    }
+
+    /// Updates or inserts a permission set for a given user on behalf of the owners.
+    /// `signatures` must contain at least the owners threshold of owner signatures of the
+    /// corresponding `OwnerAction`.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn set_mdata_user_permissions_by_owners(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        user: User,
+        permissions: PermissionSet,
+        version: u64,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     ) -> Result<(), InterfaceError> {
         let request = Request::SetMDataUserPermissions {
             name,
@@ -314,12 +650,14 @@ impl Client {
             version,
             msg_id,
             requester,
+            signatures,
         };
 
         self.send_request(dst, request, DEFAULT_PRIORITY)
     }
 
-    /// Deletes a permission set for a given user
+    /// Deletes a permission set for a given user. If the data needs more than one owner
+    /// signature, `del_mdata_user_permissions_by_owners` has to be used instead.
     #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn del_mdata_user_permissions(
         &mut self,
@@ -330,6 +668,33 @@ impl Client {
         version: u64,
         msg_id: MessageId,
         requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        self.del_mdata_user_permissions_by_owners(
+            dst,
+            name,
+            tag,
+            user,
+            version,
+            msg_id,
+            requester,
+            BTreeMap::new(),
+        )
+    }
+
+    /// Deletes a permission set for a given user on behalf of the owners. `signatures` must
+    /// contain at least the owners threshold of owner signatures of the corresponding
+    /// `OwnerAction`.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn del_mdata_user_permissions_by_owners(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        user: User,
+        version: u64,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     ) -> Result<(), InterfaceError> {
         let request = Request::DelMDataUserPermissions {
             name,
@@ -338,6 +703,7 @@ impl Client {
             version,
             msg_id,
             requester,
+            signatures,
         };
 
         self.send_request(dst, request, DEFAULT_PRIORITY)
@@ -352,13 +718,263 @@ impl Client {
         new_owners: BTreeSet<sign::PublicKey>,
         version: u64,
         msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        self.change_mdata_owners(
+            dst,
+            name,
+            tag,
+            new_owners,
+            1,
+            version,
+            msg_id,
+            BTreeMap::new(),
+        )
+    }
+
+    /// Replaces the owners and the owners threshold. `signatures` must contain at least the
+    /// current owners threshold of owner signatures of the corresponding `OwnerAction`.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn change_mdata_owners(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        new_owners: BTreeSet<sign::PublicKey>,
+        owners_threshold: u64,
+        version: u64,
+        msg_id: MessageId,
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     ) -> Result<(), InterfaceError> {
         let request = Request::ChangeMDataOwner {
             name,
             tag,
             new_owners,
+            owners_threshold,
             version,
             msg_id,
+            signatures,
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Subscribes to mutations of the provided MutableData. Until unsubscribed, every mutation is
+    /// reported as an `Event::Response` carrying a `Response::MDataChanged`. The subscription is
+    /// tied to the current proxy, so it must be renewed after reconnecting.
+    pub fn subscribe_mdata(
+        &mut self,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::SubscribeMData { name, tag, msg_id };
+
+        self.send_request(Authority::NaeManager(name), request, DEFAULT_PRIORITY)
+    }
+
+    /// Cancels a subscription made with `subscribe_mdata`.
+    pub fn unsubscribe_mdata(
+        &mut self,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::UnsubscribeMData { name, tag, msg_id };
+
+        self.send_request(Authority::NaeManager(name), request, DEFAULT_PRIORITY)
+    }
+
+    /// Registers `long_name` for `owner` by putting its DNS packet. Returns the name of the packet.
+    /// The request fails with `ClientError::DataExists` if `long_name` has already been taken.
+    pub fn register_name(
+        &mut self,
+        dst: Authority<XorName>,
+        long_name: &str,
+        owner: sign::PublicKey,
+        msg_id: MessageId,
+    ) -> Result<XorName, InterfaceError> {
+        let data = dns::new_packet(long_name, owner);
+        let name = *data.name();
+        self.put_mdata(dst, data, msg_id, owner)?;
+        Ok(name)
+    }
+
+    /// Fetches the entry of `service` in the DNS packet of `long_name`. The target it resolves to
+    /// can be decoded from the response with `dns::decode_target`.
+    pub fn resolve_name(
+        &mut self,
+        dst: Authority<XorName>,
+        long_name: &str,
+        service: &str,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let name = dns::packet_name(long_name);
+        let key = service.as_bytes().to_vec();
+        self.get_mdata_value(dst, name, TYPE_TAG_DNS_PACKET, key, msg_id)
+    }
+
+    /// Makes `service` of `long_name` resolve to `target`. See `dns::add_service_actions` for the
+    /// meaning of `entry_version`.
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn add_service(
+        &mut self,
+        dst: Authority<XorName>,
+        long_name: &str,
+        service: &str,
+        target: &XorName,
+        entry_version: u64,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let actions = dns::add_service_actions(service, target, entry_version);
+        let name = dns::packet_name(long_name);
+        self.mutate_mdata_entries(dst, name, TYPE_TAG_DNS_PACKET, actions, msg_id, requester)
+    }
+
+    /// Removes `service` from `long_name`. `entry_version` must be the current version of its
+    /// entry plus one.
+    pub fn remove_service(
+        &mut self,
+        dst: Authority<XorName>,
+        long_name: &str,
+        service: &str,
+        entry_version: u64,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let actions = dns::remove_service_actions(service, entry_version);
+        let name = dns::packet_name(long_name);
+        self.mutate_mdata_entries(dst, name, TYPE_TAG_DNS_PACKET, actions, msg_id, requester)
+    }
+
+    /// Transfers the ownership of `long_name` to `new_owner`. `version` must be the current
+    /// version of the DNS packet plus one.
+    pub fn transfer_name(
+        &mut self,
+        dst: Authority<XorName>,
+        long_name: &str,
+        new_owner: sign::PublicKey,
+        version: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let name = dns::packet_name(long_name);
+        let new_owners = iter::once(new_owner).collect();
+        self.change_mdata_owner(dst, name, TYPE_TAG_DNS_PACKET, new_owners, version, msg_id)
+    }
+
+    /// Registers a new account by putting the session packet derived from `keys`, holding the
+    /// encrypted `account` data. A failure can be mapped to the account errors with
+    /// `account::registration_error`.
+    pub fn create_account(
+        &mut self,
+        dst: Authority<XorName>,
+        keys: &AccountKeys,
+        account: &[u8],
+        invitation: Option<String>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let data = keys.new_session_packet(account, invitation);
+        self.put_mdata(dst, data, msg_id, *keys.public_key())
+    }
+
+    /// Fetches the login entry of the session packet derived from `keys`. The account data can be
+    /// decrypted from the response with `AccountKeys::decrypt_account`, and a failure mapped to
+    /// the account errors with `account::login_error`.
+    pub fn login(&mut self, keys: &AccountKeys, msg_id: MessageId) -> Result<(), InterfaceError> {
+        let name = *keys.name();
+        self.get_mdata_value(
+            Authority::NaeManager(name),
+            name,
+            TYPE_TAG_SESSION_PACKET,
+            ACC_LOGIN_ENTRY_KEY.to_vec(),
+            msg_id,
+        )
+    }
+
+    /// Creates a new `AppendableData` in the network
+    pub fn put_adata(
+        &mut self,
+        dst: Authority<XorName>,
+        data: AppendableData,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::PutAData {
+            data,
+            msg_id,
+            requester,
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Fetches the entire AppendableData
+    pub fn get_adata(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetAData { name, tag, msg_id };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches the index the next entry of the provided AppendableData will be appended at
+    pub fn get_adata_index(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetADataIndex { name, tag, msg_id };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Fetches the entries of the provided AppendableData in the index range `[start, end)`
+    /// Note: response to this request is unlikely to accumulate during churn.
+    pub fn get_adata_range(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        start: u64,
+        end: u64,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::GetADataRange {
+            name,
+            tag,
+            start,
+            end,
+            msg_id,
+        };
+
+        self.send_request(dst, request, CLIENT_GET_PRIORITY)
+    }
+
+    /// Appends entries to `AppendableData`, starting at the given (current) index
+    #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+    pub fn append_to_adata(
+        &mut self,
+        dst: Authority<XorName>,
+        name: XorName,
+        tag: u64,
+        entries: Vec<Vec<u8>>,
+        index: u64,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let request = Request::AppendToAData {
+            name,
+            tag,
+            entries,
+            index,
+            msg_id,
+            requester,
         };
 
         self.send_request(dst, request, DEFAULT_PRIORITY)
@@ -407,6 +1023,46 @@ impl Client {
 
         self.send_request(dst, request, DEFAULT_PRIORITY)
     }
+
+    /// Issues the invitation `code`. The request is sent to the section responsible for the code,
+    /// which refuses it unless `requester` is authorised to issue invitations.
+    pub fn issue_invitation(
+        &mut self,
+        code: String,
+        msg_id: MessageId,
+        requester: sign::PublicKey,
+    ) -> Result<(), InterfaceError> {
+        let dst = Authority::ClientManager(invitation::invitation_name(&code));
+        let request = Request::IssueInvitation {
+            code,
+            msg_id,
+            requester,
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
+
+    /// Claims the invitation `code` for the account of `keys`, failing with
+    /// `ClientError::InvalidInvitation` or `ClientError::InvitationAlreadyClaimed` if the code
+    /// can't be claimed. This doesn't register the account: once the claim succeeds, call
+    /// `create_account` with the same code. Claiming the code again for the same `keys` succeeds,
+    /// so both steps can be retried if registering fails.
+    pub fn claim_invitation(
+        &mut self,
+        keys: &AccountKeys,
+        account: &[u8],
+        code: String,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let dst = Authority::ClientManager(invitation::invitation_name(&code));
+        let request = Request::ClaimInvitation {
+            data: keys.new_session_packet(account, Some(code)),
+            msg_id,
+            requester: *keys.public_key(),
+        };
+
+        self.send_request(dst, request, DEFAULT_PRIORITY)
+    }
 }
 
 #[cfg(not(feature = "use-mock-crust"))]
@@ -427,6 +1083,24 @@ impl Client {
         keys: Option<FullId>,
         bootstrap_config: Option<BootstrapConfig>,
         msg_expiry_dur: Duration,
+    ) -> Result<Client, RoutingError> {
+        let cache = Box::new(NullCache);
+        Self::with_cache(event_sender, keys, bootstrap_config, msg_expiry_dur, cache)
+    }
+
+    /// Create a new `Client` like `new`, which answers cacheable requests from `cache`.
+    ///
+    /// Cacheable requests, like `get_idata` and the MutableData reads which opt in to caching,
+    /// are answered from `cache` if it holds a response to them: the response is then delivered
+    /// as an `Event::Response` with the request's message ID, without contacting the network. The
+    /// responses received for them are put in the cache. Pass an `LruCache` and keep its `stats`
+    /// to observe the cache.
+    pub fn with_cache(
+        event_sender: Sender<Event>,
+        keys: Option<FullId>,
+        bootstrap_config: Option<BootstrapConfig>,
+        msg_expiry_dur: Duration,
+        cache: Box<Cache>,
     ) -> Result<Client, RoutingError> {
         let _ = rust_sodium::init(); // enable shared global (i.e. safe to multithread now)
 
@@ -442,6 +1116,7 @@ impl Client {
                 bootstrap_config,
                 None,
                 msg_expiry_dur,
+                cache,
             );
 
             for ev in event_buffer.take_all() {
@@ -514,6 +1189,19 @@ impl Client {
         bootstrap_config: Option<BootstrapConfig>,
         config: Config,
         msg_expiry_dur: Duration,
+    ) -> Result<Client, RoutingError> {
+        let cache = Box::new(NullCache);
+        Self::with_cache(keys, bootstrap_config, config, msg_expiry_dur, cache)
+    }
+
+    /// Create a new `Client` for testing with mock crust, which answers cacheable requests from
+    /// `cache`.
+    pub fn with_cache(
+        keys: Option<FullId>,
+        bootstrap_config: Option<BootstrapConfig>,
+        config: Config,
+        msg_expiry_dur: Duration,
+        cache: Box<Cache>,
     ) -> Result<Client, RoutingError> {
         let mut event_buffer = EventBuf::new();
         let (_, machine) = Self::make_state_machine(
@@ -522,6 +1210,7 @@ impl Client {
             bootstrap_config,
             Some(config),
             msg_expiry_dur,
+            cache,
         );
 
         let (tx, rx) = channel();
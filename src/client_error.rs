@@ -6,6 +6,7 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use crate::xor_name::XorName;
 use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
@@ -52,6 +53,21 @@ pub enum ClientError {
     /// Network error occurring at Vault level which has no bearing on clients, e.g. serialisation
     /// failure or database failure
     NetworkOther(String),
+    /// Not enough owner signatures were provided. Contains the number of signatures required.
+    InsufficientSignatures(u64),
+    /// A provided signature is invalid or was not made by an owner
+    InvalidSignature,
+    /// Some mutations of a batch failed, so none was applied. Contains the error for each
+    /// failed `MutableData`, keyed by its name and type tag.
+    InvalidBatchMutations(BTreeMap<(XorName, u64), ClientError>),
+    /// Encrypted data could not be decrypted with the given key
+    DecryptionFailed,
+    /// The content of a chunk doesn't match the name it is referred to by. Contains the name.
+    InvalidChunk(XorName),
+    /// The entries don't match the schema registered for the type tag. Contains the type tag.
+    InvalidSchema(u64),
+    /// Exceeded a limit on a number of subscribers to a MutableData
+    TooManySubscribers,
 }
 
 impl<T: Into<String>> From<T> for ClientError {
@@ -87,6 +103,25 @@ impl Display for ClientError {
             ClientError::LowBalance => write!(f, "Insufficient account balance for this operation"),
             ClientError::NetworkFull => write!(f, "Network cannot store any further data"),
             ClientError::NetworkOther(ref error) => write!(f, "Error on Vault network: {}", error),
+            ClientError::InsufficientSignatures(required) => write!(
+                f,
+                "Insufficient owner signatures, {} signatures are required",
+                required
+            ),
+            ClientError::InvalidSignature => write!(f, "Invalid owner signature"),
+            ClientError::InvalidBatchMutations(ref errors) => {
+                write!(f, "Batch mutations are invalid: {:?}", errors)
+            }
+            ClientError::DecryptionFailed => write!(f, "Failed to decrypt data"),
+            ClientError::InvalidChunk(ref name) => {
+                write!(f, "Chunk content does not match its name {}", name)
+            }
+            ClientError::InvalidSchema(tag) => {
+                write!(f, "Entries do not match the schema for type tag {}", tag)
+            }
+            ClientError::TooManySubscribers => {
+                write!(f, "Exceeded a limit on a number of subscribers")
+            }
         }
     }
 }
@@ -112,6 +147,13 @@ impl Error for ClientError {
             ClientError::LowBalance => "Low account balance",
             ClientError::NetworkFull => "Network full",
             ClientError::NetworkOther(ref error) => error,
+            ClientError::InsufficientSignatures(_) => "Insufficient signatures",
+            ClientError::InvalidSignature => "Invalid signature",
+            ClientError::InvalidBatchMutations(_) => "Invalid batch mutations",
+            ClientError::DecryptionFailed => "Decryption failed",
+            ClientError::InvalidChunk(_) => "Invalid chunk",
+            ClientError::InvalidSchema(_) => "Invalid schema",
+            ClientError::TooManySubscribers => "Too many subscribers",
         }
     }
 }
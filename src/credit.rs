@@ -0,0 +1,255 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Account balances kept by `ClientManager` sections.
+//!
+//! A client transfers credit by sending `Request::TransferCredit` to its own `ClientManager`,
+//! which debits the account and forwards the request to the `ClientManager` of the recipient,
+//! which credits it. Both sides record the transfer under the request's message identifier, so a
+//! retried or duplicated request is applied only once, as long as the transfer is still among the
+//! last `MAX_BALANCE_HISTORY_LEN` ones of the account.
+
+use crate::client_error::ClientError;
+use crate::messages::Transaction;
+use crate::types::MessageId;
+use crate::xor_name::XorName;
+use std::cmp;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Maximum number of transfers kept in the history of an account. Older ones are dropped.
+pub const MAX_BALANCE_HISTORY_LEN: usize = 1000;
+
+/// The balance of a single account, together with its latest transfers.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Balance {
+    /// Name of the account
+    name: XorName,
+    /// Current balance
+    balance: u64,
+    /// The latest transfers in and out of the account, oldest first
+    history: VecDeque<Transaction>,
+    /// Sequence number of the oldest transfer in `history`, counting from the first transfer of
+    /// the account
+    first: u64,
+    /// Sequence number of each transfer in `history`, by message identifier
+    applied: BTreeMap<MessageId, u64>,
+}
+
+impl Balance {
+    /// Creates the balance of the account `name`, holding `initial` credit.
+    pub fn new(name: XorName, initial: u64) -> Balance {
+        Balance {
+            name,
+            balance: initial,
+            history: VecDeque::new(),
+            first: 0,
+            applied: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the current balance.
+    pub fn balance(&self) -> u64 {
+        self.balance
+    }
+
+    /// Returns up to `limit` transfers in and out of the account, oldest first, starting at the
+    /// sequence number `start` or the oldest transfer kept, whichever is later. A limit of zero
+    /// means no limit. Also returns the sequence number the next page starts at, or `None` if
+    /// this is the last page.
+    pub fn history(&self, start: u64, limit: u64) -> (Vec<Transaction>, Option<u64>) {
+        let start = cmp::max(start, self.first);
+        let limit = if limit == 0 {
+            usize::max_value()
+        } else {
+            limit as usize
+        };
+        let page: Vec<_> = self
+            .history
+            .iter()
+            .skip((start - self.first) as usize)
+            .take(limit)
+            .cloned()
+            .collect();
+        let next = start + page.len() as u64;
+        if next < self.first + self.history.len() as u64 {
+            (page, Some(next))
+        } else {
+            (page, None)
+        }
+    }
+
+    /// Debits `amount` for a transfer to `to`. Fails with `ClientError::LowBalance` if the
+    /// balance is insufficient.
+    pub fn debit(
+        &mut self,
+        msg_id: MessageId,
+        to: XorName,
+        amount: u64,
+    ) -> Result<(), ClientError> {
+        if amount == 0 || to == self.name {
+            return Err(ClientError::InvalidOperation);
+        }
+        let from = self.name;
+        if self.is_applied(msg_id, from, to, amount)? {
+            return Ok(());
+        }
+        let balance = self
+            .balance
+            .checked_sub(amount)
+            .ok_or(ClientError::LowBalance)?;
+        self.record(msg_id, from, to, amount, balance);
+        Ok(())
+    }
+
+    /// Credits `amount` for a transfer from `from`.
+    pub fn credit(
+        &mut self,
+        msg_id: MessageId,
+        from: XorName,
+        amount: u64,
+    ) -> Result<(), ClientError> {
+        if amount == 0 || from == self.name {
+            return Err(ClientError::InvalidOperation);
+        }
+        let to = self.name;
+        if self.is_applied(msg_id, from, to, amount)? {
+            return Ok(());
+        }
+        let balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or(ClientError::InvalidOperation)?;
+        self.record(msg_id, from, to, amount, balance);
+        Ok(())
+    }
+
+    // Returns true if the transfer has already been applied, or an error if a different transfer
+    // was applied under the same message identifier.
+    fn is_applied(
+        &self,
+        msg_id: MessageId,
+        from: XorName,
+        to: XorName,
+        amount: u64,
+    ) -> Result<bool, ClientError> {
+        match self.applied.get(&msg_id).map(|&seq| &self.history[(seq - self.first) as usize]) {
+            None => Ok(false),
+            Some(tx) if tx.from == from && tx.to == to && tx.amount == amount => Ok(true),
+            Some(_) => Err(ClientError::InvalidOperation),
+        }
+    }
+
+    fn record(&mut self, msg_id: MessageId, from: XorName, to: XorName, amount: u64, balance: u64) {
+        let seq = self.first + self.history.len() as u64;
+        let _ = self.applied.insert(msg_id, seq);
+        self.history.push_back(Transaction {
+            msg_id,
+            from,
+            to,
+            amount,
+            balance,
+        });
+        self.balance = balance;
+
+        if self.history.len() > MAX_BALANCE_HISTORY_LEN {
+            if let Some(oldest) = self.history.pop_front() {
+                let _ = self.applied.remove(&oldest.msg_id);
+                self.first += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand;
+
+    #[test]
+    fn transfers() {
+        let (alice, bob) = (rand::random(), rand::random());
+        let mut alice_balance = Balance::new(alice, 100);
+        let mut bob_balance = Balance::new(bob, 0);
+
+        let msg_id = MessageId::new();
+        unwrap!(alice_balance.debit(msg_id, bob, 30));
+        unwrap!(bob_balance.credit(msg_id, alice, 30));
+        assert_eq!(alice_balance.balance(), 70);
+        assert_eq!(bob_balance.balance(), 30);
+
+        // Duplicates are applied only once, but can't reuse the identifier for other transfers.
+        unwrap!(alice_balance.debit(msg_id, bob, 30));
+        unwrap!(bob_balance.credit(msg_id, alice, 30));
+        assert_eq!(alice_balance.balance(), 70);
+        assert_eq!(bob_balance.balance(), 30);
+        assert_eq!(
+            alice_balance.debit(msg_id, bob, 31),
+            Err(ClientError::InvalidOperation)
+        );
+
+        assert_eq!(
+            bob_balance.debit(MessageId::new(), alice, 31),
+            Err(ClientError::LowBalance)
+        );
+        assert_eq!(
+            bob_balance.debit(MessageId::new(), bob, 1),
+            Err(ClientError::InvalidOperation)
+        );
+        assert_eq!(
+            bob_balance.debit(MessageId::new(), alice, 0),
+            Err(ClientError::InvalidOperation)
+        );
+        assert_eq!(
+            bob_balance.credit(MessageId::new(), alice, 0),
+            Err(ClientError::InvalidOperation)
+        );
+
+        let msg_id2 = MessageId::new();
+        unwrap!(bob_balance.debit(msg_id2, alice, 10));
+        unwrap!(alice_balance.credit(msg_id2, bob, 10));
+
+        let (history, next) = alice_balance.history(0, 0);
+        let history: Vec<_> = history
+            .iter()
+            .map(|tx| (tx.msg_id, tx.from, tx.amount, tx.balance))
+            .collect();
+        assert_eq!(history, vec![(msg_id, alice, 30, 70), (msg_id2, bob, 10, 80)]);
+        assert_eq!(next, None);
+        assert_eq!(bob_balance.history(0, 0).0.len(), 2);
+        assert_eq!(bob_balance.balance(), 20);
+
+        let (page, next) = alice_balance.history(0, 1);
+        assert_eq!(page.iter().map(|tx| tx.msg_id).collect::<Vec<_>>(), vec![msg_id]);
+        assert_eq!(next, Some(1));
+        let (page, next) = alice_balance.history(1, 1);
+        assert_eq!(page.iter().map(|tx| tx.msg_id).collect::<Vec<_>>(), vec![msg_id2]);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn history_limit() {
+        let (alice, bob) = (rand::random(), rand::random());
+        let mut balance = Balance::new(alice, 0);
+        let first_msg_id = MessageId::new();
+        unwrap!(balance.credit(first_msg_id, bob, 1));
+        for _ in 0..MAX_BALANCE_HISTORY_LEN {
+            unwrap!(balance.credit(MessageId::new(), bob, 1));
+        }
+
+        // The oldest transfer is dropped, and pages start at the oldest one kept.
+        let (page, next) = balance.history(0, 0);
+        assert_eq!(page.len(), MAX_BALANCE_HISTORY_LEN);
+        assert_eq!(next, None);
+        assert!(page.iter().all(|tx| tx.msg_id != first_msg_id));
+        let (page, next) = balance.history(0, 10);
+        assert_eq!(page.len(), 10);
+        assert_eq!(next, Some(11));
+        assert!(balance.applied.len() <= MAX_BALANCE_HISTORY_LEN);
+        assert_eq!(balance.balance(), MAX_BALANCE_HISTORY_LEN as u64 + 1);
+    }
+}
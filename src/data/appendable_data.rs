@@ -0,0 +1,427 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::format::{self, Versioned};
+use super::{Action, PermissionSet, User};
+use crate::client_error::ClientError;
+use crate::rust_sodium::crypto::sign::PublicKey;
+use crate::xor_name::XorName;
+use maidsafe_utilities::serialisation;
+use serde::de::SeqAccess;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Formatter};
+
+/// Maximum allowed size for `AppendableData` (1 MiB)
+pub const MAX_APPENDABLE_DATA_SIZE_IN_BYTES: u64 = 1024 * 1024;
+
+/// Maximum allowed entries in `AppendableData`
+pub const MAX_APPENDABLE_DATA_ENTRIES: u64 = 1000;
+
+/// Format version `AppendableData` is serialised with.
+pub const APPENDABLE_DATA_FORMAT_VERSION: u16 = 1;
+
+/// Appendable data.
+///
+/// An append-only log of entries. Every entry is written exactly once at the next free index and
+/// can never be updated or deleted afterwards.
+///
+/// Serialised as `APPENDABLE_DATA_FORMAT_VERSION` followed by the layout of that version.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone)]
+pub struct AppendableData {
+    /// Network address
+    name: XorName,
+    /// Type tag
+    tag: u64,
+    // ---- owner and vault access only ----
+    /// Appended entries. The position of an entry is its index.
+    data: Vec<Vec<u8>>,
+    /// Maps an application key to a list of allowed or forbidden actions
+    permissions: BTreeMap<User, PermissionSet>,
+    /// Version should be increased for every change in AppendableData fields
+    /// except for data
+    version: u64,
+    /// Contains a set of owners, each of which is allowed to append.
+    owners: BTreeSet<PublicKey>,
+}
+
+impl AppendableData {
+    /// Creates a new AppendableData
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        name: XorName,
+        tag: u64,
+        permissions: BTreeMap<User, PermissionSet>,
+        data: Vec<Vec<u8>>,
+        owners: BTreeSet<PublicKey>,
+    ) -> Result<AppendableData, ClientError> {
+        let ad = AppendableData {
+            name,
+            tag,
+            data,
+            permissions,
+            version: 0,
+            owners,
+        };
+
+        ad.validate()?;
+        Ok(ad)
+    }
+
+    /// Validate this data.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        if self.data.len() > MAX_APPENDABLE_DATA_ENTRIES as usize {
+            return Err(ClientError::TooManyEntries);
+        }
+
+        if self.serialised_size() > MAX_APPENDABLE_DATA_SIZE_IN_BYTES {
+            return Err(ClientError::DataTooLarge);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the shell of this data. Shell contains the same fields as the data itself,
+    /// except the entries.
+    pub fn shell(&self) -> AppendableData {
+        AppendableData {
+            name: self.name,
+            tag: self.tag,
+            data: Vec::new(),
+            permissions: self.permissions.clone(),
+            version: self.version,
+            owners: self.owners.clone(),
+        }
+    }
+
+    /// Returns the name.
+    pub fn name(&self) -> &XorName {
+        &self.name
+    }
+
+    /// Returns the type tag of this AppendableData
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Returns the current version of this AppendableData
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the owner keys
+    pub fn owners(&self) -> &BTreeSet<PublicKey> {
+        &self.owners
+    }
+
+    /// Returns the index the next appended entry will be stored at.
+    pub fn index(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// Returns an entry by the given index
+    pub fn get(&self, index: u64) -> Option<&Vec<u8>> {
+        self.data.get(index as usize)
+    }
+
+    /// Returns all entries
+    pub fn entries(&self) -> &[Vec<u8>] {
+        &self.data
+    }
+
+    /// Returns the entries in the half-open range `[start, end)`. `end` is clamped to the current
+    /// index, but `start` must not lie past it.
+    pub fn range(&self, start: u64, end: u64) -> Result<Vec<Vec<u8>>, ClientError> {
+        if start > self.index() {
+            return Err(ClientError::NoSuchEntry);
+        }
+        let end = end.min(self.index()).max(start);
+        Ok(self.data[start as usize..end as usize].to_vec())
+    }
+
+    /// Appends `entries` to this data. `index` must be equal to the current index, so that
+    /// concurrent appends can't silently overwrite each other.
+    pub fn append(
+        &mut self,
+        entries: Vec<Vec<u8>>,
+        index: u64,
+        requester: PublicKey,
+    ) -> Result<(), ClientError> {
+        if !self.is_action_allowed(requester, Action::Append) {
+            return Err(ClientError::AccessDenied);
+        }
+        if index != self.index() {
+            return Err(ClientError::InvalidSuccessor(self.index()));
+        }
+        if self.data.len() + entries.len() > MAX_APPENDABLE_DATA_ENTRIES as usize {
+            return Err(ClientError::TooManyEntries);
+        }
+
+        let old_len = self.data.len();
+        self.data.extend(entries);
+
+        if !self.validate_size() {
+            self.data.truncate(old_len);
+            return Err(ClientError::DataTooLarge);
+        }
+
+        Ok(())
+    }
+
+    /// Appends entries without performing any validation.
+    ///
+    /// Entries which already exist at the given indices are skipped, so applying the same append
+    /// more than once has no further effect. Returns false if `index` lies past the current
+    /// index, in which case nothing is appended.
+    pub fn append_without_validation(&mut self, entries: Vec<Vec<u8>>, index: u64) -> bool {
+        if index > self.index() {
+            return false;
+        }
+
+        let skip = (self.index() - index) as usize;
+        self.data.extend(entries.into_iter().skip(skip));
+        true
+    }
+
+    /// Gets a complete list of permissions
+    pub fn permissions(&self) -> &BTreeMap<User, PermissionSet> {
+        &self.permissions
+    }
+
+    /// Gets a list of permissions for the provided user.
+    pub fn user_permissions(&self, user: &User) -> Result<&PermissionSet, ClientError> {
+        self.permissions.get(user).ok_or(ClientError::NoSuchKey)
+    }
+
+    /// Return the size of this data after serialisation.
+    pub fn serialised_size(&self) -> u64 {
+        serialisation::serialised_size(self)
+    }
+
+    /// Return true if the size is valid
+    pub fn validate_size(&self) -> bool {
+        self.serialised_size() <= MAX_APPENDABLE_DATA_SIZE_IN_BYTES
+    }
+
+    fn check_anyone_permissions(&self, action: Action) -> bool {
+        match self.permissions.get(&User::Anyone) {
+            None => false,
+            Some(perms) => perms.is_allowed(action).unwrap_or(false),
+        }
+    }
+
+    fn is_action_allowed(&self, requester: PublicKey, action: Action) -> bool {
+        if self.owners.contains(&requester) {
+            return true;
+        }
+        match self.permissions.get(&User::Key(requester)) {
+            Some(perms) => perms
+                .is_allowed(action)
+                .unwrap_or_else(|| self.check_anyone_permissions(action)),
+            None => self.check_anyone_permissions(action),
+        }
+    }
+}
+
+impl Serialize for AppendableData {
+    fn serialize<S: Serializer>(&self, serialiser: S) -> Result<S::Ok, S::Error> {
+        let layout = AppendableDataV1 {
+            name: self.name,
+            tag: self.tag,
+            data: Cow::Borrowed(&self.data),
+            permissions: Cow::Borrowed(&self.permissions),
+            version: self.version,
+            owners: Cow::Borrowed(&self.owners),
+        };
+        format::serialize_versioned(APPENDABLE_DATA_FORMAT_VERSION, &layout, serialiser)
+    }
+}
+
+impl<'de> Deserialize<'de> for AppendableData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AppendableData, D::Error> {
+        format::deserialize_versioned(deserializer)
+    }
+}
+
+impl Versioned for AppendableData {
+    fn read_layout<'de, A: SeqAccess<'de>>(version: u16, seq: &mut A) -> Result<Self, A::Error> {
+        match version {
+            1 => format::next_layout::<AppendableDataV1, _>(seq).map(AppendableData::from),
+            _ => Err(format::unsupported(version)),
+        }
+    }
+}
+
+// Layout of format version 1. Borrows the fields when serialising.
+#[derive(Serialize, Deserialize)]
+struct AppendableDataV1<'a> {
+    name: XorName,
+    tag: u64,
+    data: Cow<'a, Vec<Vec<u8>>>,
+    permissions: Cow<'a, BTreeMap<User, PermissionSet>>,
+    version: u64,
+    owners: Cow<'a, BTreeSet<PublicKey>>,
+}
+
+impl<'a> From<AppendableDataV1<'a>> for AppendableData {
+    fn from(layout: AppendableDataV1<'a>) -> AppendableData {
+        AppendableData {
+            name: layout.name,
+            tag: layout.tag,
+            data: layout.data.into_owned(),
+            permissions: layout.permissions.into_owned(),
+            version: layout.version,
+            owners: layout.owners.into_owned(),
+        }
+    }
+}
+
+impl Debug for AppendableData {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "AppendableData {{ name: {}, tag: {}, index: {}, version: {}, owners: {:?} }}",
+            self.name(),
+            self.tag,
+            self.index(),
+            self.version,
+            self.owners
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rust_sodium::crypto::sign;
+    use maidsafe_utilities::serialisation;
+    use rand;
+    use std::iter;
+
+    #[test]
+    fn append_permissions() {
+        let (owner0, _) = sign::gen_keypair();
+        let (owner1, _) = sign::gen_keypair();
+        let (pk1, _) = sign::gen_keypair();
+        let (pk2, _) = sign::gen_keypair();
+
+        let mut perms = BTreeMap::new();
+        let _ = perms.insert(User::Key(pk1), PermissionSet::new().allow(Action::Append));
+
+        let owners = vec![owner0, owner1].into_iter().collect();
+        let mut ad = unwrap!(AppendableData::new(
+            rand::random(),
+            0,
+            perms,
+            Vec::new(),
+            owners,
+        ));
+
+        unwrap!(ad.append(vec![b"abc".to_vec()], 0, pk1));
+        match ad.append(vec![b"def".to_vec()], 1, pk2) {
+            Err(ClientError::AccessDenied) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        // Appends requested by any of the owners should always be allowed
+        unwrap!(ad.append(vec![b"def".to_vec()], 1, owner0));
+        unwrap!(ad.append(vec![b"ghi".to_vec()], 2, owner1));
+
+        assert_eq!(ad.index(), 3);
+        assert_eq!(unwrap!(ad.get(0)), b"abc");
+        assert_eq!(unwrap!(ad.get(1)), b"def");
+        assert_eq!(unwrap!(ad.get(2)), b"ghi");
+    }
+
+    #[test]
+    fn write_once_index() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut ad = unwrap!(AppendableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            Vec::new(),
+            owners,
+        ));
+
+        unwrap!(ad.append(vec![vec![0], vec![1]], 0, owner));
+
+        // An append at an already taken index must fail and report the current index.
+        match ad.append(vec![vec![2]], 1, owner) {
+            Err(ClientError::InvalidSuccessor(2)) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+        assert_eq!(ad.entries(), &[vec![0], vec![1]][..]);
+
+        // Replaying a partially applied append only adds the missing entries.
+        assert!(ad.append_without_validation(vec![vec![1], vec![2]], 1));
+        assert_eq!(ad.entries(), &[vec![0], vec![1], vec![2]][..]);
+        assert!(!ad.append_without_validation(vec![vec![4]], 4));
+
+        assert_eq!(unwrap!(ad.range(1, 10)), vec![vec![1], vec![2]]);
+        assert!(unwrap!(ad.range(3, 10)).is_empty());
+        match ad.range(4, 10) {
+            Err(ClientError::NoSuchEntry) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let (owner, _) = sign::gen_keypair();
+        let owners: BTreeSet<_> = iter::once(owner).collect();
+
+        let data = vec![Vec::new(); MAX_APPENDABLE_DATA_ENTRIES as usize + 1];
+        match AppendableData::new(rand::random(), 0, BTreeMap::new(), data, owners.clone()) {
+            Err(ClientError::TooManyEntries) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        let mut ad = unwrap!(AppendableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            vec![vec![0; (MAX_APPENDABLE_DATA_SIZE_IN_BYTES - 1024) as usize]],
+            owners,
+        ));
+        match ad.append(vec![vec![0; 2048]], 1, owner) {
+            Err(ClientError::DataTooLarge) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+        assert_eq!(ad.index(), 1);
+    }
+
+    #[test]
+    fn format_versions() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut perms = BTreeMap::new();
+        let _ = perms.insert(User::Anyone, PermissionSet::new().allow(Action::Append));
+        let data = unwrap!(AppendableData::new(
+            rand::random(),
+            0,
+            perms,
+            vec![b"abc".to_vec()],
+            owners,
+        ));
+
+        let serialised = unwrap!(serialisation::serialise(&data));
+        let version: u16 = unwrap!(serialisation::deserialise(&serialised[8..10]));
+        assert_eq!(version, APPENDABLE_DATA_FORMAT_VERSION);
+        assert_eq!(unwrap!(serialisation::deserialise::<AppendableData>(&serialised)), data);
+
+        // Unknown versions are rejected rather than misread.
+        let mut future = serialised;
+        future[8..10].copy_from_slice(&unwrap!(serialisation::serialise(
+            &(APPENDABLE_DATA_FORMAT_VERSION + 1)
+        )));
+        assert!(serialisation::deserialise::<AppendableData>(&future).is_err());
+    }
+}
@@ -0,0 +1,467 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::ImmutableData;
+use crate::client_error::ClientError;
+use crate::types::MessageId;
+use crate::xor_name::XorName;
+use maidsafe_utilities::serialisation;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Maximum number of content bytes stored in a single chunk (1 MiB)
+pub const MAX_CHUNK_SIZE_IN_BYTES: u64 = 1024 * 1024;
+/// Maximum number of chunk requests a `FileUpload` or `FileDownload` keeps in flight at a time.
+pub const MAX_CHUNK_REQUESTS_IN_FLIGHT: usize = 8;
+/// Maximum number of times a `FileDownload` requests a chunk again after a failed request.
+pub const MAX_CHUNK_RETRIES: usize = 3;
+
+/// Describes how content too large for a single `ImmutableData` is split into chunks.
+///
+/// The data map itself is stored as `ImmutableData`, whose name identifies the content. If the
+/// data map is too large for one chunk, it is split again and described by a nested data map.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DataMap {
+    /// Names of the chunks holding the content, in order.
+    Content(Vec<XorName>),
+    /// Names of the chunks holding a serialised `DataMap`, in order.
+    Nested(Vec<XorName>),
+}
+
+impl DataMap {
+    /// Splits `content` into chunks. Returns the `ImmutableData` holding the root data map, and
+    /// the chunks it refers to, directly or through nested data maps.
+    pub fn split(content: &[u8]) -> (ImmutableData, Vec<ImmutableData>) {
+        split_with_chunk_size(content, MAX_CHUNK_SIZE_IN_BYTES as usize)
+    }
+
+    /// Parses the data map stored in `data`.
+    pub fn from_immutable_data(data: &ImmutableData) -> Result<DataMap, ClientError> {
+        serialisation::deserialise(data.value())
+            .map_err(|_| ClientError::from("Invalid data map"))
+    }
+
+    /// Returns the names of the chunks this data map refers to directly.
+    pub fn chunk_names(&self) -> &[XorName] {
+        match *self {
+            DataMap::Content(ref names) | DataMap::Nested(ref names) => names,
+        }
+    }
+
+    /// Returns true if the chunks of this data map hold another data map rather than the content.
+    pub fn is_nested(&self) -> bool {
+        match *self {
+            DataMap::Content(_) => false,
+            DataMap::Nested(_) => true,
+        }
+    }
+
+    /// Joins the chunks this data map refers to directly. Every chunk is checked against the
+    /// name it is referred to by.
+    ///
+    /// For a nested data map, the result is the serialised inner data map, which can be parsed
+    /// with `from_immutable_data` after wrapping it in `ImmutableData`. Otherwise it is the
+    /// content itself.
+    pub fn assemble(
+        &self,
+        chunks: &BTreeMap<XorName, ImmutableData>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let mut content = Vec::new();
+        for name in self.chunk_names() {
+            let chunk = chunks.get(name).ok_or(ClientError::NoSuchData)?;
+            if chunk.name() != name {
+                return Err(ClientError::InvalidChunk(*name));
+            }
+            content.extend_from_slice(chunk.value());
+        }
+        Ok(content)
+    }
+}
+
+/// Progress of putting content split into chunks, releasing the `PutIData` requests in batches of
+/// at most `MAX_CHUNK_REQUESTS_IN_FLIGHT`. The root data map is put last, once all the chunks it
+/// refers to are stored.
+#[derive(Debug)]
+pub struct FileUpload {
+    name: XorName,
+    root: Option<ImmutableData>,
+    chunks: VecDeque<ImmutableData>,
+    in_flight: BTreeMap<MessageId, XorName>,
+}
+
+impl FileUpload {
+    /// Splits `content` into chunks to put.
+    pub fn new(content: &[u8]) -> FileUpload {
+        FileUpload::from_split(DataMap::split(content))
+    }
+
+    fn from_split((root, chunks): (ImmutableData, Vec<ImmutableData>)) -> FileUpload {
+        FileUpload {
+            name: *root.name(),
+            root: Some(root),
+            chunks: chunks.into_iter().collect(),
+            in_flight: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the name of the root data map, which identifies the content.
+    pub fn name(&self) -> &XorName {
+        &self.name
+    }
+
+    /// Returns the chunks to put next, each with the message id of its `PutIData` request.
+    pub fn next_chunks(&mut self) -> Vec<(MessageId, ImmutableData)> {
+        let mut batch = Vec::new();
+        while self.in_flight.len() < MAX_CHUNK_REQUESTS_IN_FLIGHT {
+            let chunk = match self.chunks.pop_front() {
+                Some(chunk) => chunk,
+                None if self.in_flight.is_empty() => match self.root.take() {
+                    Some(root) => root,
+                    None => break,
+                },
+                None => break,
+            };
+            let msg_id = MessageId::new();
+            let _ = self.in_flight.insert(msg_id, *chunk.name());
+            batch.push((msg_id, chunk));
+        }
+        batch
+    }
+
+    /// Records the successful response to the `PutIData` request `msg_id`. Returns false if the
+    /// request isn't part of this upload.
+    pub fn chunk_stored(&mut self, msg_id: &MessageId) -> bool {
+        self.in_flight.remove(msg_id).is_some()
+    }
+
+    /// Returns true once all chunks and the root data map are stored.
+    pub fn is_done(&self) -> bool {
+        self.root.is_none() && self.chunks.is_empty() && self.in_flight.is_empty()
+    }
+}
+
+/// Progress of getting content by the name of its root data map, resolving nested data maps until
+/// the content is reached. The `GetIData` requests are released in batches of at most
+/// `MAX_CHUNK_REQUESTS_IN_FLIGHT`.
+#[derive(Debug)]
+pub struct FileDownload {
+    name: XorName,
+    data_map: Option<DataMap>,
+    chunks: BTreeMap<XorName, ImmutableData>,
+    in_flight: BTreeMap<MessageId, XorName>,
+    retries: BTreeMap<XorName, usize>,
+}
+
+impl FileDownload {
+    /// Starts getting the content identified by the root data map `name`.
+    pub fn new(name: XorName) -> FileDownload {
+        FileDownload {
+            name,
+            data_map: None,
+            chunks: BTreeMap::new(),
+            in_flight: BTreeMap::new(),
+            retries: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the names of the chunks to get next, each with the message id of its `GetIData`
+    /// request.
+    pub fn next_chunks(&mut self) -> Vec<(MessageId, XorName)> {
+        let names: Vec<XorName> = match self.data_map {
+            None if self.in_flight.is_empty() => vec![self.name],
+            None => vec![],
+            Some(ref data_map) => data_map
+                .chunk_names()
+                .iter()
+                .filter(|name| {
+                    !self.chunks.contains_key(*name) && !self.in_flight.values().any(|n| n == *name)
+                })
+                .cloned()
+                .collect(),
+        };
+
+        let mut batch = Vec::new();
+        for name in names {
+            if self.in_flight.len() >= MAX_CHUNK_REQUESTS_IN_FLIGHT {
+                break;
+            }
+            let msg_id = MessageId::new();
+            let _ = self.in_flight.insert(msg_id, name);
+            batch.push((msg_id, name));
+        }
+        batch
+    }
+
+    /// Handles the chunk received in response to the `GetIData` request `msg_id`. Returns the
+    /// content once all its chunks have arrived, or an error if a chunk doesn't match its name or
+    /// a data map can't be parsed.
+    pub fn chunk_received(
+        &mut self,
+        msg_id: &MessageId,
+        data: ImmutableData,
+    ) -> Result<Option<Vec<u8>>, ClientError> {
+        let name = match self.in_flight.remove(msg_id) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        if *data.name() != name {
+            return Err(ClientError::InvalidChunk(name));
+        }
+        if self.data_map.is_none() {
+            self.data_map = Some(DataMap::from_immutable_data(&data)?);
+        } else {
+            let _ = self.chunks.insert(name, data);
+        }
+
+        while let Some(data_map) = self.data_map.take() {
+            if data_map
+                .chunk_names()
+                .iter()
+                .any(|name| !self.chunks.contains_key(name))
+            {
+                self.data_map = Some(data_map);
+                return Ok(None);
+            }
+            let content = data_map.assemble(&self.chunks)?;
+            if !data_map.is_nested() {
+                self.data_map = Some(data_map);
+                return Ok(Some(content));
+            }
+            self.chunks.clear();
+            self.data_map = Some(DataMap::from_immutable_data(&ImmutableData::new(content))?);
+        }
+        Ok(None)
+    }
+
+    /// Handles the failure of the `GetIData` request `msg_id`, either an error response or no
+    /// response at all. The chunk is returned by `next_chunks` again, unless it has already been
+    /// requested `MAX_CHUNK_RETRIES` more times, in which case the download fails with
+    /// `NoSuchData`.
+    pub fn chunk_failed(&mut self, msg_id: &MessageId) -> Result<(), ClientError> {
+        let name = match self.in_flight.remove(msg_id) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let retries = self.retries.entry(name).or_insert(0);
+        if *retries >= MAX_CHUNK_RETRIES {
+            return Err(ClientError::NoSuchData);
+        }
+        *retries += 1;
+        Ok(())
+    }
+}
+
+fn split_with_chunk_size(content: &[u8], chunk_size: usize) -> (ImmutableData, Vec<ImmutableData>) {
+    let mut chunks = Vec::new();
+    let mut data_map = DataMap::Content(store_chunks(content, chunk_size, &mut chunks));
+
+    loop {
+        let serialised = unwrap!(serialisation::serialise(&data_map));
+        if serialised.len() <= chunk_size {
+            return (ImmutableData::new(serialised), chunks);
+        }
+        data_map = DataMap::Nested(store_chunks(&serialised, chunk_size, &mut chunks));
+    }
+}
+
+// Splits `content` into chunks of at most `chunk_size` bytes, appends them to `chunks` and returns
+// their names.
+fn store_chunks(
+    content: &[u8],
+    chunk_size: usize,
+    chunks: &mut Vec<ImmutableData>,
+) -> Vec<XorName> {
+    content
+        .chunks(chunk_size)
+        .map(|bytes| {
+            let chunk = ImmutableData::new(bytes.to_vec());
+            let name = *chunk.name();
+            chunks.push(chunk);
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maidsafe_utilities::SeededRng;
+    use rand::Rng;
+
+    // Fetches the content the way a client would: starting from the root, resolving nested data
+    // maps until the content is reached.
+    fn fetch(root: &ImmutableData, chunks: &BTreeMap<XorName, ImmutableData>) -> Vec<u8> {
+        let mut data_map = unwrap!(DataMap::from_immutable_data(root));
+        while data_map.is_nested() {
+            let inner = ImmutableData::new(unwrap!(data_map.assemble(chunks)));
+            data_map = unwrap!(DataMap::from_immutable_data(&inner));
+        }
+        unwrap!(data_map.assemble(chunks))
+    }
+
+    fn by_name(chunks: Vec<ImmutableData>) -> BTreeMap<XorName, ImmutableData> {
+        chunks
+            .into_iter()
+            .map(|chunk| (*chunk.name(), chunk))
+            .collect()
+    }
+
+    #[test]
+    fn split_and_assemble() {
+        let mut rng = SeededRng::thread_rng();
+
+        let content: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let (root, chunks) = split_with_chunk_size(&content, 1024);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(fetch(&root, &by_name(chunks)), content);
+
+        let (root, chunks) = DataMap::split(&[]);
+        assert!(chunks.is_empty());
+        assert!(fetch(&root, &by_name(chunks)).is_empty());
+
+        // The same content always maps to the same root.
+        let (root0, _) = split_with_chunk_size(&content, 100);
+        let (root1, chunks) = split_with_chunk_size(&content, 100);
+        assert_eq!(root0.name(), root1.name());
+        assert_eq!(chunks.len(), 10);
+        assert_eq!(fetch(&root1, &by_name(chunks)), content);
+    }
+
+    #[test]
+    fn nested_data_map() {
+        let mut rng = SeededRng::thread_rng();
+        let content: Vec<u8> = rng.gen_iter().take(10_000).collect();
+
+        // Names alone exceed the chunk size, so the data map has to be nested.
+        let (root, chunks) = split_with_chunk_size(&content, 128);
+        assert!(unwrap!(DataMap::from_immutable_data(&root)).is_nested());
+        assert!(root.payload_size() <= 128);
+        assert_eq!(fetch(&root, &by_name(chunks)), content);
+    }
+
+    #[test]
+    fn upload_in_batches() {
+        let mut rng = SeededRng::thread_rng();
+        let content: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let mut upload = FileUpload::from_split(split_with_chunk_size(&content, 10));
+        let chunk_count = upload.chunks.len();
+
+        let mut stored = Vec::new();
+        while !upload.is_done() {
+            let batch = upload.next_chunks();
+            assert!(!batch.is_empty());
+            assert!(batch.len() <= MAX_CHUNK_REQUESTS_IN_FLIGHT);
+            for (msg_id, chunk) in batch {
+                assert!(upload.chunk_stored(&msg_id));
+                stored.push(*chunk.name());
+            }
+        }
+        assert!(!upload.chunk_stored(&MessageId::new()));
+
+        // The root data map is put last.
+        assert_eq!(stored.len(), chunk_count + 1);
+        assert_eq!(stored.last(), Some(upload.name()));
+    }
+
+    #[test]
+    fn download_nested() {
+        let mut rng = SeededRng::thread_rng();
+        let content: Vec<u8> = rng.gen_iter().take(10_000).collect();
+        let (root, chunks) = split_with_chunk_size(&content, 128);
+        let mut chunks = by_name(chunks);
+        let _ = chunks.insert(*root.name(), root.clone());
+
+        let mut download = FileDownload::new(*root.name());
+        let mut requests = download.next_chunks();
+        assert_eq!(requests.len(), 1);
+        let mut result = None;
+        while result.is_none() {
+            assert!(!requests.is_empty());
+            assert!(requests.len() <= MAX_CHUNK_REQUESTS_IN_FLIGHT);
+            for (msg_id, name) in requests {
+                let chunk = unwrap!(chunks.get(&name)).clone();
+                if let Some(received) = unwrap!(download.chunk_received(&msg_id, chunk)) {
+                    result = Some(received);
+                }
+            }
+            requests = download.next_chunks();
+        }
+        assert_eq!(result, Some(content));
+        assert!(requests.is_empty());
+
+        // A chunk not matching its name is rejected.
+        let mut download = FileDownload::new(*root.name());
+        let (msg_id, _) = download.next_chunks()[0];
+        match download.chunk_received(&msg_id, ImmutableData::new(vec![0; 10])) {
+            Err(ClientError::InvalidChunk(name)) => assert_eq!(name, *root.name()),
+            x => panic!("Unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn download_retries() {
+        let mut rng = SeededRng::thread_rng();
+        let content: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let (root, chunks) = split_with_chunk_size(&content, 100);
+        let chunks = by_name(chunks);
+        let mut download = FileDownload::new(*root.name());
+
+        // A failed request of the root data map requests it again.
+        let (msg_id, name) = download.next_chunks()[0];
+        assert!(download.next_chunks().is_empty());
+        unwrap!(download.chunk_failed(&msg_id));
+        let (msg_id, retried) = download.next_chunks()[0];
+        assert_eq!(retried, name);
+        assert_eq!(unwrap!(download.chunk_received(&msg_id, root.clone())), None);
+
+        // A chunk is requested again until it has failed `MAX_CHUNK_RETRIES` more times.
+        let requests = download.next_chunks();
+        let (mut msg_id, name) = requests[0];
+        for _ in 0..MAX_CHUNK_RETRIES {
+            unwrap!(download.chunk_failed(&msg_id));
+            let retry = download.next_chunks();
+            assert_eq!(retry.len(), 1);
+            assert_eq!(retry[0].1, name);
+            msg_id = retry[0].0;
+        }
+        match download.chunk_failed(&msg_id) {
+            Err(ClientError::NoSuchData) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        // Unknown message ids are ignored.
+        unwrap!(download.chunk_failed(&MessageId::new()));
+
+        // The other chunks can still be received.
+        for (msg_id, name) in requests.into_iter().skip(1) {
+            let chunk = unwrap!(chunks.get(&name)).clone();
+            assert_eq!(unwrap!(download.chunk_received(&msg_id, chunk)), None);
+        }
+    }
+
+    #[test]
+    fn integrity() {
+        let mut rng = SeededRng::thread_rng();
+        let content: Vec<u8> = rng.gen_iter().take(1000).collect();
+        let (root, chunks) = split_with_chunk_size(&content, 100);
+        let data_map = unwrap!(DataMap::from_immutable_data(&root));
+
+        let mut chunks = by_name(chunks);
+        let name = data_map.chunk_names()[3];
+        let _ = chunks.insert(name, ImmutableData::new(vec![0; 100]));
+        match data_map.assemble(&chunks) {
+            Err(ClientError::InvalidChunk(invalid)) => assert_eq!(invalid, name),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        let _ = chunks.remove(&name);
+        match data_map.assemble(&chunks) {
+            Err(ClientError::NoSuchData) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+    }
+}
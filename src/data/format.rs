@@ -0,0 +1,99 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Versioned serialisation of data types.
+//!
+//! A versioned type is serialised as `VERSIONED_MARKER`, its format version and the layout of that
+//! version. When deserialising, every supported version is read with its own layout and migrated
+//! to the current one, so stored data stays readable and peers running different versions can
+//! still exchange data. Changing the layout of a type means adding a new version and a migration
+//! from the previous layout, instead of changing the existing layout.
+//!
+//! Data serialised before format versions were introduced has no marker. Its layout starts with
+//! eight bytes which are never `VERSIONED_MARKER` in practice (a length or the start of a name), so
+//! such data is recognised and handed to `Versioned::read_legacy` instead. This relies on the
+//! bincode encoding used by `maidsafe_utilities::serialisation`, where a tuple is the plain
+//! concatenation of its elements and can be read an element at a time.
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Formatter};
+use std::marker::PhantomData;
+
+const EXPECTING: &str = "a format version followed by a layout";
+
+/// Precedes the format version. No legacy layout starts with it: a length this large can't be
+/// allocated, and a name starting with it is as likely as a hash collision.
+const VERSIONED_MARKER: u64 = u64::MAX;
+
+/// A type serialised with an explicit format version.
+pub(super) trait Versioned: Sized {
+    /// Reads the layout of the given format version from `seq` and migrates it to `Self`.
+    /// Returns the error from `unsupported` for versions that can't be read.
+    fn read_layout<'de, A: SeqAccess<'de>>(version: u16, seq: &mut A) -> Result<Self, A::Error>;
+
+    /// Reads data serialised before format versions were introduced, given its first eight bytes
+    /// as `head` and the rest of the layout in `seq`. Types without such data reject it.
+    fn read_legacy<'de, A: SeqAccess<'de>>(head: u64, seq: &mut A) -> Result<Self, A::Error> {
+        let _ = (head, seq);
+        Err(de::Error::custom("missing format version"))
+    }
+}
+
+/// Serialises `layout` as format version `version`.
+pub(super) fn serialize_versioned<T: Serialize, S: Serializer>(
+    version: u16,
+    layout: &T,
+    serialiser: S,
+) -> Result<S::Ok, S::Error> {
+    (VERSIONED_MARKER, version, layout).serialize(serialiser)
+}
+
+/// Deserialises a `T` of any format version it supports, or of its legacy layout.
+pub(super) fn deserialize_versioned<'de, T: Versioned, D: Deserializer<'de>>(
+    deserialiser: D,
+) -> Result<T, D::Error> {
+    // The number of elements depends on the layout, so isn't known up front.
+    deserialiser.deserialize_tuple(usize::MAX, VersionedVisitor(PhantomData))
+}
+
+/// Reads the layout following the format version, or the next element of a legacy layout.
+pub(super) fn next_layout<'de, L: Deserialize<'de>, A: SeqAccess<'de>>(
+    seq: &mut A,
+) -> Result<L, A::Error> {
+    seq.next_element()?
+        .ok_or_else(|| de::Error::invalid_length(2, &EXPECTING))
+}
+
+/// The error for a format version which can't be read.
+pub(super) fn unsupported<E: de::Error>(version: u16) -> E {
+    E::custom(format_args!("unsupported format version {}", version))
+}
+
+struct VersionedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Versioned> Visitor<'de> for VersionedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(EXPECTING)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<T, A::Error> {
+        let head: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &EXPECTING))?;
+        if head != VERSIONED_MARKER {
+            return T::read_legacy(head, &mut seq);
+        }
+        let version: u16 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &EXPECTING))?;
+        T::read_layout(version, &mut seq)
+    }
+}
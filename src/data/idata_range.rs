@@ -0,0 +1,262 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::ImmutableData;
+use crate::client_error::ClientError;
+use crate::sha3::Digest256;
+use std::cmp;
+use tiny_keccak::sha3_256;
+
+/// Size of the blocks the Merkle tree over an `ImmutableData` value is built from (4 KiB).
+pub const IDATA_BLOCK_SIZE_IN_BYTES: u64 = 4 * 1024;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// A byte range of an `ImmutableData` value together with a Merkle inclusion proof.
+///
+/// The value is split into blocks of `IDATA_BLOCK_SIZE_IN_BYTES` which form the leaves of a binary
+/// Merkle tree. A range carries the blocks covering the requested bytes and the sibling hashes
+/// needed to recompute the root, so it can be checked against `ImmutableData::merkle_root` without
+/// fetching the whole chunk.
+///
+/// The chunk is still named by the hash of its whole value, which a range can't be checked
+/// against. The root is kept separately, by whoever stored the chunk, next to its name.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct IDataRange {
+    /// Offset of the requested range within the value
+    offset: u64,
+    /// Length of the requested range
+    len: u64,
+    /// Length of the whole value
+    data_len: u64,
+    /// Content of the blocks covering the requested range
+    blocks: Vec<u8>,
+    /// Sibling hashes, in the order they are consumed while walking up the tree
+    proof: Vec<Digest256>,
+}
+
+impl IDataRange {
+    /// Returns the offset of the range within the value.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length of the whole value.
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Returns the requested bytes, or `None` if the range is malformed.
+    pub fn content(&self) -> Option<&[u8]> {
+        let (first, _) = block_span(self.offset, self.len, self.data_len);
+        let start = self.offset.checked_sub(first * IDATA_BLOCK_SIZE_IN_BYTES)? as usize;
+        self.blocks.get(start..start.checked_add(self.len as usize)?)
+    }
+
+    /// Recomputes the Merkle root from the blocks and the proof. Returns `None` if the proof
+    /// doesn't fit the shape of the tree.
+    pub fn root(&self) -> Option<Digest256> {
+        if self.offset.checked_add(self.len)? > self.data_len {
+            return None;
+        }
+
+        let (first, last) = block_span(self.offset, self.len, self.data_len);
+        let mut hashes = leaf_hashes(&self.blocks);
+        if hashes.len() as u64 != last - first + 1 {
+            return None;
+        }
+
+        let mut proof = self.proof.iter();
+        let mut lo = first;
+        let mut width = block_count(self.data_len);
+
+        while width > 1 {
+            if lo % 2 == 1 {
+                hashes.insert(0, *proof.next()?);
+                lo -= 1;
+            }
+            if (lo + hashes.len() as u64) % 2 == 1 && lo + (hashes.len() as u64) < width {
+                hashes.push(*proof.next()?);
+            }
+            hashes = hashes.chunks(2).map(parent_hash).collect();
+            lo /= 2;
+            width = (width + 1) / 2;
+        }
+
+        if proof.next().is_some() {
+            return None;
+        }
+        hashes.pop()
+    }
+
+    /// Returns true if the range is consistent with the given Merkle root.
+    pub fn verify(&self, root: &Digest256) -> bool {
+        self.root().map_or(false, |computed| computed == *root)
+    }
+}
+
+impl ImmutableData {
+    /// Returns the root of the Merkle tree over the blocks of the value. It isn't derived from the
+    /// name, so it has to be kept next to the name to verify ranges of the value later.
+    pub fn merkle_root(&self) -> Digest256 {
+        let mut hashes = leaf_hashes(self.value());
+        while hashes.len() > 1 {
+            hashes = hashes.chunks(2).map(parent_hash).collect();
+        }
+        hashes[0]
+    }
+
+    /// Returns `len` bytes of the value starting at `offset`, together with a Merkle proof. `len`
+    /// is clamped to the end of the value, but `offset` must not lie past it.
+    pub fn range(&self, offset: u64, len: u64) -> Result<IDataRange, ClientError> {
+        let data_len = self.payload_size() as u64;
+        if offset > data_len {
+            return Err(ClientError::NoSuchEntry);
+        }
+        let len = cmp::min(len, data_len - offset);
+
+        let (first, last) = block_span(offset, len, data_len);
+        let start = (first * IDATA_BLOCK_SIZE_IN_BYTES) as usize;
+        let end = cmp::min((last + 1) * IDATA_BLOCK_SIZE_IN_BYTES, data_len) as usize;
+
+        let mut proof = Vec::new();
+        let mut hashes = leaf_hashes(self.value());
+        let (mut lo, mut hi) = (first as usize, last as usize);
+
+        while hashes.len() > 1 {
+            if lo % 2 == 1 {
+                proof.push(hashes[lo - 1]);
+            }
+            if hi % 2 == 0 && hi + 1 < hashes.len() {
+                proof.push(hashes[hi + 1]);
+            }
+            hashes = hashes.chunks(2).map(parent_hash).collect();
+            lo /= 2;
+            hi /= 2;
+        }
+
+        Ok(IDataRange {
+            offset,
+            len,
+            data_len,
+            blocks: self.value()[start..end].to_vec(),
+            proof,
+        })
+    }
+}
+
+// Returns the indices of the first and the last block covering the given range. An empty range
+// is covered by the block containing `offset`, or by the last block if `offset` is at the end.
+fn block_span(offset: u64, len: u64, data_len: u64) -> (u64, u64) {
+    let first = cmp::min(offset / IDATA_BLOCK_SIZE_IN_BYTES, block_count(data_len) - 1);
+    let last = if len == 0 {
+        first
+    } else {
+        (offset + len - 1) / IDATA_BLOCK_SIZE_IN_BYTES
+    };
+    (first, last)
+}
+
+// Number of leaves of the tree. An empty value still has a single, empty block.
+fn block_count(data_len: u64) -> u64 {
+    let count = data_len.saturating_add(IDATA_BLOCK_SIZE_IN_BYTES - 1) / IDATA_BLOCK_SIZE_IN_BYTES;
+    cmp::max(1, count)
+}
+
+fn leaf_hashes(value: &[u8]) -> Vec<Digest256> {
+    let mut hashes: Vec<_> = value
+        .chunks(IDATA_BLOCK_SIZE_IN_BYTES as usize)
+        .map(leaf_hash)
+        .collect();
+    if hashes.is_empty() {
+        hashes.push(leaf_hash(&[]));
+    }
+    hashes
+}
+
+fn leaf_hash(block: &[u8]) -> Digest256 {
+    let mut preimage = Vec::with_capacity(block.len() + 1);
+    preimage.push(LEAF_PREFIX);
+    preimage.extend_from_slice(block);
+    sha3_256(&preimage)
+}
+
+// A node without a sibling is carried up to the next level unchanged.
+fn parent_hash(children: &[Digest256]) -> Digest256 {
+    if children.len() == 1 {
+        return children[0];
+    }
+    let mut preimage = Vec::with_capacity(65);
+    preimage.push(NODE_PREFIX);
+    preimage.extend_from_slice(&children[0]);
+    preimage.extend_from_slice(&children[1]);
+    sha3_256(&preimage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{self, Rng};
+
+    #[test]
+    fn range_proofs() {
+        let mut rng = rand::thread_rng();
+        let len = 10 * IDATA_BLOCK_SIZE_IN_BYTES as usize + 123;
+        let data = ImmutableData::new(rng.gen_iter().take(len).collect());
+        let root = data.merkle_root();
+
+        let ranges = [
+            (0, 0),
+            (0, 10),
+            (4000, 200),
+            (IDATA_BLOCK_SIZE_IN_BYTES, IDATA_BLOCK_SIZE_IN_BYTES),
+            (5 * IDATA_BLOCK_SIZE_IN_BYTES + 7, 3 * IDATA_BLOCK_SIZE_IN_BYTES),
+            (len as u64 - 50, 1000),
+            (len as u64, 10),
+            (0, len as u64),
+        ];
+        for &(offset, range_len) in &ranges {
+            let range = unwrap!(data.range(offset, range_len));
+            assert!(range.verify(&root), "offset {}, len {}", offset, range_len);
+
+            let end = cmp::min(offset + range_len, len as u64) as usize;
+            assert_eq!(unwrap!(range.content()), &data.value()[offset as usize..end]);
+        }
+
+        match data.range(len as u64 + 1, 1) {
+            Err(ClientError::NoSuchEntry) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        // Tampering with the content or the proof must be detected.
+        let mut range = unwrap!(data.range(4000, 200));
+        range.blocks[0] ^= 1;
+        assert!(!range.verify(&root));
+
+        let mut range = unwrap!(data.range(4000, 200));
+        range.proof[0][0] ^= 1;
+        assert!(!range.verify(&root));
+
+        let mut range = unwrap!(data.range(4000, 200));
+        let _ = range.proof.pop();
+        assert!(!range.verify(&root));
+
+        // A range at the very end of a value filling its last block exactly.
+        let data = ImmutableData::new(vec![7; 2 * IDATA_BLOCK_SIZE_IN_BYTES as usize]);
+        let range = unwrap!(data.range(2 * IDATA_BLOCK_SIZE_IN_BYTES, 1));
+        assert!(range.verify(&data.merkle_root()));
+        assert_eq!(unwrap!(range.content()), &[][..]);
+
+        // An empty value still has a root and a provable (empty) range.
+        let empty = ImmutableData::new(Vec::new());
+        let range = unwrap!(empty.range(0, 10));
+        assert!(range.verify(&empty.merkle_root()));
+        assert_eq!(unwrap!(range.content()), &[][..]);
+    }
+}
@@ -6,8 +6,13 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::format::{self, Versioned};
+use crate::client_error::ClientError;
+use crate::rust_sodium::crypto::sign::{self, PublicKey, Signature};
 use crate::xor_name::XorName;
 use maidsafe_utilities::serialisation;
+use rand;
+use serde::de::SeqAccess;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::{self, Debug, Formatter};
 use tiny_keccak::sha3_256;
@@ -15,10 +20,18 @@ use tiny_keccak::sha3_256;
 /// Maximum allowed size for a serialised Immutable Data (ID) to grow to
 pub const MAX_IMMUTABLE_DATA_SIZE_IN_BYTES: u64 = 1024 * 1024 + 10 * 1024;
 
+/// Format version `ImmutableData` is serialised with. Version 1 consists of the value only; data
+/// serialised before format versions were introduced is the bare value.
+pub const IMMUTABLE_DATA_FORMAT_VERSION: u16 = 1;
+
+/// Format version `UnpubImmutableData` is serialised with. Version 1 consists of the value, the
+/// owner and the nonce.
+pub const UNPUB_IMMUTABLE_DATA_FORMAT_VERSION: u16 = 1;
+
 /// An immutable chunk of data.
 ///
 /// Note that the `name` member is omitted when serialising `ImmutableData` and is calculated from
-/// the `value` when deserialising.
+/// the `value` when deserialising. The value is preceded by `IMMUTABLE_DATA_FORMAT_VERSION`.
 #[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct ImmutableData {
     name: XorName,
@@ -62,13 +75,29 @@ impl ImmutableData {
 
 impl Serialize for ImmutableData {
     fn serialize<S: Serializer>(&self, serialiser: S) -> Result<S::Ok, S::Error> {
-        self.value.serialize(serialiser)
+        format::serialize_versioned(IMMUTABLE_DATA_FORMAT_VERSION, &self.value, serialiser)
     }
 }
 
 impl<'de> Deserialize<'de> for ImmutableData {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ImmutableData, D::Error> {
-        let value: Vec<u8> = Deserialize::deserialize(deserializer)?;
+        format::deserialize_versioned(deserializer)
+    }
+}
+
+impl Versioned for ImmutableData {
+    fn read_layout<'de, A: SeqAccess<'de>>(version: u16, seq: &mut A) -> Result<Self, A::Error> {
+        match version {
+            1 => format::next_layout(seq).map(ImmutableData::new),
+            _ => Err(format::unsupported(version)),
+        }
+    }
+
+    // The bare value, of which `head` is the length.
+    fn read_legacy<'de, A: SeqAccess<'de>>(head: u64, seq: &mut A) -> Result<Self, A::Error> {
+        let value = (0..head)
+            .map(|_| format::next_layout(seq))
+            .collect::<Result<_, _>>()?;
         Ok(ImmutableData::new(value))
     }
 }
@@ -79,6 +108,125 @@ impl Debug for ImmutableData {
     }
 }
 
+/// An unpublished, owned chunk of immutable data.
+///
+/// Unlike `ImmutableData`, the name is derived from both the `value` and the `owner`, so the same
+/// content stored by different owners lives at different addresses. Only the owner can delete it.
+/// As with `ImmutableData`, the `name` member is omitted when serialising and is recalculated when
+/// deserialising. The rest is preceded by `UNPUB_IMMUTABLE_DATA_FORMAT_VERSION`.
+///
+/// Each instance carries a random nonce, which deletion signatures cover: once the data has been
+/// deleted and put again, a signature captured from the earlier deletion doesn't delete it.
+#[derive(Hash, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct UnpubImmutableData {
+    name: XorName,
+    value: Vec<u8>,
+    owner: PublicKey,
+    nonce: u64,
+}
+
+impl UnpubImmutableData {
+    /// Creates a new instance of `UnpubImmutableData` with a random nonce.
+    pub fn new(value: Vec<u8>, owner: PublicKey) -> UnpubImmutableData {
+        Self::with_nonce(value, owner, rand::random())
+    }
+
+    fn with_nonce(value: Vec<u8>, owner: PublicKey, nonce: u64) -> UnpubImmutableData {
+        let mut preimage = value.clone();
+        preimage.extend_from_slice(&owner[..]);
+        UnpubImmutableData {
+            name: XorName(sha3_256(&preimage)),
+            value,
+            owner,
+            nonce,
+        }
+    }
+
+    /// Returns the value
+    pub fn value(&self) -> &Vec<u8> {
+        &self.value
+    }
+
+    /// Returns name ensuring invariant.
+    pub fn name(&self) -> &XorName {
+        &self.name
+    }
+
+    /// Returns the owner key
+    pub fn owner(&self) -> &PublicKey {
+        &self.owner
+    }
+
+    /// Returns the nonce deletion signatures have to cover.
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Returns size of contained value.
+    pub fn payload_size(&self) -> usize {
+        self.value.len()
+    }
+
+    /// Returns size of this data after serialisation.
+    pub fn serialised_size(&self) -> u64 {
+        serialisation::serialised_size(self)
+    }
+
+    /// Return true if the size is valid
+    pub fn validate_size(&self) -> bool {
+        self.serialised_size() <= MAX_IMMUTABLE_DATA_SIZE_IN_BYTES
+    }
+
+    /// Returns the bytes the owner has to sign to delete the data with the given name and nonce.
+    pub fn deletion_signing_data(name: &XorName, nonce: u64) -> Vec<u8> {
+        let mut signing_data = b"DeleteUnpubIData".to_vec();
+        signing_data.extend_from_slice(&name.0);
+        signing_data.extend_from_slice(&unwrap!(serialisation::serialise(&nonce)));
+        signing_data
+    }
+
+    /// Checks that `signature` is a valid deletion signature by the owner of this data.
+    pub fn verify_deletion(&self, signature: &Signature) -> Result<(), ClientError> {
+        let signing_data = Self::deletion_signing_data(&self.name, self.nonce);
+        if sign::verify_detached(signature, &signing_data, &self.owner) {
+            Ok(())
+        } else {
+            Err(ClientError::InvalidSignature)
+        }
+    }
+}
+
+impl Serialize for UnpubImmutableData {
+    fn serialize<S: Serializer>(&self, serialiser: S) -> Result<S::Ok, S::Error> {
+        let layout = (&self.value, &self.owner, self.nonce);
+        format::serialize_versioned(UNPUB_IMMUTABLE_DATA_FORMAT_VERSION, &layout, serialiser)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnpubImmutableData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<UnpubImmutableData, D::Error> {
+        format::deserialize_versioned(deserializer)
+    }
+}
+
+impl Versioned for UnpubImmutableData {
+    fn read_layout<'de, A: SeqAccess<'de>>(version: u16, seq: &mut A) -> Result<Self, A::Error> {
+        match version {
+            1 => {
+                let (value, owner, nonce): (Vec<u8>, PublicKey, u64) = format::next_layout(seq)?;
+                Ok(UnpubImmutableData::with_nonce(value, owner, nonce))
+            }
+            _ => Err(format::unsupported(version)),
+        }
+    }
+}
+
+impl Debug for UnpubImmutableData {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "UnpubImmutableData {:?}", self.name())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,4 +254,62 @@ mod tests {
         let parsed = unwrap!(serialisation::deserialise(&serialised));
         assert_eq!(immutable_data, parsed);
     }
+
+    #[test]
+    fn format_versions() {
+        let data = ImmutableData::new(b"versioned".to_vec());
+        let serialised = unwrap!(serialisation::serialise(&data));
+        let version: u16 = unwrap!(serialisation::deserialise(&serialised[8..10]));
+        assert_eq!(version, IMMUTABLE_DATA_FORMAT_VERSION);
+
+        // Data stored before format versions were introduced is the bare value.
+        let unversioned = unwrap!(serialisation::serialise(data.value()));
+        assert_eq!(unwrap!(serialisation::deserialise::<ImmutableData>(&unversioned)), data);
+        let empty = unwrap!(serialisation::serialise(&Vec::<u8>::new()));
+        assert_eq!(
+            unwrap!(serialisation::deserialise::<ImmutableData>(&empty)),
+            ImmutableData::new(vec![])
+        );
+
+        // Unknown versions are rejected rather than misread.
+        let future = (u64::MAX, IMMUTABLE_DATA_FORMAT_VERSION + 1, data.value());
+        let serialised = unwrap!(serialisation::serialise(&future));
+        assert!(serialisation::deserialise::<ImmutableData>(&serialised).is_err());
+    }
+
+    #[test]
+    fn unpublished() {
+        let (owner0, sk0) = sign::gen_keypair();
+        let (owner1, sk1) = sign::gen_keypair();
+        let value = "unpublished data value".to_owned().into_bytes();
+
+        let data0 = UnpubImmutableData::new(value.clone(), owner0);
+        let data1 = UnpubImmutableData::new(value.clone(), owner1);
+        assert_ne!(data0.name(), data1.name());
+        assert_ne!(data0.name(), ImmutableData::new(value.clone()).name());
+
+        let serialised = unwrap!(serialisation::serialise(&data0));
+        let version: u16 = unwrap!(serialisation::deserialise(&serialised[8..10]));
+        assert_eq!(version, UNPUB_IMMUTABLE_DATA_FORMAT_VERSION);
+        let parsed: UnpubImmutableData = unwrap!(serialisation::deserialise(&serialised));
+        assert_eq!(data0, parsed);
+        let unversioned = unwrap!(serialisation::serialise(&(data0.value(), data0.owner())));
+        assert!(serialisation::deserialise::<UnpubImmutableData>(&unversioned).is_err());
+
+        let signing_data = UnpubImmutableData::deletion_signing_data(data0.name(), data0.nonce());
+        let signature = sign::sign_detached(&signing_data, &sk0);
+        unwrap!(data0.verify_deletion(&signature));
+        match data0.verify_deletion(&sign::sign_detached(&signing_data, &sk1)) {
+            Err(ClientError::InvalidSignature) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        // The signature doesn't delete the same data put again.
+        let data0_again = UnpubImmutableData::new(value, owner0);
+        assert_eq!(data0_again.name(), data0.name());
+        match data0_again.verify_deletion(&signature) {
+            Err(ClientError::InvalidSignature) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+    }
 }
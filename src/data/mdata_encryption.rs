@@ -0,0 +1,116 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::User;
+use crate::client_error::ClientError;
+use crate::id::{FullId, PublicId};
+use crate::rust_sodium::crypto::{box_, secretbox};
+use maidsafe_utilities::serialisation;
+use std::fmt::{self, Debug, Formatter};
+use tiny_keccak::sha3_256;
+
+/// Symmetric key the entries of private `MutableData` are encrypted with.
+///
+/// Entry keys are encrypted deterministically, so that an entry can still be looked up by its
+/// encrypted key. Entry values are encrypted with a fresh nonce every time.
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MDataEncryptionKey {
+    key: secretbox::Key,
+    nonce: secretbox::Nonce,
+}
+
+impl MDataEncryptionKey {
+    /// Generates a new random key.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> MDataEncryptionKey {
+        MDataEncryptionKey {
+            key: secretbox::gen_key(),
+            nonce: secretbox::gen_nonce(),
+        }
+    }
+
+    /// Encrypts the key of an entry.
+    pub fn encrypt_key(&self, key: &[u8]) -> Vec<u8> {
+        self.seal(key, self.key_nonce(key))
+    }
+
+    /// Decrypts the key of an entry.
+    pub fn decrypt_key(&self, encrypted: &[u8]) -> Result<Vec<u8>, ClientError> {
+        self.open(encrypted)
+    }
+
+    /// Encrypts the content of an entry.
+    pub fn encrypt_content(&self, content: &[u8]) -> Vec<u8> {
+        self.seal(content, secretbox::gen_nonce())
+    }
+
+    /// Decrypts the content of an entry.
+    pub fn decrypt_content(&self, encrypted: &[u8]) -> Result<Vec<u8>, ClientError> {
+        self.open(encrypted)
+    }
+
+    /// Encrypts this key for `recipient`, so that it can be shared with them. Returns the
+    /// recipient's `User::Key`, which has to be granted `Action::Read` on the data, together with
+    /// the encrypted key.
+    pub fn share(&self, recipient: &PublicId, our_id: &FullId) -> (User, Vec<u8>) {
+        let nonce = box_::gen_nonce();
+        let plaintext = unwrap!(serialisation::serialise(self));
+        let ciphertext = box_::seal(
+            &plaintext,
+            &nonce,
+            recipient.encrypting_public_key(),
+            our_id.encrypting_private_key(),
+        );
+        let user = User::Key(*recipient.signing_public_key());
+        (user, unwrap!(serialisation::serialise(&(nonce, ciphertext))))
+    }
+
+    /// Decrypts a key shared with us by `sender`.
+    pub fn from_shared(
+        shared: &[u8],
+        sender: &PublicId,
+        our_id: &FullId,
+    ) -> Result<MDataEncryptionKey, ClientError> {
+        let (nonce, ciphertext): (box_::Nonce, Vec<u8>) =
+            serialisation::deserialise(shared).map_err(|_| ClientError::DecryptionFailed)?;
+        let plaintext = box_::open(
+            &ciphertext,
+            &nonce,
+            sender.encrypting_public_key(),
+            our_id.encrypting_private_key(),
+        )
+        .map_err(|()| ClientError::DecryptionFailed)?;
+        serialisation::deserialise(&plaintext).map_err(|_| ClientError::DecryptionFailed)
+    }
+
+    // Derives the nonce for an entry key from the key itself, so that equal keys encrypt equally
+    // while different keys don't share a nonce.
+    fn key_nonce(&self, key: &[u8]) -> secretbox::Nonce {
+        let mut input = key.to_vec();
+        input.extend_from_slice(&self.nonce.0);
+        let hash = sha3_256(&input);
+        unwrap!(secretbox::Nonce::from_slice(&hash[..secretbox::NONCEBYTES]))
+    }
+
+    fn seal(&self, plaintext: &[u8], nonce: secretbox::Nonce) -> Vec<u8> {
+        let ciphertext = secretbox::seal(plaintext, &nonce, &self.key);
+        unwrap!(serialisation::serialise(&(nonce, ciphertext)))
+    }
+
+    fn open(&self, encrypted: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let (nonce, ciphertext): (secretbox::Nonce, Vec<u8>) =
+            serialisation::deserialise(encrypted).map_err(|_| ClientError::DecryptionFailed)?;
+        secretbox::open(&ciphertext, &nonce, &self.key).map_err(|()| ClientError::DecryptionFailed)
+    }
+}
+
+impl Debug for MDataEncryptionKey {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "MDataEncryptionKey(..)")
+    }
+}
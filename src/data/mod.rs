@@ -6,13 +6,36 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+mod appendable_data;
+mod data_map;
+mod format;
+mod idata_range;
 mod immutable_data;
+mod mdata_encryption;
 mod mutable_data;
+mod schema;
 
-pub use self::immutable_data::{ImmutableData, MAX_IMMUTABLE_DATA_SIZE_IN_BYTES};
+pub use self::appendable_data::{
+    AppendableData, APPENDABLE_DATA_FORMAT_VERSION, MAX_APPENDABLE_DATA_ENTRIES,
+    MAX_APPENDABLE_DATA_SIZE_IN_BYTES,
+};
+pub use self::data_map::{
+    DataMap, FileDownload, FileUpload, MAX_CHUNK_REQUESTS_IN_FLIGHT, MAX_CHUNK_RETRIES,
+    MAX_CHUNK_SIZE_IN_BYTES,
+};
+pub use self::idata_range::{IDataRange, IDATA_BLOCK_SIZE_IN_BYTES};
+pub use self::immutable_data::{
+    ImmutableData, UnpubImmutableData, IMMUTABLE_DATA_FORMAT_VERSION,
+    MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, UNPUB_IMMUTABLE_DATA_FORMAT_VERSION,
+};
+pub use self::mdata_encryption::MDataEncryptionKey;
 pub use self::mutable_data::{
-    Action, EntryAction, EntryActions, MutableData, PermissionSet, User, Value,
-    MAX_MUTABLE_DATA_ENTRIES, MAX_MUTABLE_DATA_SIZE_IN_BYTES,
+    Action, EntriesRange, EntryAction, EntryActions, MDataChanges, MutableData, OwnerAction,
+    PermissionSet, User, Value, MAX_KEY_PREFIX_LEN, MAX_MUTABLE_DATA_ENTRIES,
+    MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN, MAX_MUTABLE_DATA_SIZE_IN_BYTES, MUTABLE_DATA_FORMAT_VERSION,
+};
+pub use self::schema::{
+    DnsPacketValidator, SessionPacketValidator, TypeTagValidator, TypeTagValidators,
 };
 use crate::rust_sodium::crypto::sign::{self, PublicKey};
 
@@ -6,15 +6,24 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
+use super::format::{self, Versioned};
+use super::schema::TypeTagValidators;
+use super::MDataEncryptionKey;
 use crate::client_error::{ClientError, EntryError};
-use crate::rust_sodium::crypto::sign::PublicKey;
+use crate::routing_table::Authority;
+use crate::rust_sodium::crypto::sign::{self, PublicKey, Signature};
 use crate::xor_name::XorName;
 use maidsafe_utilities::serialisation;
 use rand::{Rand, Rng};
+use serde::de::{self, SeqAccess};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::btree_map::{BTreeMap, Entry};
+use std::cmp;
 use std::collections::BTreeSet;
 use std::fmt::{self, Debug, Formatter};
 use std::mem;
+use std::ops::Bound;
 
 /// Maximum allowed size for `MutableData` (1 MiB)
 pub const MAX_MUTABLE_DATA_SIZE_IN_BYTES: u64 = 1024 * 1024;
@@ -22,8 +31,25 @@ pub const MAX_MUTABLE_DATA_SIZE_IN_BYTES: u64 = 1024 * 1024;
 /// Maximum allowed entries in `MutableData`
 pub const MAX_MUTABLE_DATA_ENTRIES: u64 = 1000;
 
+/// Maximum number of previous versions retained per entry in `MutableData`
+pub const MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN: u64 = 16;
+
+/// Maximum length of the key prefix a `PermissionSet` can be scoped to.
+pub const MAX_KEY_PREFIX_LEN: usize = 32;
+
+/// Format version `MutableData` is serialised with.
+///
+/// Data serialised before format versions were introduced has the original layout, without owner
+/// thresholds, entry history, change tracking or private entries, and with permissions covering
+/// Insert, Update, Delete and ManagePermissions only. Version 1 adds those, and version 2, the
+/// current layout, adds entry expiry times and the time the expired entries were last purged at.
+pub const MUTABLE_DATA_FORMAT_VERSION: u16 = 2;
+
 /// Mutable data.
-#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+///
+/// Serialised as `MUTABLE_DATA_FORMAT_VERSION` followed by the layout of that version. Data of
+/// older format versions is migrated when deserialised.
+#[derive(Hash, Eq, PartialEq, PartialOrd, Ord, Clone)]
 pub struct MutableData {
     /// Network address
     name: XorName,
@@ -38,8 +64,27 @@ pub struct MutableData {
     /// except for data
     version: u64,
     /// Contains a set of owners which are allowed to mutate permissions.
-    /// Currently limited to one owner to disallow multisig.
     owners: BTreeSet<PublicKey>,
+    /// Number of owner signatures required to mutate permissions or change the owners.
+    owners_threshold: u64,
+    /// Maps a key to the previous values of its entry, oldest first. Only keys in `data` have a
+    /// history.
+    history: BTreeMap<Vec<u8>, Vec<Value>>,
+    /// Number of previous values retained per entry. Zero disables the history.
+    history_len: u64,
+    /// Data version should be increased for every change in data, permissions or owners
+    data_version: u64,
+    /// Maps a key to the data versions at which its entry was inserted and last changed. Holds a
+    /// record for every entry, tombstones included, and no others, so it is bounded by
+    /// `MAX_MUTABLE_DATA_ENTRIES`
+    changes: BTreeMap<Vec<u8>, ChangeRecord>,
+    /// Whether the entries are encrypted and reading them requires the `Read` permission
+    private: bool,
+    /// Maps a key to the time its entry expires at, in seconds since the UNIX epoch
+    expiries: BTreeMap<Vec<u8>, u64>,
+    /// Time agreed by the section, in seconds since the UNIX epoch, the expired entries were last
+    /// purged at. Reads hide the entries which have expired by then.
+    purged_at: u64,
 }
 
 /// A value in `MutableData`
@@ -71,9 +116,22 @@ pub enum Action {
     Delete,
     /// Permission to modify permissions for other users.
     ManagePermissions,
+    /// Permission to append new entries to `AppendableData`.
+    Append,
+    /// Permission to read the entries of private `MutableData`. It is checked against the client
+    /// sending the read request, see `MutableData::check_read_permission`.
+    Read,
 }
 
 /// Set of user permissions.
+///
+/// Insert, Update and Delete can be scoped to the entries whose keys start with a given prefix.
+/// For keys outside of the scope, these actions are neither allowed nor denied by the set.
+///
+/// A permission set can expire at a data version of the `MutableData` it is granted on. Expiring
+/// at a data version rather than at a point in time keeps the decision the same in every vault.
+/// The data version advances with permission and owner changes too, so a grant also expires on
+/// data whose entries never change. Once expired, the set neither allows nor denies any action.
 #[derive(
     Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize, Default,
 )]
@@ -82,6 +140,49 @@ pub struct PermissionSet {
     update: Option<bool>,
     delete: Option<bool>,
     manage_permissions: Option<bool>,
+    append: Option<bool>,
+    read: Option<bool>,
+    key_prefix: Option<KeyPrefix>,
+    expiry: Option<u64>,
+}
+
+/// A key prefix stored inline, so that `PermissionSet` stays `Copy`. It is serialised like the
+/// byte vector it stands for.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+struct KeyPrefix {
+    len: u8,
+    bytes: [u8; MAX_KEY_PREFIX_LEN],
+}
+
+impl KeyPrefix {
+    fn new(prefix: &[u8]) -> Option<KeyPrefix> {
+        if prefix.len() > MAX_KEY_PREFIX_LEN {
+            return None;
+        }
+        let mut bytes = [0; MAX_KEY_PREFIX_LEN];
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        Some(KeyPrefix {
+            len: prefix.len() as u8,
+            bytes,
+        })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+}
+
+impl Serialize for KeyPrefix {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_slice().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyPrefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<KeyPrefix, D::Error> {
+        let prefix: Vec<u8> = Deserialize::deserialize(deserializer)?;
+        KeyPrefix::new(&prefix).ok_or_else(|| de::Error::custom("key prefix too long"))
+    }
 }
 
 impl PermissionSet {
@@ -92,6 +193,10 @@ impl PermissionSet {
             update: None,
             delete: None,
             manage_permissions: None,
+            append: None,
+            read: None,
+            key_prefix: None,
+            expiry: None,
         }
     }
 
@@ -102,6 +207,8 @@ impl PermissionSet {
             Action::Update => self.update = Some(true),
             Action::Delete => self.delete = Some(true),
             Action::ManagePermissions => self.manage_permissions = Some(true),
+            Action::Append => self.append = Some(true),
+            Action::Read => self.read = Some(true),
         }
         self
     }
@@ -113,6 +220,8 @@ impl PermissionSet {
             Action::Update => self.update = Some(false),
             Action::Delete => self.delete = Some(false),
             Action::ManagePermissions => self.manage_permissions = Some(false),
+            Action::Append => self.append = Some(false),
+            Action::Read => self.read = Some(false),
         }
         self
     }
@@ -124,17 +233,64 @@ impl PermissionSet {
             Action::Update => self.update = None,
             Action::Delete => self.delete = None,
             Action::ManagePermissions => self.manage_permissions = None,
+            Action::Append => self.append = None,
+            Action::Read => self.read = None,
         }
         self
     }
 
-    /// Is the given action allowed according to this permission set?
+    /// Scope Insert, Update and Delete to the entries whose keys start with `key_prefix`, which
+    /// can be at most `MAX_KEY_PREFIX_LEN` bytes long.
+    pub fn scope(mut self, key_prefix: &[u8]) -> Result<Self, ClientError> {
+        self.key_prefix = Some(KeyPrefix::new(key_prefix).ok_or(ClientError::InvalidOperation)?);
+        Ok(self)
+    }
+
+    /// Returns the key prefix Insert, Update and Delete are scoped to, if any.
+    pub fn key_prefix(&self) -> Option<&[u8]> {
+        self.key_prefix.as_ref().map(KeyPrefix::as_slice)
+    }
+
+    /// Let this permission set expire once the data version of the `MutableData` reaches
+    /// `data_version`.
+    pub fn expires_at(mut self, data_version: u64) -> Self {
+        self.expiry = Some(data_version);
+        self
+    }
+
+    /// Returns the data version this permission set expires at, if any.
+    pub fn expiry(&self) -> Option<u64> {
+        self.expiry
+    }
+
+    /// Has this permission set expired at the given data version?
+    pub fn is_expired(&self, data_version: u64) -> bool {
+        self.expiry.map_or(false, |expiry| data_version >= expiry)
+    }
+
+    /// Is the given action allowed according to this permission set? The key scope and the expiry
+    /// are ignored.
     pub fn is_allowed(self, action: Action) -> Option<bool> {
         match action {
             Action::Insert => self.insert,
             Action::Update => self.update,
             Action::Delete => self.delete,
             Action::ManagePermissions => self.manage_permissions,
+            Action::Append => self.append,
+            Action::Read => self.read,
+        }
+    }
+
+    /// Is the given action on the entry with the given key allowed according to this permission
+    /// set?
+    pub fn is_allowed_for_key(self, action: Action, key: &[u8]) -> Option<bool> {
+        let in_scope = match self.key_prefix {
+            Some(ref prefix) => key.starts_with(prefix.as_slice()),
+            None => true,
+        };
+        match action {
+            Action::Insert | Action::Update | Action::Delete if !in_scope => None,
+            _ => self.is_allowed(action),
         }
     }
 }
@@ -146,17 +302,60 @@ impl Rand for PermissionSet {
             update: Rand::rand(rng),
             delete: Rand::rand(rng),
             manage_permissions: Rand::rand(rng),
+            append: Rand::rand(rng),
+            read: Rand::rand(rng),
+            key_prefix: None,
+            expiry: None,
         }
     }
 }
 
+/// Action on `MutableData` which has to be signed by its owners.
+///
+/// When more than one owner signature is required, the owners sign the bytes returned by
+/// `signing_data` and the signatures are passed along with the corresponding request.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum OwnerAction {
+    /// Insert or update permissions for a user.
+    SetUserPermissions {
+        /// User whose permissions are set
+        user: User,
+        /// New permissions of the user
+        permissions: PermissionSet,
+    },
+    /// Delete permissions for a user.
+    DelUserPermissions {
+        /// User whose permissions are deleted
+        user: User,
+    },
+    /// Replace the owners.
+    ChangeOwners {
+        /// New set of owners
+        new_owners: BTreeSet<PublicKey>,
+        /// New number of owner signatures required
+        threshold: u64,
+    },
+}
+
+impl OwnerAction {
+    /// Returns the bytes the owners have to sign to perform this action on the `MutableData`
+    /// with the given name and type tag, producing the given (incremented) version.
+    pub fn signing_data(&self, name: &XorName, tag: u64, version: u64) -> Vec<u8> {
+        unwrap!(serialisation::serialise(&(name, tag, self, version)))
+    }
+}
+
 /// Action performed on a single entry: insert, update or delete.
+///
+/// Inserts and updates can let the entry expire at a point in time, in seconds since the UNIX
+/// epoch. Updating or deleting an entry replaces its expiry, so an update without one makes the
+/// entry permanent again. See `MutableData::purge_expired`.
 #[derive(Hash, Debug, Eq, PartialEq, Clone, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum EntryAction {
-    /// Inserts a new entry
-    Ins(Value),
-    /// Updates an entry with a new value and version
-    Update(Value),
+    /// Inserts a new entry, with an optional expiry
+    Ins(Value, Option<u64>),
+    /// Updates an entry with a new value and version, with an optional expiry
+    Update(Value, Option<u64>),
     /// Deletes an entry by emptying its contents. Contains the version number
     Del(u64),
 }
@@ -174,26 +373,46 @@ impl EntryActions {
     }
 
     /// Insert a new key-value pair
-    pub fn ins(mut self, key: Vec<u8>, content: Vec<u8>, version: u64) -> Self {
-        let _ = self.actions.insert(
-            key,
-            EntryAction::Ins(Value {
-                entry_version: version,
-                content,
-            }),
-        );
-        self
+    pub fn ins(self, key: Vec<u8>, content: Vec<u8>, version: u64) -> Self {
+        self.ins_with_expiry(key, content, version, None)
     }
 
     /// Update existing key-value pair
-    pub fn update(mut self, key: Vec<u8>, content: Vec<u8>, version: u64) -> Self {
-        let _ = self.actions.insert(
-            key,
-            EntryAction::Update(Value {
-                entry_version: version,
-                content,
-            }),
-        );
+    pub fn update(self, key: Vec<u8>, content: Vec<u8>, version: u64) -> Self {
+        self.update_with_expiry(key, content, version, None)
+    }
+
+    /// Insert a new key-value pair which expires at `expiry`, in seconds since the UNIX epoch, if
+    /// given
+    pub fn ins_with_expiry(
+        mut self,
+        key: Vec<u8>,
+        content: Vec<u8>,
+        version: u64,
+        expiry: Option<u64>,
+    ) -> Self {
+        let value = Value {
+            entry_version: version,
+            content,
+        };
+        let _ = self.actions.insert(key, EntryAction::Ins(value, expiry));
+        self
+    }
+
+    /// Update existing key-value pair, letting it expire at `expiry`, in seconds since the UNIX
+    /// epoch, if given
+    pub fn update_with_expiry(
+        mut self,
+        key: Vec<u8>,
+        content: Vec<u8>,
+        version: u64,
+        expiry: Option<u64>,
+    ) -> Self {
+        let value = Value {
+            entry_version: version,
+            content,
+        };
+        let _ = self.actions.insert(key, EntryAction::Update(value, expiry));
         self
     }
 
@@ -202,6 +421,41 @@ impl EntryActions {
         let _ = self.actions.insert(key, EntryAction::Del(version));
         self
     }
+
+    /// Insert a new key-value pair into private `MutableData`, encrypting both with `enc_key`
+    pub fn ins_encrypted(
+        self,
+        key: &[u8],
+        content: &[u8],
+        version: u64,
+        enc_key: &MDataEncryptionKey,
+    ) -> Self {
+        self.ins(
+            enc_key.encrypt_key(key),
+            enc_key.encrypt_content(content),
+            version,
+        )
+    }
+
+    /// Update existing key-value pair in private `MutableData`, encrypting both with `enc_key`
+    pub fn update_encrypted(
+        self,
+        key: &[u8],
+        content: &[u8],
+        version: u64,
+        enc_key: &MDataEncryptionKey,
+    ) -> Self {
+        self.update(
+            enc_key.encrypt_key(key),
+            enc_key.encrypt_content(content),
+            version,
+        )
+    }
+
+    /// Delete existing key from private `MutableData`, encrypting it with `enc_key`
+    pub fn del_encrypted(self, key: &[u8], version: u64, enc_key: &MDataEncryptionKey) -> Self {
+        self.del(enc_key.encrypt_key(key), version)
+    }
 }
 
 impl Into<BTreeMap<Vec<u8>, EntryAction>> for EntryActions {
@@ -210,6 +464,86 @@ impl Into<BTreeMap<Vec<u8>, EntryAction>> for EntryActions {
     }
 }
 
+/// Selects a key-ordered page of `MutableData` entries.
+///
+/// The default range selects all entries. To fetch the next page, repeat the request with `start`
+/// set to the key returned alongside the current page.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntriesRange {
+    /// First key to list (inclusive). `None` starts at the lowest key.
+    pub start: Option<Vec<u8>>,
+    /// Key to stop listing at (exclusive). `None` lists up to the highest key.
+    pub end: Option<Vec<u8>>,
+    /// If set, only keys starting with this prefix are listed.
+    pub prefix: Option<Vec<u8>>,
+    /// Maximum number of entries in a page. `None` or zero means no limit.
+    pub limit: Option<u64>,
+}
+
+impl EntriesRange {
+    /// Constructs a range selecting all entries.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Starts the range at the given key (inclusive).
+    pub fn start(mut self, key: Vec<u8>) -> Self {
+        self.start = Some(key);
+        self
+    }
+
+    /// Ends the range at the given key (exclusive).
+    pub fn end(mut self, key: Vec<u8>) -> Self {
+        self.end = Some(key);
+        self
+    }
+
+    /// Restricts the range to keys starting with the given prefix.
+    pub fn prefix(mut self, prefix: Vec<u8>) -> Self {
+        self.prefix = Some(prefix);
+        self
+    }
+
+    /// Limits the number of entries in a page. A limit of zero means no limit, as an empty page
+    /// couldn't make progress.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// Changes to the entries of `MutableData` since a given data version.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MDataChanges {
+    /// Data version the changes lead up to.
+    pub data_version: u64,
+    /// Entries inserted since the given version, with their current values.
+    pub inserted: BTreeMap<Vec<u8>, Value>,
+    /// Entries updated since the given version, with their new values.
+    pub updated: BTreeMap<Vec<u8>, Value>,
+    /// Tombstones of entries deleted since the given version. A tombstone has empty content and
+    /// the entry version of the deletion.
+    pub deleted: BTreeMap<Vec<u8>, Value>,
+    /// Times the inserted and updated entries expire at, in seconds since the UNIX epoch.
+    pub expiries: BTreeMap<Vec<u8>, u64>,
+}
+
+// Data versions at which an entry was inserted and last changed, and whether that change was a
+// deletion. Entries the data was created with are recorded as inserted at data version zero.
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+struct ChangeRecord {
+    inserted: u64,
+    changed: u64,
+    deleted: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Change {
+    Insert,
+    Update,
+    Delete,
+}
+
 impl MutableData {
     /// Creates a new MutableData
     #[allow(clippy::new_ret_no_self)]
@@ -223,20 +557,74 @@ impl MutableData {
         let md = MutableData {
             name,
             tag,
+            changes: initial_changes(&data),
             data,
             permissions,
             version: 0,
             owners,
+            owners_threshold: 1,
+            history: BTreeMap::new(),
+            history_len: 0,
+            data_version: 0,
+            private: false,
+            expiries: BTreeMap::new(),
+            purged_at: 0,
         };
 
         md.validate()?;
         Ok(md)
     }
 
+    /// Sets the number of owner signatures required to mutate permissions or change the owners.
+    /// It must be at least one and must not exceed the number of owners.
+    pub fn with_owners_threshold(mut self, threshold: u64) -> Result<MutableData, ClientError> {
+        validate_owners(&self.owners, threshold)?;
+        self.owners_threshold = threshold;
+        Ok(self)
+    }
+
+    /// Enables retaining the last `history_len` previous values of every entry. The history counts
+    /// towards `MAX_MUTABLE_DATA_SIZE_IN_BYTES`. Passing zero disables the history again.
+    pub fn with_entry_history(mut self, history_len: u64) -> Result<MutableData, ClientError> {
+        if history_len > MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN {
+            return Err(ClientError::InvalidOperation);
+        }
+        self.history_len = history_len;
+        if history_len == 0 {
+            self.history.clear();
+        }
+        for versions in self.history.values_mut() {
+            truncate_history(versions, history_len);
+        }
+        Ok(self)
+    }
+
+    /// Marks this data as private. Its entries are expected to be encrypted client-side with a
+    /// `MDataEncryptionKey`, and reading them requires the `Read` permission.
+    pub fn with_private_entries(mut self) -> MutableData {
+        self.private = true;
+        self
+    }
+
     /// Validate this data.
     pub fn validate(&self) -> Result<(), ClientError> {
-        if self.owners.len() > 1 {
-            return Err(ClientError::InvalidOwners);
+        validate_owners(&self.owners, self.owners_threshold)?;
+        if self.history_len > MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN
+            || self
+                .history
+                .values()
+                .any(|versions| versions.len() > self.history_len as usize)
+        {
+            return Err(ClientError::InvalidOperation);
+        }
+        if self
+            .expiries
+            .keys()
+            .chain(self.changes.keys())
+            .chain(self.history.keys())
+            .any(|key| !self.data.contains_key(key))
+        {
+            return Err(ClientError::InvalidOperation);
         }
         if self.data.len() >= (MAX_MUTABLE_DATA_ENTRIES + 1) as usize {
             return Err(ClientError::TooManyEntries);
@@ -249,6 +637,16 @@ impl MutableData {
         Ok(())
     }
 
+    /// Validates this data, and checks its entries against the validator for its type tag, if
+    /// any. Vaults check data put by clients this way.
+    pub fn validate_with_validators(
+        &self,
+        validators: &TypeTagValidators,
+    ) -> Result<(), ClientError> {
+        self.validate()?;
+        validators.validate(self)
+    }
+
     /// Returns the shell of this data. Shell contains the same fields as the data itself,
     /// except the entries.
     pub fn shell(&self) -> MutableData {
@@ -259,6 +657,14 @@ impl MutableData {
             permissions: self.permissions.clone(),
             version: self.version,
             owners: self.owners.clone(),
+            owners_threshold: self.owners_threshold,
+            history: BTreeMap::new(),
+            history_len: self.history_len,
+            data_version: self.data_version,
+            changes: BTreeMap::new(),
+            private: self.private,
+            expiries: BTreeMap::new(),
+            purged_at: self.purged_at,
         }
     }
 
@@ -277,33 +683,213 @@ impl MutableData {
         self.version
     }
 
+    /// Returns the current data version of this MutableData. It is increased for every change
+    /// in the entries, the permissions or the owners.
+    pub fn data_version(&self) -> u64 {
+        self.data_version
+    }
+
+    /// Returns true if this data is private
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
     /// Returns the owner keys
     pub fn owners(&self) -> &BTreeSet<PublicKey> {
         &self.owners
     }
 
-    /// Returns a value by the given key
+    /// Returns the number of owner signatures required to mutate permissions or change the owners
+    pub fn owners_threshold(&self) -> u64 {
+        self.owners_threshold
+    }
+
+    /// Returns a value by the given key, unless it has expired
     pub fn get(&self, key: &[u8]) -> Option<&Value> {
+        if self.is_expired(key) {
+            return None;
+        }
         self.data.get(key)
     }
 
-    /// Returns keys of all entries
+    /// Returns the time the entry with the given key expires at, in seconds since the UNIX epoch,
+    /// if it expires at all
+    pub fn entry_expiry(&self, key: &[u8]) -> Option<u64> {
+        self.expiries.get(key).cloned()
+    }
+
+    /// Returns the time agreed by the section, in seconds since the UNIX epoch, the expired
+    /// entries were last purged at. Entries count as expired once their expiry is reached by this
+    /// time, never by the local clock.
+    pub fn purged_at(&self) -> u64 {
+        self.purged_at
+    }
+
+    /// Returns keys of all entries which haven't expired
     pub fn keys(&self) -> BTreeSet<&Vec<u8>> {
-        self.data.keys().collect()
+        self.live_entries().map(|(key, _)| key).collect()
     }
 
-    /// Returns values of all entries
+    /// Returns values of all entries which haven't expired
     pub fn values(&self) -> Vec<&Value> {
-        self.data.values().collect()
+        self.live_entries().map(|(_, value)| value).collect()
+    }
+
+    /// Returns all entries which haven't expired
+    pub fn entries(&self) -> BTreeMap<Vec<u8>, Value> {
+        self.live_entries()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Returns the entry with the given plain key, decrypted with `enc_key`. Deleted entries
+    /// are returned with empty content.
+    pub fn get_decrypted(
+        &self,
+        key: &[u8],
+        enc_key: &MDataEncryptionKey,
+    ) -> Result<Option<Value>, ClientError> {
+        match self.get(&enc_key.encrypt_key(key)) {
+            Some(value) => Ok(Some(decrypt_value(value, enc_key)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns all entries which haven't expired, with both keys and values decrypted with
+    /// `enc_key`. Deleted entries are returned with empty content.
+    pub fn decrypted_entries(
+        &self,
+        enc_key: &MDataEncryptionKey,
+    ) -> Result<BTreeMap<Vec<u8>, Value>, ClientError> {
+        let mut entries = BTreeMap::new();
+        for (key, value) in self.live_entries() {
+            let _ = entries.insert(enc_key.decrypt_key(key)?, decrypt_value(value, enc_key)?);
+        }
+        Ok(entries)
+    }
+
+    /// Returns an error if `src`, the source of a request reading the entries of this data, isn't
+    /// allowed to read them. The entries of private data can only be read by clients whose key is
+    /// allowed `Action::Read`, and by other nodes, e.g. to replicate the data. Vaults have to check
+    /// this before responding to any request for the entries.
+    pub fn check_read_permission(&self, src: &Authority<XorName>) -> Result<(), ClientError> {
+        let requester = match *src {
+            Authority::Client { ref client_id, .. } => *client_id.signing_public_key(),
+            _ => return Ok(()),
+        };
+        if !self.private || self.is_action_allowed(requester, Action::Read, None) {
+            Ok(())
+        } else {
+            Err(ClientError::AccessDenied)
+        }
+    }
+
+    /// Returns the number of previous values retained per entry
+    pub fn entry_history_len(&self) -> u64 {
+        self.history_len
+    }
+
+    /// Returns the previous values of the entry with the given key, oldest first, or `None` if
+    /// there is no such entry.
+    pub fn entry_history(&self, key: &[u8]) -> Option<&[Value]> {
+        if !self.data.contains_key(key) {
+            return None;
+        }
+        Some(self.history.get(key).map_or(&[][..], |versions| &versions[..]))
+    }
+
+    /// Returns a page of entries in the given range, together with the key the next page starts
+    /// at, or `None` if this is the last page.
+    pub fn entries_range(
+        &self,
+        range: &EntriesRange,
+    ) -> (BTreeMap<Vec<u8>, Value>, Option<Vec<u8>>) {
+        let (page, next) = self.page(range);
+        let entries = page
+            .into_iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        (entries, next)
+    }
+
+    /// Returns a page of keys in the given range, together with the key the next page starts at,
+    /// or `None` if this is the last page.
+    pub fn keys_range(&self, range: &EntriesRange) -> (BTreeSet<Vec<u8>>, Option<Vec<u8>>) {
+        let (page, next) = self.page(range);
+        (page.into_iter().map(|(key, _)| key.clone()).collect(), next)
+    }
+
+    /// Returns a page of values in the given range, together with the key the next page starts
+    /// at, or `None` if this is the last page.
+    pub fn values_range(&self, range: &EntriesRange) -> (Vec<Value>, Option<Vec<u8>>) {
+        let (page, next) = self.page(range);
+        (page.into_iter().map(|(_, value)| value.clone()).collect(), next)
+    }
+
+    /// Returns the entries inserted, updated and deleted after the data version `since`, with
+    /// their current values. Since zero, all entries are returned, including those the data was
+    /// created with, so a replica can be built from the shell.
+    pub fn changes_since(&self, since: u64) -> Result<MDataChanges, ClientError> {
+        if since > self.data_version {
+            return Err(ClientError::InvalidSuccessor(self.data_version));
+        }
+
+        let mut changes = MDataChanges {
+            data_version: self.data_version,
+            ..Default::default()
+        };
+        for (key, record) in &self.changes {
+            if since > 0 && record.changed <= since {
+                continue;
+            }
+            let value = match self.data.get(key) {
+                Some(value) => value.clone(),
+                None => continue,
+            };
+            let _ = if record.deleted {
+                changes.deleted.insert(key.clone(), value)
+            } else if since == 0 || record.inserted > since {
+                changes.inserted.insert(key.clone(), value)
+            } else {
+                changes.updated.insert(key.clone(), value)
+            };
+            if let Some(&expiry) = self.expiries.get(key) {
+                let _ = changes.expiries.insert(key.clone(), expiry);
+            }
+        }
+
+        Ok(changes)
     }
 
-    /// Returns all entries
-    pub fn entries(&self) -> &BTreeMap<Vec<u8>, Value> {
-        &self.data
+    /// Applies changes fetched from another replica without performing any validation, except the
+    /// version check of every entry (new version must be higher than the existing one).
+    pub fn apply_changes(&mut self, changes: MDataChanges) {
+        let data_version = cmp::max(self.data_version, changes.data_version);
+        let MDataChanges {
+            inserted,
+            updated,
+            deleted,
+            expiries,
+            ..
+        } = changes;
+
+        for (key, value) in inserted.into_iter().chain(updated) {
+            if self.set_entry_without_validation(key.clone(), value, data_version, false) {
+                let expiry = expiries.get(&key).cloned();
+                set_expiry(&mut self.expiries, key, expiry);
+            }
+        }
+        for (key, value) in deleted {
+            let _ = self.set_entry_without_validation(key, value, data_version, true);
+        }
+        self.data_version = data_version;
     }
 
     /// Removes and returns all entries
     pub fn take_entries(&mut self) -> BTreeMap<Vec<u8>, Value> {
+        self.history.clear();
+        self.expiries.clear();
+        self.changes.clear();
         mem::replace(&mut self.data, BTreeMap::new())
     }
 
@@ -318,11 +904,11 @@ impl MutableData {
             (BTreeMap::new(), BTreeMap::new(), BTreeMap::new()),
             |(mut insert, mut update, mut delete), (key, item)| {
                 match item {
-                    EntryAction::Ins(value) => {
-                        let _ = insert.insert(key, value);
+                    EntryAction::Ins(value, expiry) => {
+                        let _ = insert.insert(key, (value, expiry));
                     }
-                    EntryAction::Update(value) => {
-                        let _ = update.insert(key, value);
+                    EntryAction::Update(value, expiry) => {
+                        let _ = update.insert(key, (value, expiry));
                     }
                     EntryAction::Del(version) => {
                         let _ = delete.insert(key, version);
@@ -332,17 +918,22 @@ impl MutableData {
             },
         );
 
-        if (!insert.is_empty() && !self.is_action_allowed(requester, Action::Insert))
-            || (!update.is_empty() && !self.is_action_allowed(requester, Action::Update))
-            || (!delete.is_empty() && !self.is_action_allowed(requester, Action::Delete))
+        let allowed = |action, key: &[u8]| self.is_action_allowed(requester, action, Some(key));
+        if !insert.keys().all(|key| allowed(Action::Insert, key))
+            || !update.keys().all(|key| allowed(Action::Update, key))
+            || !delete.keys().all(|key| allowed(Action::Delete, key))
         {
             return Err(ClientError::AccessDenied);
         }
 
+        let data_version = self.data_version + 1;
         let mut new_data = self.data.clone();
+        let mut new_expiries = self.expiries.clone();
         let mut errors = BTreeMap::new();
+        let mut replaced = Vec::new();
+        let mut changed = Vec::new();
 
-        for (key, val) in insert {
+        for (key, (val, expiry)) in insert {
             match new_data.entry(key) {
                 Entry::Occupied(entry) => {
                     let _ = errors.insert(
@@ -351,17 +942,22 @@ impl MutableData {
                     );
                 }
                 Entry::Vacant(entry) => {
+                    changed.push((entry.key().clone(), Change::Insert));
+                    set_expiry(&mut new_expiries, entry.key().clone(), expiry);
                     let _ = entry.insert(val);
                 }
             }
         }
 
-        for (key, val) in update {
+        for (key, (val, expiry)) in update {
             match new_data.entry(key) {
                 Entry::Occupied(mut entry) => {
                     let current_version = entry.get().entry_version;
                     if val.entry_version == current_version + 1 {
-                        let _ = entry.insert(val);
+                        let old_value = entry.insert(val);
+                        replaced.push((entry.key().clone(), old_value));
+                        changed.push((entry.key().clone(), Change::Update));
+                        set_expiry(&mut new_expiries, entry.key().clone(), expiry);
                     } else {
                         let _ = errors.insert(
                             entry.key().clone(),
@@ -383,10 +979,13 @@ impl MutableData {
                 Entry::Occupied(mut entry) => {
                     let current_version = entry.get().entry_version;
                     if version == current_version + 1 {
-                        let _ = entry.insert(Value {
+                        let old_value = entry.insert(Value {
                             content: Vec::new(),
                             entry_version: version,
                         });
+                        replaced.push((entry.key().clone(), old_value));
+                        changed.push((entry.key().clone(), Change::Delete));
+                        let _ = new_expiries.remove(entry.key());
                     } else {
                         let _ = errors.insert(
                             entry.key().clone(),
@@ -408,13 +1007,109 @@ impl MutableData {
             return Err(ClientError::TooManyEntries);
         }
 
+        let mut new_history = self.history.clone();
+        for (key, old_value) in replaced {
+            push_history(&mut new_history, self.history_len, key, old_value);
+        }
+
+        let mut new_changes = self.changes.clone();
+        for (key, change) in changed {
+            record_change(&mut new_changes, key, data_version, change);
+        }
+
         let old_data = mem::replace(&mut self.data, new_data);
+        let old_history = mem::replace(&mut self.history, new_history);
+        let old_changes = mem::replace(&mut self.changes, new_changes);
+        let old_expiries = mem::replace(&mut self.expiries, new_expiries);
 
         if !self.validate_size() {
             self.data = old_data;
+            self.history = old_history;
+            self.changes = old_changes;
+            self.expiries = old_expiries;
             return Err(ClientError::DataTooLarge);
         }
 
+        self.data_version = data_version;
+        Ok(())
+    }
+
+    /// Mutates entries of several `MutableData` in bulk, all or nothing.
+    ///
+    /// `actions` maps the name and type tag of each target to the actions to be performed on it.
+    /// Every target must be among `data`. If the actions on any target fail, none of the targets
+    /// is changed and the error for each failed target is returned.
+    pub fn mutate_batch<'a, I>(
+        data: I,
+        mut actions: BTreeMap<(XorName, u64), BTreeMap<Vec<u8>, EntryAction>>,
+        requester: PublicKey,
+    ) -> Result<(), ClientError>
+    where
+        I: IntoIterator<Item = &'a mut MutableData>,
+    {
+        let mut mutated = Vec::new();
+        let mut errors = BTreeMap::new();
+
+        for md in data {
+            let id = (md.name, md.tag);
+            if let Some(md_actions) = actions.remove(&id) {
+                let mut new_md = md.clone();
+                match new_md.mutate_entries(md_actions, requester) {
+                    Ok(()) => mutated.push((md, new_md)),
+                    Err(error) => {
+                        let _ = errors.insert(id, error);
+                    }
+                }
+            }
+        }
+
+        for id in actions.keys() {
+            let _ = errors.insert(*id, ClientError::NoSuchData);
+        }
+
+        if !errors.is_empty() {
+            return Err(ClientError::InvalidBatchMutations(errors));
+        }
+
+        for (md, new_md) in mutated {
+            *md = new_md;
+        }
+
+        Ok(())
+    }
+
+    /// Like `mutate_entries`, but also rejects the mutation if it leaves the entries invalid for
+    /// the validator of the type tag, if any. Vaults apply client mutations this way.
+    pub fn mutate_entries_with_validators(
+        &mut self,
+        validators: &TypeTagValidators,
+        actions: BTreeMap<Vec<u8>, EntryAction>,
+        requester: PublicKey,
+    ) -> Result<(), ClientError> {
+        validators.mutate_entries(self, actions, requester)
+    }
+
+    /// Purges the entries which have expired at `now`, a time agreed by the section in seconds
+    /// since the UNIX epoch, then mutates the entries as `mutate_entries` does. If the mutation
+    /// fails, nothing is purged either. Vaults apply client mutations this way, so that every
+    /// replica frees the keys of expired entries at the same point.
+    pub fn mutate_entries_at(
+        &mut self,
+        actions: BTreeMap<Vec<u8>, EntryAction>,
+        requester: PublicKey,
+        now: u64,
+    ) -> Result<(), ClientError> {
+        let now = cmp::max(self.purged_at, now);
+        if self.expiries.values().all(|&expiry| expiry > now) {
+            self.mutate_entries(actions, requester)?;
+            self.purged_at = now;
+            return Ok(());
+        }
+
+        let mut new_md = self.clone();
+        let _ = new_md.purge_expired(now);
+        new_md.mutate_entries(actions, requester)?;
+        *self = new_md;
         Ok(())
     }
 
@@ -423,33 +1118,44 @@ impl MutableData {
     /// For updates and deletes, the mutation is performed only if he entry version
     /// of the action is higher than the current version of the entry.
     pub fn mutate_entries_without_validation(&mut self, actions: BTreeMap<Vec<u8>, EntryAction>) {
+        let data_version = self.data_version + 1;
+        let mut changed = false;
+
         for (key, action) in actions {
             match action {
-                EntryAction::Ins(new_value) => {
+                EntryAction::Ins(new_value, expiry) => {
+                    record_change(&mut self.changes, key.clone(), data_version, Change::Insert);
+                    set_expiry(&mut self.expiries, key.clone(), expiry);
                     let _ = self.data.insert(key, new_value);
+                    changed = true;
                 }
-                EntryAction::Update(new_value) => match self.data.entry(key) {
-                    Entry::Occupied(mut entry) => {
-                        if new_value.entry_version > entry.get().entry_version {
-                            let _ = entry.insert(new_value);
-                        }
+                EntryAction::Update(new_value, expiry) => {
+                    if self.set_entry_without_validation(
+                        key.clone(),
+                        new_value,
+                        data_version,
+                        false,
+                    ) {
+                        set_expiry(&mut self.expiries, key, expiry);
+                        changed = true;
                     }
-                    Entry::Vacant(entry) => {
-                        let _ = entry.insert(new_value);
-                    }
-                },
+                }
                 EntryAction::Del(new_version) => {
-                    if let Entry::Occupied(mut entry) = self.data.entry(key) {
-                        if new_version > entry.get().entry_version {
-                            let _ = entry.insert(Value {
-                                content: Vec::new(),
-                                entry_version: new_version,
-                            });
-                        }
+                    if self.data.contains_key(&key) {
+                        let tombstone = Value {
+                            content: Vec::new(),
+                            entry_version: new_version,
+                        };
+                        changed |=
+                            self.set_entry_without_validation(key, tombstone, data_version, true);
                     }
                 }
             }
         }
+
+        if changed {
+            self.data_version = data_version;
+        }
     }
 
     /// Mutates single entry without performing any validations, except the version
@@ -458,20 +1164,47 @@ impl MutableData {
     /// Returns true if the version check passed and the entry was mutated,
     /// false otherwise.
     pub fn mutate_entry_without_validation(&mut self, key: Vec<u8>, value: Value) -> bool {
-        match self.data.entry(key) {
-            Entry::Occupied(mut entry) => {
-                if value.entry_version > entry.get().entry_version {
-                    let _ = entry.insert(value);
-                    true
-                } else {
-                    false
-                }
-            }
-            Entry::Vacant(entry) => {
-                let _ = entry.insert(value);
-                true
-            }
+        let data_version = self.data_version + 1;
+        if self.set_entry_without_validation(key, value, data_version, false) {
+            self.data_version = data_version;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Deletes the entries which have expired at `now`, a time agreed by the section in seconds
+    /// since the UNIX epoch, leaving tombstones as `EntryAction::Del` does. The purge time never
+    /// goes back, so an earlier `now` purges the entries expired by the last purge time. If any
+    /// entry was purged, the data version is increased and true is returned.
+    ///
+    /// Vaults purge as part of every mutation, see `mutate_entries_at`, and may purge in between
+    /// too. As long as they use the time agreed by their section rather than their own clock,
+    /// their replicas stay identical.
+    pub fn purge_expired(&mut self, now: u64) -> bool {
+        self.purged_at = cmp::max(self.purged_at, now);
+        let purged_at = self.purged_at;
+        let expired: Vec<_> = self
+            .expiries
+            .iter()
+            .filter(|&(_, &expiry)| expiry <= purged_at)
+            .map(|(key, _)| key.clone())
+            .collect();
+        if expired.is_empty() {
+            return false;
+        }
+
+        let data_version = self.data_version + 1;
+        for key in expired {
+            let entry_version = self.data.get(&key).map_or(0, |value| value.entry_version + 1);
+            let tombstone = Value {
+                content: Vec::new(),
+                entry_version,
+            };
+            let _ = self.set_entry_without_validation(key, tombstone, data_version, true);
         }
+        self.data_version = data_version;
+        true
     }
 
     /// Gets a complete list of permissions
@@ -479,6 +1212,15 @@ impl MutableData {
         &self.permissions
     }
 
+    /// Returns the users whose permissions have expired at the current data version.
+    pub fn expired_permissions(&self) -> BTreeSet<User> {
+        self.permissions
+            .iter()
+            .filter(|&(_, perms)| perms.is_expired(self.data_version))
+            .map(|(user, _)| *user)
+            .collect()
+    }
+
     /// Gets a list of permissions for the provided user.
     pub fn user_permissions(&self, user: &User) -> Result<&PermissionSet, ClientError> {
         self.permissions.get(user).ok_or(ClientError::NoSuchKey)
@@ -492,9 +1234,30 @@ impl MutableData {
         version: u64,
         requester: PublicKey,
     ) -> Result<(), ClientError> {
-        if !self.is_action_allowed(requester, Action::ManagePermissions) {
-            return Err(ClientError::AccessDenied);
-        }
+        self.check_manage_permissions(requester)?;
+        self.insert_user_permissions(user, permissions, version)
+    }
+
+    /// Insert or update permissions for the provided user on behalf of the owners. At least
+    /// `owners_threshold` owners must have signed the corresponding `OwnerAction`.
+    pub fn set_user_permissions_by_owners(
+        &mut self,
+        user: User,
+        permissions: PermissionSet,
+        version: u64,
+        signatures: &BTreeMap<PublicKey, Signature>,
+    ) -> Result<(), ClientError> {
+        let action = OwnerAction::SetUserPermissions { user, permissions };
+        self.verify_owner_signatures(&action, version, signatures)?;
+        self.insert_user_permissions(user, permissions, version)
+    }
+
+    fn insert_user_permissions(
+        &mut self,
+        user: User,
+        permissions: PermissionSet,
+        version: u64,
+    ) -> Result<(), ClientError> {
         if version != self.version + 1 {
             return Err(ClientError::InvalidSuccessor(self.version));
         }
@@ -508,6 +1271,7 @@ impl MutableData {
             return Err(ClientError::DataTooLarge);
         }
         self.version = version;
+        self.data_version += 1;
         Ok(())
     }
 
@@ -524,6 +1288,7 @@ impl MutableData {
 
         let _ = self.permissions.insert(user, permissions);
         self.version = version;
+        self.data_version += 1;
         true
     }
 
@@ -534,9 +1299,24 @@ impl MutableData {
         version: u64,
         requester: PublicKey,
     ) -> Result<(), ClientError> {
-        if !self.is_action_allowed(requester, Action::ManagePermissions) {
-            return Err(ClientError::AccessDenied);
-        }
+        self.check_manage_permissions(requester)?;
+        self.remove_user_permissions(user, version)
+    }
+
+    /// Delete permissions for the provided user on behalf of the owners. At least
+    /// `owners_threshold` owners must have signed the corresponding `OwnerAction`.
+    pub fn del_user_permissions_by_owners(
+        &mut self,
+        user: &User,
+        version: u64,
+        signatures: &BTreeMap<PublicKey, Signature>,
+    ) -> Result<(), ClientError> {
+        let action = OwnerAction::DelUserPermissions { user: *user };
+        self.verify_owner_signatures(&action, version, signatures)?;
+        self.remove_user_permissions(user, version)
+    }
+
+    fn remove_user_permissions(&mut self, user: &User, version: u64) -> Result<(), ClientError> {
         if version != self.version + 1 {
             return Err(ClientError::InvalidSuccessor(self.version));
         }
@@ -545,6 +1325,7 @@ impl MutableData {
         }
         let _ = self.permissions.remove(user);
         self.version = version;
+        self.data_version += 1;
         Ok(())
     }
 
@@ -556,17 +1337,24 @@ impl MutableData {
 
         let _ = self.permissions.remove(user);
         self.version = version;
+        self.data_version += 1;
         true
     }
 
-    /// Change owner of the mutable data.
+    /// Change owner of the mutable data. Only allowed when a single owner signature suffices,
+    /// otherwise `change_owners` has to be used.
     pub fn change_owner(&mut self, new_owner: PublicKey, version: u64) -> Result<(), ClientError> {
         if version != self.version + 1 {
             return Err(ClientError::InvalidSuccessor(self.version));
         }
+        if self.owners_threshold > 1 {
+            return Err(ClientError::InsufficientSignatures(self.owners_threshold));
+        }
         self.owners.clear();
         let _ = self.owners.insert(new_owner);
+        self.owners_threshold = 1;
         self.version = version;
+        self.data_version += 1;
         Ok(())
     }
 
@@ -578,13 +1366,78 @@ impl MutableData {
 
         self.owners.clear();
         let _ = self.owners.insert(new_owner);
+        self.owners_threshold = 1;
         self.version = version;
+        self.data_version += 1;
         true
     }
 
-    /// Return the size of this data after serialisation.
-    pub fn serialised_size(&self) -> u64 {
-        serialisation::serialised_size(self)
+    /// Replace the owners and the owners threshold of the mutable data. At least
+    /// `owners_threshold` of the current owners must have signed the corresponding `OwnerAction`.
+    pub fn change_owners(
+        &mut self,
+        new_owners: BTreeSet<PublicKey>,
+        new_threshold: u64,
+        version: u64,
+        signatures: &BTreeMap<PublicKey, Signature>,
+    ) -> Result<(), ClientError> {
+        if version != self.version + 1 {
+            return Err(ClientError::InvalidSuccessor(self.version));
+        }
+        validate_owners(&new_owners, new_threshold)?;
+        let action = OwnerAction::ChangeOwners {
+            new_owners: new_owners.clone(),
+            threshold: new_threshold,
+        };
+        self.verify_owner_signatures(&action, version, signatures)?;
+        self.owners = new_owners;
+        self.owners_threshold = new_threshold;
+        self.version = version;
+        self.data_version += 1;
+        Ok(())
+    }
+
+    /// Replace the owners and the owners threshold without performing any validation.
+    pub fn change_owners_without_validation(
+        &mut self,
+        new_owners: BTreeSet<PublicKey>,
+        new_threshold: u64,
+        version: u64,
+    ) -> bool {
+        if version <= self.version {
+            return false;
+        }
+
+        self.owners = new_owners;
+        self.owners_threshold = new_threshold;
+        self.version = version;
+        self.data_version += 1;
+        true
+    }
+
+    /// Checks that `signatures` contains valid signatures of the given owner action from at least
+    /// `owners_threshold` owners. Signatures from keys which aren't owners are rejected.
+    pub fn verify_owner_signatures(
+        &self,
+        action: &OwnerAction,
+        version: u64,
+        signatures: &BTreeMap<PublicKey, Signature>,
+    ) -> Result<(), ClientError> {
+        let signed_data = action.signing_data(&self.name, self.tag, version);
+        for (key, signature) in signatures {
+            if !self.owners.contains(key) || !sign::verify_detached(signature, &signed_data, key) {
+                return Err(ClientError::InvalidSignature);
+            }
+        }
+        if (signatures.len() as u64) < self.owners_threshold {
+            return Err(ClientError::InsufficientSignatures(self.owners_threshold));
+        }
+        Ok(())
+    }
+
+    /// Return the size of this data after serialisation.
+    pub fn serialised_size(&self) -> u64 {
+        serialisation::serialised_size(self)
     }
 
     /// Return true if the size is valid
@@ -592,26 +1445,203 @@ impl MutableData {
         self.serialised_size() <= MAX_MUTABLE_DATA_SIZE_IN_BYTES
     }
 
-    fn check_anyone_permissions(&self, action: Action) -> bool {
+    // Inserts the entry, or replaces it if the new value has a higher version, and records the
+    // change at `data_version`. Returns true if the entry was changed.
+    fn set_entry_without_validation(
+        &mut self,
+        key: Vec<u8>,
+        value: Value,
+        data_version: u64,
+        deleted: bool,
+    ) -> bool {
+        let change = match self.data.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                if value.entry_version <= entry.get().entry_version {
+                    return false;
+                }
+                let old_value = entry.insert(value);
+                push_history(&mut self.history, self.history_len, key.clone(), old_value);
+                Change::Update
+            }
+            Entry::Vacant(entry) => {
+                let _ = entry.insert(value);
+                Change::Insert
+            }
+        };
+
+        let change = if deleted {
+            let _ = self.expiries.remove(&key);
+            Change::Delete
+        } else {
+            change
+        };
+        record_change(&mut self.changes, key, data_version, change);
+        true
+    }
+
+    // Has the entry with the given key expired by the time the data was last purged at?
+    fn is_expired(&self, key: &[u8]) -> bool {
+        self.expiries
+            .get(key)
+            .map_or(false, |&expiry| expiry <= self.purged_at)
+    }
+
+    // Iterates over the entries which haven't expired.
+    fn live_entries<'a>(&'a self) -> impl Iterator<Item = (&'a Vec<u8>, &'a Value)> + 'a {
+        self.data.iter().filter(move |&(key, _)| !self.is_expired(key))
+    }
+
+    fn page(&self, range: &EntriesRange) -> (Vec<(&Vec<u8>, &Value)>, Option<Vec<u8>>) {
+        // Keys sharing a prefix are contiguous, so listing can start at the larger of `start` and
+        // `prefix` and stop at the first key out of range.
+        let lower = cmp::max(range.start.as_ref(), range.prefix.as_ref())
+            .map_or(Bound::Unbounded, |key| Bound::Included(key.clone()));
+        let mut iter = self
+            .data
+            .range((lower, Bound::Unbounded))
+            .take_while(|&(key, _)| {
+                range.end.as_ref().map_or(true, |end| key < end)
+                    && range
+                        .prefix
+                        .as_ref()
+                        .map_or(true, |prefix| key.starts_with(prefix))
+            })
+            .filter(|&(key, _)| !self.is_expired(key));
+
+        let limit = range
+            .limit
+            .filter(|&limit| limit > 0)
+            .map_or(usize::MAX, |limit| limit as usize);
+        let page = iter.by_ref().take(limit).collect();
+        let next = iter.next().map(|(key, _)| key.clone());
+        (page, next)
+    }
+
+    fn check_anyone_permissions(&self, action: Action, key: Option<&[u8]>) -> bool {
         match self.permissions.get(&User::Anyone) {
             None => false,
-            Some(perms) => perms.is_allowed(action).unwrap_or(false),
+            Some(perms) => self.check_permissions(perms, action, key).unwrap_or(false),
+        }
+    }
+
+    fn check_permissions(
+        &self,
+        perms: &PermissionSet,
+        action: Action,
+        key: Option<&[u8]>,
+    ) -> Option<bool> {
+        if perms.is_expired(self.data_version) {
+            return None;
+        }
+        match key {
+            Some(key) => perms.is_allowed_for_key(action, key),
+            None => perms.is_allowed(action),
         }
     }
 
-    fn is_action_allowed(&self, requester: PublicKey, action: Action) -> bool {
-        if self.owners.contains(&requester) {
+    // `key` is the key of the entry the action is performed on, if any.
+    // Checks that `requester` alone may manage permissions. An owner of data needing more than
+    // one owner signature has to go through the `*_by_owners` methods instead.
+    fn check_manage_permissions(&self, requester: PublicKey) -> Result<(), ClientError> {
+        if self.is_action_allowed(requester, Action::ManagePermissions, None) {
+            Ok(())
+        } else if self.owners.contains(&requester) {
+            Err(ClientError::InsufficientSignatures(self.owners_threshold))
+        } else {
+            Err(ClientError::AccessDenied)
+        }
+    }
+
+    fn is_action_allowed(&self, requester: PublicKey, action: Action, key: Option<&[u8]>) -> bool {
+        // With more than one required owner signature, a single owner can't manage permissions.
+        if self.owners.contains(&requester)
+            && (action != Action::ManagePermissions || self.owners_threshold <= 1)
+        {
             return true;
         }
         match self.permissions.get(&User::Key(requester)) {
-            Some(perms) => perms
-                .is_allowed(action)
-                .unwrap_or_else(|| self.check_anyone_permissions(action)),
-            None => self.check_anyone_permissions(action),
+            Some(perms) => self
+                .check_permissions(perms, action, key)
+                .unwrap_or_else(|| self.check_anyone_permissions(action, key)),
+            None => self.check_anyone_permissions(action, key),
         }
     }
 }
 
+// The owners threshold must be at least one and, unless there are no owners at all, must not exceed
+// the number of owners.
+fn validate_owners(owners: &BTreeSet<PublicKey>, threshold: u64) -> Result<(), ClientError> {
+    if threshold == 0 || (!owners.is_empty() && threshold > owners.len() as u64) {
+        return Err(ClientError::InvalidOwners);
+    }
+    Ok(())
+}
+
+// Appends `value` to the history of the entry with the given key, dropping the oldest values
+// beyond `history_len`.
+fn push_history(
+    history: &mut BTreeMap<Vec<u8>, Vec<Value>>,
+    history_len: u64,
+    key: Vec<u8>,
+    value: Value,
+) {
+    if history_len == 0 {
+        return;
+    }
+    let versions = history.entry(key).or_insert_with(Vec::new);
+    versions.push(value);
+    truncate_history(versions, history_len);
+}
+
+// Decrypts the content of a value, leaving the empty content of deleted entries as it is.
+fn decrypt_value(value: &Value, enc_key: &MDataEncryptionKey) -> Result<Value, ClientError> {
+    let content = if value.content.is_empty() {
+        Vec::new()
+    } else {
+        enc_key.decrypt_content(&value.content)?
+    };
+    Ok(Value {
+        content,
+        entry_version: value.entry_version,
+    })
+}
+
+// Records the given entries as inserted at data version zero.
+fn initial_changes(data: &BTreeMap<Vec<u8>, Value>) -> BTreeMap<Vec<u8>, ChangeRecord> {
+    data.keys()
+        .map(|key| (key.clone(), ChangeRecord::default()))
+        .collect()
+}
+
+// Records a change of the entry with the given key at `data_version`.
+fn record_change(
+    changes: &mut BTreeMap<Vec<u8>, ChangeRecord>,
+    key: Vec<u8>,
+    data_version: u64,
+    change: Change,
+) {
+    let record = changes.entry(key).or_insert_with(ChangeRecord::default);
+    if change == Change::Insert {
+        record.inserted = data_version;
+    }
+    record.changed = data_version;
+    record.deleted = change == Change::Delete;
+}
+
+// Sets the expiry of the entry with the given key, or makes the entry permanent.
+fn set_expiry(expiries: &mut BTreeMap<Vec<u8>, u64>, key: Vec<u8>, expiry: Option<u64>) {
+    let _ = match expiry {
+        Some(expiry) => expiries.insert(key, expiry),
+        None => expiries.remove(&key),
+    };
+}
+
+// Drops the oldest values so that at most `history_len` remain.
+fn truncate_history(versions: &mut Vec<Value>, history_len: u64) {
+    let excess = versions.len().saturating_sub(history_len as usize);
+    let _ = versions.drain(..excess);
+}
+
 impl Debug for MutableData {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         // TODO(nbaksalyar): write all other fields
@@ -626,10 +1656,205 @@ impl Debug for MutableData {
     }
 }
 
+impl Serialize for MutableData {
+    fn serialize<S: Serializer>(&self, serialiser: S) -> Result<S::Ok, S::Error> {
+        let layout = MutableDataV2 {
+            name: self.name,
+            tag: self.tag,
+            data: Cow::Borrowed(&self.data),
+            permissions: Cow::Borrowed(&self.permissions),
+            version: self.version,
+            owners: Cow::Borrowed(&self.owners),
+            owners_threshold: self.owners_threshold,
+            history: Cow::Borrowed(&self.history),
+            history_len: self.history_len,
+            data_version: self.data_version,
+            changes: Cow::Borrowed(&self.changes),
+            private: self.private,
+            expiries: Cow::Borrowed(&self.expiries),
+            purged_at: self.purged_at,
+        };
+        format::serialize_versioned(MUTABLE_DATA_FORMAT_VERSION, &layout, serialiser)
+    }
+}
+
+impl<'de> Deserialize<'de> for MutableData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<MutableData, D::Error> {
+        format::deserialize_versioned(deserializer)
+    }
+}
+
+impl Versioned for MutableData {
+    fn read_layout<'de, A: SeqAccess<'de>>(version: u16, seq: &mut A) -> Result<Self, A::Error> {
+        match version {
+            1 => format::next_layout::<MutableDataV1, _>(seq).map(MutableData::from),
+            2 => format::next_layout::<MutableDataV2, _>(seq).map(MutableData::from),
+            _ => Err(format::unsupported(version)),
+        }
+    }
+
+    // `MutableDataV0`, of which `head` holds the first eight bytes of the name.
+    fn read_legacy<'de, A: SeqAccess<'de>>(head: u64, seq: &mut A) -> Result<Self, A::Error> {
+        let mut name = XorName::default();
+        for (i, byte) in name.0.iter_mut().enumerate() {
+            *byte = if i < 8 {
+                (head >> (8 * i)) as u8
+            } else {
+                format::next_layout(seq)?
+            };
+        }
+        Ok(MutableData::from(MutableDataV0 {
+            name,
+            tag: format::next_layout(seq)?,
+            data: format::next_layout(seq)?,
+            permissions: format::next_layout(seq)?,
+            version: format::next_layout(seq)?,
+            owners: format::next_layout(seq)?,
+        }))
+    }
+}
+
+// Layout of data serialised before format versions were introduced.
+#[derive(Serialize, Deserialize)]
+struct MutableDataV0 {
+    name: XorName,
+    tag: u64,
+    data: BTreeMap<Vec<u8>, Value>,
+    permissions: BTreeMap<User, PermissionSetV0>,
+    version: u64,
+    owners: BTreeSet<PublicKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PermissionSetV0 {
+    insert: Option<bool>,
+    update: Option<bool>,
+    delete: Option<bool>,
+    manage_permissions: Option<bool>,
+}
+
+impl From<MutableDataV0> for MutableData {
+    fn from(layout: MutableDataV0) -> MutableData {
+        let permissions = layout
+            .permissions
+            .into_iter()
+            .map(|(user, set)| {
+                let set = PermissionSet {
+                    insert: set.insert,
+                    update: set.update,
+                    delete: set.delete,
+                    manage_permissions: set.manage_permissions,
+                    ..PermissionSet::new()
+                };
+                (user, set)
+            })
+            .collect();
+
+        MutableData {
+            name: layout.name,
+            tag: layout.tag,
+            changes: initial_changes(&layout.data),
+            data: layout.data,
+            permissions,
+            version: layout.version,
+            owners: layout.owners,
+            owners_threshold: 1,
+            history: BTreeMap::new(),
+            history_len: 0,
+            data_version: 0,
+            private: false,
+            expiries: BTreeMap::new(),
+            purged_at: 0,
+        }
+    }
+}
+
+// Layout of format version 1.
+#[derive(Serialize, Deserialize)]
+struct MutableDataV1 {
+    name: XorName,
+    tag: u64,
+    data: BTreeMap<Vec<u8>, Value>,
+    permissions: BTreeMap<User, PermissionSet>,
+    version: u64,
+    owners: BTreeSet<PublicKey>,
+    owners_threshold: u64,
+    history: BTreeMap<Vec<u8>, Vec<Value>>,
+    history_len: u64,
+    data_version: u64,
+    changes: BTreeMap<Vec<u8>, ChangeRecord>,
+    private: bool,
+}
+
+impl From<MutableDataV1> for MutableData {
+    fn from(layout: MutableDataV1) -> MutableData {
+        // Version 1 had no records for the entries the data was created with.
+        let mut changes = initial_changes(&layout.data);
+        changes.extend(layout.changes);
+        MutableData {
+            name: layout.name,
+            tag: layout.tag,
+            data: layout.data,
+            permissions: layout.permissions,
+            version: layout.version,
+            owners: layout.owners,
+            owners_threshold: layout.owners_threshold,
+            history: layout.history,
+            history_len: layout.history_len,
+            data_version: layout.data_version,
+            changes,
+            private: layout.private,
+            expiries: BTreeMap::new(),
+            purged_at: 0,
+        }
+    }
+}
+
+// Layout of format version 2. Borrows the fields when serialising.
+#[derive(Serialize, Deserialize)]
+struct MutableDataV2<'a> {
+    name: XorName,
+    tag: u64,
+    data: Cow<'a, BTreeMap<Vec<u8>, Value>>,
+    permissions: Cow<'a, BTreeMap<User, PermissionSet>>,
+    version: u64,
+    owners: Cow<'a, BTreeSet<PublicKey>>,
+    owners_threshold: u64,
+    history: Cow<'a, BTreeMap<Vec<u8>, Vec<Value>>>,
+    history_len: u64,
+    data_version: u64,
+    changes: Cow<'a, BTreeMap<Vec<u8>, ChangeRecord>>,
+    private: bool,
+    expiries: Cow<'a, BTreeMap<Vec<u8>, u64>>,
+    purged_at: u64,
+}
+
+impl<'a> From<MutableDataV2<'a>> for MutableData {
+    fn from(layout: MutableDataV2<'a>) -> MutableData {
+        MutableData {
+            name: layout.name,
+            tag: layout.tag,
+            data: layout.data.into_owned(),
+            permissions: layout.permissions.into_owned(),
+            version: layout.version,
+            owners: layout.owners.into_owned(),
+            owners_threshold: layout.owners_threshold,
+            history: layout.history.into_owned(),
+            history_len: layout.history_len,
+            data_version: layout.data_version,
+            changes: layout.changes.into_owned(),
+            private: layout.private,
+            expiries: layout.expiries.into_owned(),
+            purged_at: layout.purged_at,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::client_error::ClientError;
+    use crate::id::FullId;
     use crate::rust_sodium::crypto::sign;
     use rand;
     use std::collections::{BTreeMap, BTreeSet};
@@ -742,6 +1967,208 @@ mod tests {
         assert!(!unwrap!(user2.is_allowed(Action::ManagePermissions)));
     }
 
+    #[test]
+    fn scoped_permissions() {
+        let (owner, _) = sign::gen_keypair();
+        let (pk1, _) = sign::gen_keypair();
+
+        let ps = PermissionSet::new()
+            .allow(Action::Insert)
+            .allow(Action::Update);
+        let ps = unwrap!(ps.scope(b"x/"));
+        assert_eq!(ps.key_prefix(), Some(&b"x/"[..]));
+        let serialised = unwrap!(serialisation::serialise(&ps));
+        assert_eq!(unwrap!(serialisation::deserialise::<PermissionSet>(&serialised)), ps);
+        assert_err!(
+            PermissionSet::new().scope(&[0; MAX_KEY_PREFIX_LEN + 1]),
+            ClientError::InvalidOperation
+        );
+        assert!(unwrap!(ps.is_allowed_for_key(Action::Insert, b"x/1")));
+        assert!(ps.is_allowed_for_key(Action::Insert, b"y/1").is_none());
+
+        let mut perms = BTreeMap::new();
+        let _ = perms.insert(User::Key(pk1), ps);
+        let _ = perms.insert(User::Anyone, PermissionSet::new().allow(Action::Delete));
+
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            perms,
+            BTreeMap::new(),
+            owners,
+        ));
+
+        unwrap!(md.mutate_entries(
+            EntryActions::new()
+                .ins(b"x/1".to_vec(), b"abc".to_vec(), 0)
+                .into(),
+            pk1
+        ));
+        assert_err!(
+            md.mutate_entries(
+                EntryActions::new()
+                    .ins(b"x/2".to_vec(), b"abc".to_vec(), 0)
+                    .ins(b"y/1".to_vec(), b"abc".to_vec(), 0)
+                    .into(),
+                pk1
+            ),
+            ClientError::AccessDenied
+        );
+        assert!(md.get(b"x/2").is_none());
+
+        // Owners aren't restricted by the scope.
+        unwrap!(md.mutate_entries(
+            EntryActions::new()
+                .ins(b"y/1".to_vec(), b"abc".to_vec(), 0)
+                .into(),
+            owner
+        ));
+        assert_err!(
+            md.mutate_entries(
+                EntryActions::new()
+                    .update(b"y/1".to_vec(), b"def".to_vec(), 1)
+                    .into(),
+                pk1
+            ),
+            ClientError::AccessDenied
+        );
+
+        // Outside of the scope, the permissions of anyone apply.
+        unwrap!(md.mutate_entries(EntryActions::new().del(b"y/1".to_vec(), 1).into(), pk1));
+    }
+
+    #[test]
+    fn expiring_permissions() {
+        let (owner, _) = sign::gen_keypair();
+        let (pk1, _) = sign::gen_keypair();
+
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+
+        // Grant inserts to pk1 until the data version reaches 3. Setting the grant is a change
+        // itself.
+        let ps = PermissionSet::new().allow(Action::Insert).expires_at(3);
+        unwrap!(md.set_user_permissions(User::Key(pk1), ps, 1, owner));
+        assert_eq!(md.data_version(), 1);
+        assert!(md.expired_permissions().is_empty());
+
+        unwrap!(md.mutate_entries(EntryActions::new().ins(vec![0], vec![0], 0).into(), pk1));
+        unwrap!(md.mutate_entries(EntryActions::new().ins(vec![1], vec![1], 0).into(), pk1));
+        assert_eq!(md.data_version(), 3);
+
+        assert_err!(
+            md.mutate_entries(EntryActions::new().ins(vec![2], vec![2], 0).into(), pk1),
+            ClientError::AccessDenied
+        );
+        let expired: BTreeSet<_> = iter::once(User::Key(pk1)).collect();
+        assert_eq!(md.expired_permissions(), expired);
+
+        // An expired grant falls back to the permissions of anyone.
+        let ps = PermissionSet::new().allow(Action::Insert);
+        unwrap!(md.set_user_permissions(User::Anyone, ps, 2, owner));
+        unwrap!(md.mutate_entries(EntryActions::new().ins(vec![2], vec![2], 0).into(), pk1));
+
+        // Permission changes advance the data version too, so a grant to manage the permissions
+        // expires without any entry changing.
+        let (pk2, _) = sign::gen_keypair();
+        let expiry = md.data_version() + 2;
+        let ps = PermissionSet::new()
+            .allow(Action::ManagePermissions)
+            .expires_at(expiry);
+        unwrap!(md.set_user_permissions(User::Key(pk2), ps, 3, owner));
+        unwrap!(md.del_user_permissions(&User::Key(pk1), 4, pk2));
+        assert_eq!(md.data_version(), expiry);
+        assert_err!(
+            md.del_user_permissions(&User::Anyone, 5, pk2),
+            ClientError::AccessDenied
+        );
+    }
+
+    #[test]
+    fn private_entries() {
+        let ids = [FullId::new(), FullId::new(), FullId::new()];
+        let owner = *ids[0].public_id().signing_public_key();
+        let pk1 = *ids[1].public_id().signing_public_key();
+
+        let mut perms = BTreeMap::new();
+        let _ = perms.insert(User::Key(pk1), PermissionSet::new().allow(Action::Read));
+
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            perms,
+            BTreeMap::new(),
+            owners,
+        ))
+        .with_private_entries();
+        assert!(md.is_private());
+
+        let enc_key = MDataEncryptionKey::new();
+        let actions = EntryActions::new()
+            .ins_encrypted(b"key0", b"value0", 0, &enc_key)
+            .ins_encrypted(b"key1", b"value1", 0, &enc_key);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        let actions = EntryActions::new()
+            .update_encrypted(b"key0", b"value0-1", 1, &enc_key)
+            .del_encrypted(b"key1", 1, &enc_key);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+
+        // Vaults only see ciphertext.
+        assert!(md.get(b"key0").is_none());
+        assert!(md.entries().values().all(|value| value.content != b"value0-1"));
+
+        let value = unwrap!(unwrap!(md.get_decrypted(b"key0", &enc_key)));
+        assert_eq!(value.content, b"value0-1");
+        assert_eq!(value.entry_version, 1);
+
+        let entries = unwrap!(md.decrypted_entries(&enc_key));
+        assert_eq!(entries.len(), 2);
+        assert!(unwrap!(entries.get(&b"key1"[..])).content.is_empty());
+
+        assert_err!(
+            md.decrypted_entries(&MDataEncryptionKey::new()),
+            ClientError::DecryptionFailed
+        );
+
+        let client = |id: &FullId| Authority::Client {
+            client_id: *id.public_id(),
+            proxy_node_name: rand::random(),
+        };
+        unwrap!(md.check_read_permission(&client(&ids[0])));
+        unwrap!(md.check_read_permission(&client(&ids[1])));
+        assert_err!(md.check_read_permission(&client(&ids[2])), ClientError::AccessDenied);
+        unwrap!(md.check_read_permission(&Authority::NaeManager(*md.name())));
+    }
+
+    #[test]
+    fn share_encryption_key() {
+        let our_id = FullId::new();
+        let their_id = FullId::new();
+
+        let enc_key = MDataEncryptionKey::new();
+        let (user, shared) = enc_key.share(their_id.public_id(), &our_id);
+        assert_eq!(user, User::Key(*their_id.public_id().signing_public_key()));
+        let received = unwrap!(MDataEncryptionKey::from_shared(
+            &shared,
+            our_id.public_id(),
+            &their_id
+        ));
+        assert!(received == enc_key);
+
+        assert_err!(
+            MDataEncryptionKey::from_shared(&shared, their_id.public_id(), &their_id),
+            ClientError::DecryptionFailed
+        );
+    }
+
     #[test]
     fn max_entries_limit() {
         let to_vec_of_u8 = |i: u64| vec![(i >> 24) as u8, (i >> 16) as u8, (i >> 8) as u8, i as u8];
@@ -778,7 +2205,7 @@ mod tests {
         // Reach the limit.
         let actions = iter::once((
             to_vec_of_u8(MAX_MUTABLE_DATA_ENTRIES - 1),
-            EntryAction::Ins(val.clone()),
+            EntryAction::Ins(val.clone(), None),
         ))
         .collect();
         unwrap!(md.mutate_entries(actions, owner));
@@ -790,7 +2217,7 @@ mod tests {
         // Try to get over the limit.
         let actions = iter::once((
             to_vec_of_u8(MAX_MUTABLE_DATA_ENTRIES),
-            EntryAction::Ins(val.clone()),
+            EntryAction::Ins(val.clone(), None),
         ))
         .collect();
         assert_err!(
@@ -839,7 +2266,7 @@ mod tests {
 
         // Try to get over the size limit
         let actions0: BTreeMap<_, _> =
-            iter::once((vec![1], EntryAction::Ins(small_val.clone()))).collect();
+            iter::once((vec![1], EntryAction::Ins(small_val.clone(), None))).collect();
         assert_err!(
             md.mutate_entries(actions0.clone(), owner),
             ClientError::DataTooLarge
@@ -850,6 +2277,330 @@ mod tests {
         unwrap!(md.mutate_entries(actions0, owner));
     }
 
+    #[test]
+    fn entry_history() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ))
+        .with_entry_history(2));
+
+        let key = vec![0];
+        assert!(md.entry_history(&key).is_none());
+
+        unwrap!(md.mutate_entries(EntryActions::new().ins(key.clone(), vec![0], 0).into(), owner));
+        assert!(unwrap!(md.entry_history(&key)).is_empty());
+
+        for version in 1..4 {
+            let actions = EntryActions::new().update(key.clone(), vec![version as u8], version);
+            unwrap!(md.mutate_entries(actions.into(), owner));
+        }
+        unwrap!(md.mutate_entries(EntryActions::new().del(key.clone(), 4).into(), owner));
+
+        // Only the last two previous values are retained, oldest first.
+        let history: Vec<_> = unwrap!(md.entry_history(&key))
+            .iter()
+            .map(|value| (value.content.clone(), value.entry_version))
+            .collect();
+        assert_eq!(history, vec![(vec![2], 2), (vec![3], 3)]);
+
+        // Replicated mutations are recorded too.
+        assert!(md.mutate_entry_without_validation(
+            key.clone(),
+            Value {
+                content: vec![5],
+                entry_version: 5,
+            },
+        ));
+        assert_eq!(unwrap!(md.entry_history(&key)).len(), 2);
+        assert_eq!(unwrap!(md.entry_history(&key))[1].entry_version, 4);
+
+        // The shell carries the history setting but not the history itself.
+        assert_eq!(md.shell().entry_history_len(), 2);
+
+        // Taking the entries drops their history as well.
+        let mut taken = md.clone();
+        let _ = taken.take_entries();
+        unwrap!(taken.validate());
+
+        // Disabling the history drops the retained values.
+        let md = unwrap!(md.with_entry_history(0));
+        assert!(unwrap!(md.entry_history(&key)).is_empty());
+
+        assert_err!(
+            md.with_entry_history(MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN + 1),
+            ClientError::InvalidOperation
+        );
+    }
+
+    #[test]
+    fn entry_history_size_limit() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ))
+        .with_entry_history(1));
+
+        let key = vec![0];
+        let big_content = vec![0; (MAX_MUTABLE_DATA_SIZE_IN_BYTES / 2) as usize];
+        let actions = EntryActions::new().ins(key.clone(), big_content.clone(), 0);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+
+        // The old value would be kept in the history, exceeding the size limit.
+        let actions = EntryActions::new().update(key.clone(), big_content, 1);
+        assert_err!(
+            md.mutate_entries(actions.into(), owner),
+            ClientError::DataTooLarge
+        );
+        assert_eq!(unwrap!(md.get(&key)).entry_version, 0);
+        assert!(unwrap!(md.entry_history(&key)).is_empty());
+    }
+
+    #[test]
+    fn entries_range() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut entries = BTreeMap::new();
+        for key in &["a1", "a2", "a3", "b1", "b2", "c1"] {
+            let value = Value {
+                content: key.as_bytes().to_vec(),
+                entry_version: 0,
+            };
+            let _ = entries.insert(key.as_bytes().to_vec(), value);
+        }
+        let md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            entries,
+            owners,
+        ));
+
+        // The default range lists everything in a single page.
+        let (page, next) = md.entries_range(&EntriesRange::new());
+        assert_eq!(page, md.entries());
+        assert_eq!(next, None);
+
+        // Paging through all keys visits each of them exactly once.
+        let mut keys = BTreeSet::new();
+        let mut range = EntriesRange::new().limit(4);
+        loop {
+            let (page, next) = md.keys_range(&range);
+            assert!(page.len() <= 4);
+            keys.extend(page);
+            match next {
+                Some(key) => range = range.start(key),
+                None => break,
+            }
+        }
+        let all_keys: BTreeSet<_> = md.keys().into_iter().cloned().collect();
+        assert_eq!(keys, all_keys);
+
+        // Prefix.
+        let range = EntriesRange::new().prefix(b"b".to_vec());
+        let (page, next) = md.keys_range(&range);
+        let expected: BTreeSet<_> = vec![b"b1".to_vec(), b"b2".to_vec()].into_iter().collect();
+        assert_eq!(page, expected);
+        assert_eq!(next, None);
+
+        // Prefix combined with a start key and a limit.
+        let range = EntriesRange::new()
+            .prefix(b"a".to_vec())
+            .start(b"a2".to_vec())
+            .limit(1);
+        let (page, next) = md.values_range(&range);
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].content, b"a2".to_vec());
+        assert_eq!(next, Some(b"a3".to_vec()));
+
+        // Key range.
+        let range = EntriesRange::new().start(b"a3".to_vec()).end(b"b2".to_vec());
+        let (page, next) = md.keys_range(&range);
+        let expected: BTreeSet<_> = vec![b"a3".to_vec(), b"b1".to_vec()].into_iter().collect();
+        assert_eq!(page, expected);
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn changes_since() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+        let mut replica = md.clone();
+
+        let actions = EntryActions::new()
+            .ins(b"a".to_vec(), b"a0".to_vec(), 0)
+            .ins(b"b".to_vec(), b"b0".to_vec(), 0)
+            .ins(b"c".to_vec(), b"c0".to_vec(), 0);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        assert_eq!(md.data_version(), 1);
+        assert_eq!(md.version(), 0);
+
+        // Bring the replica up to date.
+        replica.apply_changes(unwrap!(md.changes_since(replica.data_version())));
+        assert_eq!(replica.entries(), md.entries());
+        assert_eq!(replica.data_version(), 1);
+
+        let actions = EntryActions::new()
+            .update(b"a".to_vec(), b"a1".to_vec(), 1)
+            .del(b"b".to_vec(), 1)
+            .ins(b"d".to_vec(), b"d0".to_vec(), 0);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        let actions = EntryActions::new().del(b"d".to_vec(), 1);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+
+        // Failed mutations don't change the data version.
+        let actions = EntryActions::new().update(b"c".to_vec(), b"c1".to_vec(), 5);
+        assert_err!(
+            md.mutate_entries(actions.into(), owner),
+            ClientError::InvalidEntryActions(_)
+        );
+        assert_eq!(md.data_version(), 3);
+
+        let changes = unwrap!(md.changes_since(1));
+        assert_eq!(changes.data_version, 3);
+        assert!(changes.inserted.is_empty());
+        assert_eq!(changes.updated.keys().collect::<Vec<_>>(), vec![b"a"]);
+        assert_eq!(changes.deleted.keys().collect::<Vec<_>>(), vec![b"b", b"d"]);
+        assert!(unwrap!(changes.deleted.get(&b"b"[..])).content.is_empty());
+
+        let changes = unwrap!(md.changes_since(2));
+        assert_eq!(changes.deleted.keys().collect::<Vec<_>>(), vec![b"d"]);
+        assert!(changes.updated.is_empty());
+
+        replica.apply_changes(unwrap!(md.changes_since(replica.data_version())));
+        assert_eq!(replica.entries(), md.entries());
+        assert_eq!(replica.data_version(), 3);
+        assert_eq!(unwrap!(replica.changes_since(1)), unwrap!(md.changes_since(1)));
+
+        let no_changes = MDataChanges {
+            data_version: 3,
+            ..Default::default()
+        };
+        assert_eq!(unwrap!(md.changes_since(3)), no_changes);
+        assert_err!(md.changes_since(4), ClientError::InvalidSuccessor(3));
+
+        // Since zero, the entries the data was created with are included too.
+        let initial = Value {
+            content: b"x0".to_vec(),
+            entry_version: 0,
+        };
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            iter::once((b"x".to_vec(), initial.clone())).collect(),
+            iter::once(owner).collect(),
+        ));
+        let changes = unwrap!(md.changes_since(0));
+        assert_eq!(changes.inserted.get(&b"x"[..]), Some(&initial));
+        assert!(changes.updated.is_empty());
+
+        let actions = EntryActions::new().update(b"x".to_vec(), b"x1".to_vec(), 1);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        let mut replica = md.shell();
+        replica.apply_changes(unwrap!(md.changes_since(0)));
+        assert_eq!(replica.entries(), md.entries());
+        assert!(unwrap!(md.changes_since(1)).updated.is_empty());
+
+        // Taking the entries drops their records as well.
+        let _ = md.take_entries();
+        unwrap!(md.validate());
+    }
+
+    #[test]
+    fn entry_expiry() {
+        let (owner, _) = sign::gen_keypair();
+        let owners = iter::once(owner).collect();
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ));
+        let permanent_size = md.serialised_size();
+        let now = 1_000_000;
+
+        let actions = EntryActions::new()
+            .ins_with_expiry(b"a".to_vec(), b"a0".to_vec(), 0, Some(now + 3600))
+            .ins_with_expiry(b"b".to_vec(), b"b0".to_vec(), 0, Some(now));
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        assert_eq!(md.entry_expiry(b"a"), Some(now + 3600));
+        assert!(md.serialised_size() > permanent_size);
+
+        let serialised = unwrap!(serialisation::serialise(&md));
+        assert_eq!(unwrap!(serialisation::deserialise::<MutableData>(&serialised)), md);
+
+        // Reads don't go by the local clock, so nothing is hidden before the section agrees on a
+        // time past the expiry.
+        assert_eq!(unwrap!(md.get(b"b")).content, b"b0".to_vec());
+        assert_eq!(md.entries().len(), 2);
+
+        // A replica purged at that time already hides the expired entry it is given, but doesn't
+        // drop it until purged again.
+        let mut replica = md.shell();
+        assert!(!replica.purge_expired(now));
+        replica.apply_changes(unwrap!(md.changes_since(0)));
+        assert!(replica.get(b"b").is_none());
+        assert_eq!(replica.keys().len(), 1);
+        assert_eq!(replica.values().len(), 1);
+        assert_eq!(replica.entries_range(&EntriesRange::new()).0.len(), 1);
+        assert_eq!(replica.entries().len(), 1);
+        assert_eq!(replica.clone().take_entries().len(), 2);
+
+        // Updating without an expiry makes an entry permanent.
+        let actions = EntryActions::new().update(b"b".to_vec(), b"b1".to_vec(), 1);
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        assert_eq!(md.entry_expiry(b"b"), None);
+        assert_eq!(unwrap!(md.get(b"b")).content, b"b1".to_vec());
+
+        // A mutation at an agreed time purges first. Purging leaves a tombstone, which is reported
+        // as a deletion.
+        let actions =
+            EntryActions::new().ins_with_expiry(b"c".to_vec(), b"c0".to_vec(), 0, Some(now));
+        unwrap!(md.mutate_entries(actions.into(), owner));
+        assert_eq!(md.data_version(), 3);
+        let actions = EntryActions::new().ins(b"d".to_vec(), b"d0".to_vec(), 0);
+        unwrap!(md.mutate_entries_at(actions.into(), owner, now));
+        assert_eq!(md.data_version(), 5);
+        assert_eq!(md.purged_at(), now);
+        assert!(!md.purge_expired(now));
+        assert_eq!(md.entry_expiry(b"c"), None);
+        let tombstone = Value {
+            content: Vec::new(),
+            entry_version: 1,
+        };
+        assert_eq!(md.get(b"c"), Some(&tombstone));
+        assert_eq!(unwrap!(md.changes_since(3)).deleted.get(&b"c"[..]), Some(&tombstone));
+        assert_eq!(unwrap!(md.get(b"a")).content, b"a0".to_vec());
+
+        // A failed mutation purges nothing.
+        let actions = EntryActions::new().ins(b"d".to_vec(), b"d1".to_vec(), 0);
+        assert_err!(
+            md.mutate_entries_at(actions.into(), owner, now + 3600),
+            ClientError::InvalidEntryActions(_)
+        );
+        assert_eq!(md.purged_at(), now);
+        assert_eq!(unwrap!(md.get(b"a")).content, b"a0".to_vec());
+    }
+
     #[test]
     fn transfer_ownership() {
         let (owner, _) = sign::gen_keypair();
@@ -871,6 +2622,104 @@ mod tests {
         assert!(!md.owners().contains(&owner));
     }
 
+    #[test]
+    fn multiple_owners() {
+        let (owner0, sk0) = sign::gen_keypair();
+        let (owner1, sk1) = sign::gen_keypair();
+        let (owner2, sk2) = sign::gen_keypair();
+        let (pk1, pk1_sk) = sign::gen_keypair();
+
+        let owners: BTreeSet<_> = vec![owner0, owner1, owner2].into_iter().collect();
+        assert_err!(
+            unwrap!(MutableData::new(
+                rand::random(),
+                0,
+                BTreeMap::new(),
+                BTreeMap::new(),
+                owners.clone(),
+            ))
+            .with_owners_threshold(4),
+            ClientError::InvalidOwners
+        );
+        let mut md = unwrap!(unwrap!(MutableData::new(
+            rand::random(),
+            0,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ))
+        .with_owners_threshold(2));
+
+        // A single owner can't manage permissions or change the owners on its own.
+        let perms = PermissionSet::new().allow(Action::Insert);
+        assert_err!(
+            md.set_user_permissions(User::Key(pk1), perms, 1, owner0),
+            ClientError::InsufficientSignatures(2)
+        );
+        assert_err!(
+            md.del_user_permissions(&User::Anyone, 1, owner0),
+            ClientError::InsufficientSignatures(2)
+        );
+        assert_err!(
+            md.change_owner(pk1, 1),
+            ClientError::InsufficientSignatures(2)
+        );
+
+        let action = OwnerAction::SetUserPermissions {
+            user: User::Key(pk1),
+            permissions: perms,
+        };
+        let data = action.signing_data(md.name(), md.tag(), 1);
+
+        let mut sigs = BTreeMap::new();
+        let _ = sigs.insert(owner0, sign::sign_detached(&data, &sk0));
+        assert_err!(
+            md.set_user_permissions_by_owners(User::Key(pk1), perms, 1, &sigs),
+            ClientError::InsufficientSignatures(2)
+        );
+
+        // Signatures of non-owners are rejected.
+        let mut bad_sigs = sigs.clone();
+        let _ = bad_sigs.insert(pk1, sign::sign_detached(&data, &pk1_sk));
+        assert_err!(
+            md.set_user_permissions_by_owners(User::Key(pk1), perms, 1, &bad_sigs),
+            ClientError::InvalidSignature
+        );
+
+        // Signatures must cover the requested version.
+        let _ = sigs.insert(
+            owner1,
+            sign::sign_detached(&action.signing_data(md.name(), md.tag(), 2), &sk1),
+        );
+        assert_err!(
+            md.set_user_permissions_by_owners(User::Key(pk1), perms, 1, &sigs),
+            ClientError::InvalidSignature
+        );
+
+        let _ = sigs.insert(owner1, sign::sign_detached(&data, &sk1));
+        unwrap!(md.set_user_permissions_by_owners(User::Key(pk1), perms, 1, &sigs));
+        assert_eq!(*unwrap!(md.user_permissions(&User::Key(pk1))), perms);
+
+        // Data mutations by a single owner are still allowed.
+        unwrap!(md.mutate_entries(EntryActions::new().ins(vec![0], vec![0], 0).into(), owner2));
+
+        // Hand the data over to a single owner.
+        let new_owners: BTreeSet<_> = iter::once(pk1).collect();
+        let action = OwnerAction::ChangeOwners {
+            new_owners: new_owners.clone(),
+            threshold: 1,
+        };
+        let data = action.signing_data(md.name(), md.tag(), 2);
+        let mut sigs = BTreeMap::new();
+        let _ = sigs.insert(owner1, sign::sign_detached(&data, &sk1));
+        let _ = sigs.insert(owner2, sign::sign_detached(&data, &sk2));
+        unwrap!(md.change_owners(new_owners.clone(), 1, 2, &sigs));
+        assert_eq!(*md.owners(), new_owners);
+        assert_eq!(md.owners_threshold(), 1);
+
+        unwrap!(md.del_user_permissions(&User::Key(pk1), 3, pk1));
+    }
+
     #[test]
     fn versions_succession() {
         let (owner, _) = sign::gen_keypair();
@@ -888,10 +2737,13 @@ mod tests {
         let mut v1 = BTreeMap::new();
         let _ = v1.insert(
             vec![1],
-            EntryAction::Ins(Value {
-                content: vec![100],
-                entry_version: 0,
-            }),
+            EntryAction::Ins(
+                Value {
+                    content: vec![100],
+                    entry_version: 0,
+                },
+                None,
+            ),
         );
         assert!(md.mutate_entries(v1, owner).is_ok());
 
@@ -899,10 +2751,13 @@ mod tests {
         let mut v2 = BTreeMap::new();
         let _ = v2.insert(
             vec![1],
-            EntryAction::Update(Value {
-                content: vec![105],
-                entry_version: 0,
-            }),
+            EntryAction::Update(
+                Value {
+                    content: vec![105],
+                    entry_version: 0,
+                },
+                None,
+            ),
         );
         match md.mutate_entries(v2.clone(), owner) {
             Err(ClientError::InvalidEntryActions(errors)) => {
@@ -916,10 +2771,13 @@ mod tests {
 
         let _ = v2.insert(
             vec![1],
-            EntryAction::Update(Value {
-                content: vec![105],
-                entry_version: 2,
-            }),
+            EntryAction::Update(
+                Value {
+                    content: vec![105],
+                    entry_version: 2,
+                },
+                None,
+            ),
         );
         match md.mutate_entries(v2.clone(), owner) {
             Err(ClientError::InvalidEntryActions(errors)) => {
@@ -934,10 +2792,13 @@ mod tests {
         // Check update with a valid version
         let _ = v2.insert(
             vec![1],
-            EntryAction::Update(Value {
-                content: vec![105],
-                entry_version: 1,
-            }),
+            EntryAction::Update(
+                Value {
+                    content: vec![105],
+                    entry_version: 1,
+                },
+                None,
+            ),
         );
         assert!(md.mutate_entries(v2, owner).is_ok());
 
@@ -978,10 +2839,13 @@ mod tests {
         let mut v1 = BTreeMap::new();
         let _ = v1.insert(
             vec![0],
-            EntryAction::Ins(Value {
-                content: vec![1],
-                entry_version: 0,
-            }),
+            EntryAction::Ins(
+                Value {
+                    content: vec![1],
+                    entry_version: 0,
+                },
+                None,
+            ),
         );
         assert_err!(
             md.mutate_entries(v1.clone(), pk1),
@@ -1019,10 +2883,13 @@ mod tests {
         let mut v2 = BTreeMap::new();
         let _ = v2.insert(
             vec![1],
-            EntryAction::Ins(Value {
-                content: vec![1],
-                entry_version: 0,
-            }),
+            EntryAction::Ins(
+                Value {
+                    content: vec![1],
+                    entry_version: 0,
+                },
+                None,
+            ),
         );
         assert_err!(md.mutate_entries(v2, pk1), ClientError::AccessDenied);
 
@@ -1036,4 +2903,74 @@ mod tests {
         assert!(md.get(&[0]).is_some());
         assert!(md.get(&[1]).is_none());
     }
+
+    #[test]
+    fn format_versions() {
+        let (owner, _) = sign::gen_keypair();
+        let owners: BTreeSet<_> = iter::once(owner).collect();
+        let mut entries = BTreeMap::new();
+        let _ = entries.insert(
+            b"key".to_vec(),
+            Value {
+                content: b"value".to_vec(),
+                entry_version: 0,
+            },
+        );
+        let mut permissions = BTreeMap::new();
+        let _ = permissions.insert(User::Anyone, PermissionSet::new().allow(Action::Insert));
+
+        // The current version round-trips.
+        let data = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            permissions,
+            entries.clone(),
+            owners.clone(),
+        ));
+        let data = unwrap!(data.with_entry_history(2)).with_private_entries();
+        let serialised = unwrap!(serialisation::serialise(&data));
+        let version: u16 = unwrap!(serialisation::deserialise(&serialised[8..10]));
+        assert_eq!(version, MUTABLE_DATA_FORMAT_VERSION);
+        assert_eq!(unwrap!(serialisation::deserialise::<MutableData>(&serialised)), data);
+
+        // Data stored before format versions were introduced is migrated to the current layout.
+        let mut permissions = BTreeMap::new();
+        let _ = permissions.insert(
+            User::Anyone,
+            PermissionSetV0 {
+                insert: Some(true),
+                update: None,
+                delete: Some(false),
+                manage_permissions: None,
+            },
+        );
+        let v0 = MutableDataV0 {
+            name: rand::random(),
+            tag: 10_000,
+            data: entries,
+            permissions,
+            version: 3,
+            owners,
+        };
+        let unversioned = unwrap!(serialisation::serialise(&v0));
+        let migrated: MutableData = unwrap!(serialisation::deserialise(&unversioned));
+        assert_eq!(*migrated.name(), v0.name);
+        assert_eq!(migrated.version(), 3);
+        assert_eq!(migrated.owners_threshold(), 1);
+        assert!(!migrated.is_private());
+        assert_eq!(unwrap!(migrated.get(b"key")).content, b"value".to_vec());
+        let set = unwrap!(migrated.user_permissions(&User::Anyone));
+        assert_eq!(set.is_allowed(Action::Insert), Some(true));
+        assert_eq!(set.is_allowed(Action::Delete), Some(false));
+        assert_eq!(set.is_allowed(Action::Read), None);
+
+        // Once migrated, the data is written with the current version.
+        let serialised = unwrap!(serialisation::serialise(&migrated));
+        assert_eq!(unwrap!(serialisation::deserialise::<MutableData>(&serialised)), migrated);
+
+        // Unknown versions are rejected rather than misread.
+        let future = (u64::MAX, MUTABLE_DATA_FORMAT_VERSION + 1, &v0);
+        let serialised = unwrap!(serialisation::serialise(&future));
+        assert!(serialisation::deserialise::<MutableData>(&serialised).is_err());
+    }
 }
@@ -0,0 +1,323 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Schemas the entries of `MutableData` with a given type tag have to follow.
+
+use super::{EntryAction, MutableData, Value};
+use crate::client_error::ClientError;
+use crate::common_types::AccountPacket;
+use crate::rust_sodium::crypto::sign::PublicKey;
+use crate::xor_name::XorName;
+use crate::{ACC_LOGIN_ENTRY_KEY, TYPE_TAG_DNS_PACKET, TYPE_TAG_SESSION_PACKET};
+use maidsafe_utilities::serialisation;
+use std::collections::BTreeMap;
+use std::str;
+
+/// Checks that the entries of `MutableData` with a particular type tag are well-formed.
+///
+/// Deleted entries, whose content is empty, are passed to validators as well.
+pub trait TypeTagValidator: Send + Sync {
+    /// Validates the complete set of entries. Returns the error to reject the data or the
+    /// mutation with, usually `ClientError::InvalidSchema`.
+    fn validate(&self, tag: u64, entries: &BTreeMap<Vec<u8>, Value>) -> Result<(), ClientError>;
+}
+
+/// Validator for session packets (`TYPE_TAG_SESSION_PACKET`): the only allowed entry is
+/// `ACC_LOGIN_ENTRY_KEY`, containing a serialised `AccountPacket`.
+pub struct SessionPacketValidator;
+
+impl TypeTagValidator for SessionPacketValidator {
+    fn validate(&self, tag: u64, entries: &BTreeMap<Vec<u8>, Value>) -> Result<(), ClientError> {
+        for (key, value) in entries {
+            if &key[..] != ACC_LOGIN_ENTRY_KEY
+                || (!value.content.is_empty()
+                    && serialisation::deserialise::<AccountPacket>(&value.content).is_err())
+            {
+                return Err(ClientError::InvalidSchema(tag));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validator for DNS packets (`TYPE_TAG_DNS_PACKET`): every key is a non-empty UTF-8 service
+/// name, and every value a serialised `XorName` the service resolves to.
+pub struct DnsPacketValidator;
+
+impl TypeTagValidator for DnsPacketValidator {
+    fn validate(&self, tag: u64, entries: &BTreeMap<Vec<u8>, Value>) -> Result<(), ClientError> {
+        for (key, value) in entries {
+            if key.is_empty()
+                || str::from_utf8(key).is_err()
+                || (!value.content.is_empty()
+                    && serialisation::deserialise::<XorName>(&value.content).is_err())
+            {
+                return Err(ClientError::InvalidSchema(tag));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The validators a node checks `MutableData` against, by type tag. The built-in type tags are
+/// validated from the start: `TYPE_TAG_SESSION_PACKET` by a `SessionPacketValidator` and
+/// `TYPE_TAG_DNS_PACKET` by a `DnsPacketValidator`. Other type tags are only validated once a
+/// validator has been inserted for them.
+///
+/// Validators can't see into encrypted entries, so private data is rejected for any type tag
+/// which has a validator.
+pub struct TypeTagValidators {
+    validators: BTreeMap<u64, Box<TypeTagValidator>>,
+}
+
+impl Default for TypeTagValidators {
+    fn default() -> TypeTagValidators {
+        TypeTagValidators::new()
+    }
+}
+
+impl TypeTagValidators {
+    /// Creates the validators for the built-in type tags.
+    pub fn new() -> TypeTagValidators {
+        let mut validators = TypeTagValidators {
+            validators: BTreeMap::new(),
+        };
+        validators.insert(TYPE_TAG_SESSION_PACKET, Box::new(SessionPacketValidator));
+        validators.insert(TYPE_TAG_DNS_PACKET, Box::new(DnsPacketValidator));
+        validators
+    }
+
+    /// Sets `validator` for `MutableData` with the type tag `tag`, replacing any validator set
+    /// for it before.
+    pub fn insert(&mut self, tag: u64, validator: Box<TypeTagValidator>) {
+        let _ = self.validators.insert(tag, validator);
+    }
+
+    /// Stops validating `MutableData` with the type tag `tag`, including a built-in one.
+    pub fn remove(&mut self, tag: u64) {
+        let _ = self.validators.remove(&tag);
+    }
+
+    /// Checks the entries of `data` against the validator for its type tag, if any.
+    pub fn validate(&self, data: &MutableData) -> Result<(), ClientError> {
+        let validator = match self.validators.get(&data.tag()) {
+            Some(validator) => validator,
+            None => return Ok(()),
+        };
+        if data.is_private() {
+            return Err(ClientError::InvalidSchema(data.tag()));
+        }
+        validator.validate(data.tag(), &data.entries())
+    }
+
+    /// Like `MutableData::mutate_entries`, but also rejects the mutation if it leaves the entries
+    /// invalid. Either `data` is mutated and valid, or it is left unchanged.
+    pub fn mutate_entries(
+        &self,
+        data: &mut MutableData,
+        actions: BTreeMap<Vec<u8>, EntryAction>,
+        requester: PublicKey,
+    ) -> Result<(), ClientError> {
+        if !self.validators.contains_key(&data.tag()) {
+            return data.mutate_entries(actions, requester);
+        }
+        let mut new_data = data.clone();
+        new_data.mutate_entries(actions, requester)?;
+        self.validate(&new_data)?;
+        *data = new_data;
+        Ok(())
+    }
+
+    /// Like `MutableData::mutate_batch`, but also rejects the batch if it leaves the entries of
+    /// any target invalid. Either every target is mutated and valid, or none is changed.
+    pub fn mutate_batch<'a, I>(
+        &self,
+        data: I,
+        actions: BTreeMap<(XorName, u64), BTreeMap<Vec<u8>, EntryAction>>,
+        requester: PublicKey,
+    ) -> Result<(), ClientError>
+    where
+        I: IntoIterator<Item = &'a mut MutableData>,
+    {
+        let targets: Vec<_> = data
+            .into_iter()
+            .filter(|md| actions.contains_key(&(*md.name(), md.tag())))
+            .collect();
+        let mut new_data: Vec<_> = targets.iter().map(|md| (**md).clone()).collect();
+        MutableData::mutate_batch(&mut new_data, actions, requester)?;
+
+        let errors: BTreeMap<_, _> = new_data
+            .iter()
+            .filter_map(|md| {
+                self.validate(md)
+                    .err()
+                    .map(|error| ((*md.name(), md.tag()), error))
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(ClientError::InvalidBatchMutations(errors));
+        }
+
+        for (md, new_md) in targets.into_iter().zip(new_data) {
+            *md = new_md;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EntryActions;
+    use crate::rust_sodium::crypto::sign;
+    use rand;
+    use std::collections::BTreeSet;
+    use std::iter;
+
+    fn entry(key: &[u8], content: Vec<u8>) -> BTreeMap<Vec<u8>, Value> {
+        let mut entries = BTreeMap::new();
+        let value = Value {
+            content,
+            entry_version: 0,
+        };
+        let _ = entries.insert(key.to_vec(), value);
+        entries
+    }
+
+    #[test]
+    fn builtin_validators() {
+        let acc_pkt = unwrap!(serialisation::serialise(&AccountPacket::AccPkt(vec![1, 2, 3])));
+        let session = TYPE_TAG_SESSION_PACKET;
+        let validator = SessionPacketValidator;
+        unwrap!(validator.validate(session, &entry(ACC_LOGIN_ENTRY_KEY, acc_pkt.clone())));
+        unwrap!(validator.validate(session, &entry(ACC_LOGIN_ENTRY_KEY, Vec::new())));
+        assert_eq!(
+            validator.validate(session, &entry(b"other", acc_pkt)),
+            Err(ClientError::InvalidSchema(session))
+        );
+        assert_eq!(
+            validator.validate(session, &entry(ACC_LOGIN_ENTRY_KEY, vec![0xff; 3])),
+            Err(ClientError::InvalidSchema(session))
+        );
+
+        let target = unwrap!(serialisation::serialise(&rand::random::<XorName>()));
+        let dns = TYPE_TAG_DNS_PACKET;
+        let validator = DnsPacketValidator;
+        unwrap!(validator.validate(dns, &entry(b"www", target.clone())));
+        assert_eq!(
+            validator.validate(dns, &entry(&[0xff, 0xfe], target.clone())),
+            Err(ClientError::InvalidSchema(dns))
+        );
+        assert_eq!(
+            validator.validate(dns, &entry(b"", target)),
+            Err(ClientError::InvalidSchema(dns))
+        );
+        assert_eq!(
+            validator.validate(dns, &entry(b"www", vec![1, 2, 3])),
+            Err(ClientError::InvalidSchema(dns))
+        );
+    }
+
+    #[test]
+    fn default_validators() {
+        let (owner, _) = sign::gen_keypair();
+        let owners: BTreeSet<_> = iter::once(owner).collect();
+        let md = unwrap!(MutableData::new(
+            rand::random(),
+            TYPE_TAG_SESSION_PACKET,
+            BTreeMap::new(),
+            entry(b"other", vec![1]),
+            owners,
+        ));
+
+        // The built-in type tags are validated without configuring anything.
+        let mut validators = TypeTagValidators::default();
+        assert_eq!(
+            md.validate_with_validators(&validators),
+            Err(ClientError::InvalidSchema(TYPE_TAG_SESSION_PACKET))
+        );
+
+        validators.remove(TYPE_TAG_SESSION_PACKET);
+        unwrap!(md.validate_with_validators(&validators));
+    }
+
+    #[test]
+    fn validated_mutations() {
+        let (owner, _) = sign::gen_keypair();
+        let owners: BTreeSet<_> = iter::once(owner).collect();
+        let validators = TypeTagValidators::new();
+
+        // DNS packets only accept UTF-8 service names mapped to serialised names.
+        let mut md = unwrap!(MutableData::new(
+            rand::random(),
+            TYPE_TAG_DNS_PACKET,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners.clone(),
+        ));
+        let target = unwrap!(serialisation::serialise(&rand::random::<XorName>()));
+        let actions = EntryActions::new().ins(b"www".to_vec(), target, 0);
+        unwrap!(validators.mutate_entries(&mut md, actions.into(), owner));
+        let actions = EntryActions::new().ins(b"blog".to_vec(), vec![1], 0);
+        assert_eq!(
+            validators.mutate_entries(&mut md, actions.into(), owner),
+            Err(ClientError::InvalidSchema(TYPE_TAG_DNS_PACKET))
+        );
+        assert!(md.get(b"blog").is_none());
+        assert_eq!(md.data_version(), 1);
+
+        // A batch is rejected as a whole if it leaves any target invalid.
+        let mut other = unwrap!(MutableData::new(
+            rand::random(),
+            10_000,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners.clone(),
+        ));
+        let mut actions = BTreeMap::new();
+        let _ = actions.insert(
+            (*md.name(), md.tag()),
+            EntryActions::new().ins(b"blog".to_vec(), vec![1], 0).into(),
+        );
+        let _ = actions.insert(
+            (*other.name(), other.tag()),
+            EntryActions::new().ins(b"key".to_vec(), vec![1], 0).into(),
+        );
+        match validators.mutate_batch(vec![&mut md, &mut other], actions, owner) {
+            Err(ClientError::InvalidBatchMutations(ref errors)) => {
+                assert_eq!(errors.keys().collect::<Vec<_>>(), vec![&(*md.name(), md.tag())]);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert!(md.get(b"blog").is_none());
+        assert!(other.get(b"key").is_none());
+
+        // Type tags without a validator aren't checked.
+        let actions = EntryActions::new().ins(vec![0xff], vec![1], 0);
+        unwrap!(validators.mutate_entries(&mut other, actions.into(), owner));
+
+        // Private data can't be checked, so it is refused for validated type tags.
+        let mut private = unwrap!(MutableData::new(
+            rand::random(),
+            TYPE_TAG_DNS_PACKET,
+            BTreeMap::new(),
+            BTreeMap::new(),
+            owners,
+        ))
+        .with_private_entries();
+        assert_eq!(
+            validators.validate(&private),
+            Err(ClientError::InvalidSchema(TYPE_TAG_DNS_PACKET))
+        );
+        let actions = EntryActions::new().ins(b"www".to_vec(), vec![1], 0);
+        assert_eq!(
+            validators.mutate_entries(&mut private, actions.into(), owner),
+            Err(ClientError::InvalidSchema(TYPE_TAG_DNS_PACKET))
+        );
+    }
+}
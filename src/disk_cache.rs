@@ -0,0 +1,307 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::cache::{increment, Cache, CacheStats, LruEntries};
+use crate::data::ImmutableData;
+use crate::messages::{Request, Response};
+use crate::xor_name::XorName;
+use maidsafe_utilities::serialisation;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Extension of the files data is written to before they are renamed to the data's name.
+pub(crate) const TMP_EXTENSION: &str = "tmp";
+
+/// A cache of ImmutableData responses kept in a directory, so it survives restarts of the node.
+///
+/// Each data is stored in a file named after the hex-encoded name of the data. It is written to a
+/// temporary file first, which is only renamed once it has been synced, so a crash never leaves a
+/// partially written file under the name of a data. When the cache is opened, leftover temporary
+/// files are removed, as are files whose content doesn't hash to their name.
+///
+/// Once the files are larger than the byte budget in total, the least recently used data is
+/// removed. Data cached before the cache was opened counts as used when its file was written.
+pub struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: RefCell<LruEntries<XorName, ()>>,
+    stats: Arc<CacheStats>,
+}
+
+impl DiskCache {
+    /// Opens the cache in `dir`, creating the directory if it doesn't exist. The files in it are
+    /// at most `max_bytes` long in total.
+    pub fn open<P: Into<PathBuf>>(dir: P, max_bytes: u64) -> io::Result<DiskCache> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == TMP_EXTENSION) {
+                debug!("Removing interrupted cache write {:?}", path);
+                fs::remove_file(&path)?;
+                continue;
+            }
+            let name = match cached_name(&path) {
+                Some(name) => name,
+                None => continue, // Not one of ours.
+            };
+            if load(&path, &name).is_none() {
+                debug!("Removing invalid cache file {:?}", path);
+                fs::remove_file(&path)?;
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            files.push((metadata.modified()?, name, metadata.len()));
+        }
+        files.sort();
+
+        let mut entries = LruEntries::new();
+        for (_, name, size) in files {
+            entries.insert(name, (), size);
+        }
+        let cache = DiskCache {
+            dir,
+            max_bytes,
+            entries: RefCell::new(entries),
+            stats: Default::default(),
+        };
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// Returns the counters of the cache. They are shared with the cache, so they keep being
+    /// updated after it has been passed to the node.
+    pub fn stats(&self) -> Arc<CacheStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Returns the number of cached responses.
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Returns true if no response is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total size of the cache files.
+    pub fn bytes(&self) -> u64 {
+        self.entries.borrow().bytes()
+    }
+
+    fn path(&self, name: &XorName) -> PathBuf {
+        self.dir.join(name.to_hex())
+    }
+
+    fn remove(&self, name: &XorName) {
+        let _ = self.entries.borrow_mut().remove(name);
+        if let Err(error) = fs::remove_file(self.path(name)) {
+            warn!("Failed to remove cached {:?}: {:?}", name, error);
+        }
+    }
+
+    fn evict(&self) {
+        loop {
+            let name = {
+                let mut entries = self.entries.borrow_mut();
+                if entries.bytes() <= self.max_bytes {
+                    return;
+                }
+                match entries.remove_lru() {
+                    Some((name, ())) => name,
+                    None => return,
+                }
+            };
+            self.remove(&name);
+            increment(&self.stats.evictions);
+        }
+    }
+}
+
+impl Cache for DiskCache {
+    fn get(&self, request: &Request) -> Option<Response> {
+        let (name, msg_id) = match *request {
+            Request::GetIData { ref name, msg_id } => (name, msg_id),
+            _ => return None,
+        };
+        let cached = self.entries.borrow_mut().get(name).is_some();
+        let data = if cached {
+            let data = load(&self.path(name), name);
+            if data.is_none() {
+                // The file was changed or lost behind our back.
+                self.remove(name);
+            }
+            data
+        } else {
+            None
+        };
+
+        if data.is_some() {
+            increment(&self.stats.hits);
+        } else {
+            increment(&self.stats.misses);
+        }
+        data.map(|data| Response::GetIData {
+            res: Ok(data),
+            msg_id,
+        })
+    }
+
+    fn put(&self, response: Response) {
+        if !response.is_cacheable() {
+            return;
+        }
+        let data = match response {
+            Response::GetIData { res: Ok(data), .. } => data,
+            _ => return,
+        };
+        if self.entries.borrow_mut().get(data.name()).is_some() {
+            return;
+        }
+        let bytes = unwrap!(serialisation::serialise(&data));
+        if bytes.len() as u64 > self.max_bytes {
+            return;
+        }
+
+        if let Err(error) = write_atomically(&self.path(data.name()), &bytes) {
+            warn!("Failed to cache {:?}: {:?}", data, error);
+            return;
+        }
+        self.entries
+            .borrow_mut()
+            .insert(*data.name(), (), bytes.len() as u64);
+        self.evict();
+    }
+}
+
+/// Writes `bytes` to `path` through a temporary file which is only renamed once it has been synced.
+/// The directory is synced too, so the rename itself survives a crash.
+pub(crate) fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let tmp_path = path.with_extension(TMP_EXTENSION);
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+        _ => sync_dir(Path::new(".")),
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+// Directories can't be opened, hence not synced, on other platforms.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+// Returns the name of the data cached in the file at `path`, or `None` if the file isn't a cache
+// file.
+fn cached_name(path: &Path) -> Option<XorName> {
+    if !path.is_file() {
+        return None;
+    }
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .and_then(|file_name| XorName::from_hex(file_name).ok())
+}
+
+// Reads the data cached in the file at `path`, if it can be read and its content hashes to `name`.
+fn load(path: &Path, name: &XorName) -> Option<ImmutableData> {
+    let bytes = fs::read(path).ok()?;
+    let data: ImmutableData = serialisation::deserialise(&bytes).ok()?;
+    if data.name() == name {
+        Some(data)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MessageId;
+    use rand;
+    use std::env;
+
+    fn get(cache: &DiskCache, data: &ImmutableData) -> Option<ImmutableData> {
+        let request = Request::GetIData {
+            name: *data.name(),
+            msg_id: MessageId::new(),
+        };
+        match cache.get(&request) {
+            Some(Response::GetIData { res: Ok(data), .. }) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn put(cache: &DiskCache, data: &ImmutableData) {
+        cache.put(Response::GetIData {
+            res: Ok(data.clone()),
+            msg_id: MessageId::new(),
+        });
+    }
+
+    #[test]
+    fn persistence() {
+        let dir_name = format!("routing_disk_cache_{:016x}", rand::random::<u64>());
+        let dir = env::temp_dir().join(dir_name);
+        let data: Vec<_> = (0..3u8).map(|i| ImmutableData::new(vec![i; 100])).collect();
+        let size = unwrap!(serialisation::serialise(&data[0])).len() as u64;
+
+        // Data survives reopening the cache.
+        {
+            let cache = unwrap!(DiskCache::open(&dir, 2 * size));
+            put(&cache, &data[0]);
+            put(&cache, &data[1]);
+            assert_eq!(get(&cache, &data[0]), Some(data[0].clone()));
+        }
+        let cache = unwrap!(DiskCache::open(&dir, 2 * size));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(get(&cache, &data[1]), Some(data[1].clone()));
+
+        // Exceeding the budget evicts the least recently used data.
+        put(&cache, &data[2]);
+        assert_eq!(get(&cache, &data[0]), None);
+        assert_eq!(get(&cache, &data[2]), Some(data[2].clone()));
+        assert_eq!(cache.bytes(), 2 * size);
+        let stats = cache.stats();
+        assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (2, 1, 1));
+        drop(cache);
+
+        // Corrupted files and interrupted writes are removed on opening, other files are kept.
+        unwrap!(fs::write(dir.join(data[1].name().to_hex()), b"corrupted"));
+        unwrap!(fs::write(dir.join(data[0].name().to_hex()).with_extension("tmp"), b"partial"));
+        unwrap!(fs::write(dir.join("README"), b"unrelated"));
+        let cache = unwrap!(DiskCache::open(&dir, 2 * size));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(get(&cache, &data[2]), Some(data[2].clone()));
+        assert!(!dir.join(data[1].name().to_hex()).exists());
+        assert_eq!(unwrap!(fs::read_dir(&dir)).count(), 2);
+
+        // Reopening with a smaller budget evicts down to it.
+        put(&cache, &data[0]);
+        drop(cache);
+        let cache = unwrap!(DiskCache::open(&dir, size));
+        assert_eq!(cache.len(), 1);
+
+        unwrap!(fs::remove_dir_all(&dir));
+    }
+}
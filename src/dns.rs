@@ -0,0 +1,128 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! DNS-style registration and resolution of human-readable names.
+//!
+//! A registered name is a `MutableData` with the type tag `TYPE_TAG_DNS_PACKET`, stored at
+//! `packet_name(long_name)`. Its entries map service names, such as `"www"`, to the serialised
+//! `XorName` the service resolves to. Names are owned on a first-come basis: putting the packet of
+//! a name which has already been registered fails with `ClientError::DataExists`, and only the
+//! owner can change its services or transfer it.
+
+use crate::client_error::ClientError;
+use crate::data::{EntryAction, EntryActions, MutableData, Value};
+use crate::rust_sodium::crypto::sign::PublicKey;
+use crate::xor_name::XorName;
+use crate::TYPE_TAG_DNS_PACKET;
+use maidsafe_utilities::serialisation;
+use std::collections::BTreeMap;
+use std::iter;
+use tiny_keccak::sha3_256;
+
+/// Returns the network name of the DNS packet for `long_name`.
+pub fn packet_name(long_name: &str) -> XorName {
+    XorName(sha3_256(long_name.as_bytes()))
+}
+
+/// Creates the DNS packet for `long_name`, owned by `owner` and without any services.
+pub fn new_packet(long_name: &str, owner: PublicKey) -> MutableData {
+    let owners = iter::once(owner).collect();
+    unwrap!(MutableData::new(
+        packet_name(long_name),
+        TYPE_TAG_DNS_PACKET,
+        BTreeMap::new(),
+        BTreeMap::new(),
+        owners,
+    ))
+}
+
+/// Returns the entry actions to make `service` resolve to `target`.
+///
+/// `entry_version` is the version the entry will have: 0 for a service which has never been
+/// added, otherwise the current version of its entry plus one.
+pub fn add_service_actions(
+    service: &str,
+    target: &XorName,
+    entry_version: u64,
+) -> BTreeMap<Vec<u8>, EntryAction> {
+    let key = service.as_bytes().to_vec();
+    let content = unwrap!(serialisation::serialise(target));
+    if entry_version == 0 {
+        EntryActions::new().ins(key, content, 0).into()
+    } else {
+        EntryActions::new().update(key, content, entry_version).into()
+    }
+}
+
+/// Returns the entry actions to remove `service`. `entry_version` must be the current version of
+/// its entry plus one.
+pub fn remove_service_actions(
+    service: &str,
+    entry_version: u64,
+) -> BTreeMap<Vec<u8>, EntryAction> {
+    EntryActions::new()
+        .del(service.as_bytes().to_vec(), entry_version)
+        .into()
+}
+
+/// Decodes the target of a service from the value of its entry.
+pub fn decode_target(value: &Value) -> Result<XorName, ClientError> {
+    if value.content.is_empty() {
+        return Err(ClientError::NoSuchEntry);
+    }
+    serialisation::deserialise(&value.content)
+        .map_err(|_| ClientError::InvalidSchema(TYPE_TAG_DNS_PACKET))
+}
+
+/// Resolves `service` in the given DNS packet.
+pub fn resolve(packet: &MutableData, service: &str) -> Result<XorName, ClientError> {
+    packet
+        .get(service.as_bytes())
+        .ok_or(ClientError::NoSuchEntry)
+        .and_then(decode_target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rust_sodium::crypto::sign;
+    use rand;
+
+    #[test]
+    fn services() {
+        let (owner, _) = sign::gen_keypair();
+        let (other, _) = sign::gen_keypair();
+        let mut packet = new_packet("example", owner);
+        assert_eq!(*packet.name(), packet_name("example"));
+        assert_ne!(packet_name("example"), packet_name("Example"));
+
+        let www = rand::random();
+        unwrap!(packet.mutate_entries(add_service_actions("www", &www, 0), owner));
+        assert_eq!(unwrap!(resolve(&packet, "www")), www);
+
+        // Only the owner can change the services.
+        match packet.mutate_entries(add_service_actions("blog", &www, 0), other) {
+            Err(ClientError::AccessDenied) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        let www2 = rand::random();
+        unwrap!(packet.mutate_entries(add_service_actions("www", &www2, 1), owner));
+        assert_eq!(unwrap!(resolve(&packet, "www")), www2);
+
+        unwrap!(packet.mutate_entries(remove_service_actions("www", 2), owner));
+        match resolve(&packet, "www") {
+            Err(ClientError::NoSuchEntry) => (),
+            x => panic!("Unexpected {:?}", x),
+        }
+
+        // A removed service can be added again.
+        unwrap!(packet.mutate_entries(add_service_actions("www", &www, 3), owner));
+        assert_eq!(unwrap!(resolve(&packet, "www")), www);
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Invitation codes for controlled onboarding.
+//!
+//! Each code is managed by the `ClientManager` section closest to `invitation_name(code)`. Keys
+//! authorised by that section issue codes with `Request::IssueInvitation`.
+//!
+//! Registering an account with a code takes two steps, as the code and the session packet are
+//! managed by different sections. First `Request::ClaimInvitation`, carrying a session packet
+//! which contains the code, binds the code to the name of that packet, so a code can't be used by
+//! two accounts. Then the packet is stored with `Request::PutMData`. Claiming a code again for
+//! the same packet name succeeds, so if the second step fails, the claimant can retry both steps
+//! without losing the code, while nobody else can use it.
+
+use crate::account;
+use crate::client_error::ClientError;
+use crate::data::MutableData;
+use crate::rust_sodium::crypto::sign::PublicKey;
+use crate::xor_name::XorName;
+use crate::TYPE_TAG_SESSION_PACKET;
+use std::collections::{BTreeMap, BTreeSet};
+use tiny_keccak::sha3_256;
+
+/// Returns the name of the section managing the invitation `code`.
+pub fn invitation_name(code: &str) -> XorName {
+    let mut preimage = b"invitation".to_vec();
+    preimage.extend_from_slice(code.as_bytes());
+    XorName(sha3_256(&preimage))
+}
+
+/// Invitation codes managed by a section, together with the accounts which claimed them.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Invitations {
+    /// Keys allowed to issue new codes
+    issuers: BTreeSet<PublicKey>,
+    /// Issued codes, mapped to the name of the session packet which claimed them
+    codes: BTreeMap<String, Option<XorName>>,
+}
+
+impl Invitations {
+    /// Creates an empty set of invitations which can be issued by the given keys.
+    pub fn new(issuers: BTreeSet<PublicKey>) -> Invitations {
+        Invitations {
+            issuers,
+            codes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the keys allowed to issue new codes.
+    pub fn issuers(&self) -> &BTreeSet<PublicKey> {
+        &self.issuers
+    }
+
+    /// Returns true if `code` has been issued and not yet claimed.
+    pub fn is_available(&self, code: &str) -> bool {
+        self.codes.get(code).map_or(false, Option::is_none)
+    }
+
+    /// Returns the name of the session packet which claimed `code`, if any.
+    pub fn claimant(&self, code: &str) -> Option<&XorName> {
+        self.codes.get(code).and_then(Option::as_ref)
+    }
+
+    /// Issues `code` on behalf of `requester`.
+    pub fn issue(&mut self, code: String, requester: &PublicKey) -> Result<(), ClientError> {
+        if !self.issuers.contains(requester) {
+            return Err(ClientError::AccessDenied);
+        }
+        if code.is_empty() {
+            return Err(ClientError::InvalidInvitation);
+        }
+        if self.codes.contains_key(&code) {
+            return Err(ClientError::DataExists);
+        }
+        let _ = self.codes.insert(code, None);
+        Ok(())
+    }
+
+    /// Claims the code contained in the session packet `data` for the account it registers. Either
+    /// the code is claimed and `Ok` returned, or nothing changes.
+    ///
+    /// Claiming a code again for the same session packet succeeds, so a retried request isn't
+    /// refused, but any other claim of a used code fails with
+    /// `ClientError::InvitationAlreadyClaimed`.
+    pub fn claim(&mut self, data: &MutableData) -> Result<(), ClientError> {
+        if data.tag() != TYPE_TAG_SESSION_PACKET {
+            return Err(ClientError::InvalidOperation);
+        }
+        let code = account::invitation(data).ok_or(ClientError::InvalidInvitation)?;
+        match self.codes.get_mut(&code) {
+            None => Err(ClientError::InvalidInvitation),
+            Some(&mut Some(ref claimant)) if claimant == data.name() => Ok(()),
+            Some(&mut Some(_)) => Err(ClientError::InvitationAlreadyClaimed),
+            Some(claimant) => {
+                *claimant = Some(*data.name());
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::AccountKeys;
+    use crate::rust_sodium::crypto::sign;
+    use std::iter;
+
+    #[test]
+    fn issue_and_claim() {
+        let (issuer, _) = sign::gen_keypair();
+        let (other, _) = sign::gen_keypair();
+        let mut invitations = Invitations::new(iter::once(issuer).collect());
+
+        // Only authorised keys can issue codes, and each code only once.
+        assert_eq!(
+            invitations.issue("code".to_owned(), &other),
+            Err(ClientError::AccessDenied)
+        );
+        unwrap!(invitations.issue("code".to_owned(), &issuer));
+        assert_eq!(
+            invitations.issue("code".to_owned(), &issuer),
+            Err(ClientError::DataExists)
+        );
+        assert!(invitations.is_available("code"));
+
+        let alice = AccountKeys::from_credentials(b"alice", b"password");
+        let bob = AccountKeys::from_credentials(b"bob", b"password");
+
+        let packet = bob.new_session_packet(b"bob's account", Some("unknown".to_owned()));
+        assert_eq!(
+            invitations.claim(&packet),
+            Err(ClientError::InvalidInvitation)
+        );
+        let packet = bob.new_session_packet(b"bob's account", None);
+        assert_eq!(
+            invitations.claim(&packet),
+            Err(ClientError::InvalidInvitation)
+        );
+
+        // The first claim wins, and a retry of it is harmless.
+        let packet = alice.new_session_packet(b"alice's account", Some("code".to_owned()));
+        unwrap!(invitations.claim(&packet));
+        unwrap!(invitations.claim(&packet));
+        assert!(!invitations.is_available("code"));
+        assert_eq!(invitations.claimant("code"), Some(alice.name()));
+
+        let packet = bob.new_session_packet(b"bob's account", Some("code".to_owned()));
+        assert_eq!(
+            invitations.claim(&packet),
+            Err(ClientError::InvitationAlreadyClaimed)
+        );
+        assert_eq!(invitations.claimant("code"), Some(alice.name()));
+    }
+}
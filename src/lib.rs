@@ -177,6 +177,7 @@ mod common_types;
 mod config_handler;
 mod cumulative_own_section_merge;
 mod data;
+mod disk_cache;
 mod error;
 mod event;
 mod event_stream;
@@ -189,6 +190,7 @@ mod peer_manager;
 mod rate_limiter;
 mod resource_prover;
 mod routing_message_filter;
+mod routing_snapshot;
 mod routing_table;
 mod section_list_cache;
 mod signature_accumulator;
@@ -217,6 +219,21 @@ pub mod mock_crust;
 /// SHA-3 type alias.
 pub mod sha3;
 
+/// DNS-style name registration.
+pub mod dns;
+
+/// Account registration and login.
+pub mod account;
+
+/// Invitation codes for controlled onboarding.
+pub mod invitation;
+
+/// Account balances and credit transfers.
+pub mod credit;
+
+/// Subscriptions to mutations of `MutableData`.
+pub mod subscription;
+
 /// Messaging infrastructure
 pub mod messaging;
 /// Structured Data Tag for Session Packet Type
@@ -236,21 +253,28 @@ pub const MIN_SECTION_SIZE: usize = 8;
 /// Key of an account data in the account packet
 pub const ACC_LOGIN_ENTRY_KEY: &[u8] = b"Login";
 
-pub use crate::cache::{Cache, NullCache};
+pub use crate::cache::{Cache, CacheStats, LruCache, NullCache, DEFAULT_MDATA_TTL_SECS};
 pub use crate::client::Client;
 pub use crate::client_error::{ClientError, EntryError};
 pub use crate::common_types::AccountPacket;
 pub use crate::config_handler::{Config, DevConfig};
 pub use crate::data::{
-    Action, EntryAction, EntryActions, ImmutableData, MutableData, PermissionSet, User, Value,
-    MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, MAX_MUTABLE_DATA_ENTRIES, MAX_MUTABLE_DATA_SIZE_IN_BYTES,
-    NO_OWNER_PUB_KEY,
+    Action, AppendableData, DataMap, DnsPacketValidator, EntriesRange, EntryAction, EntryActions,
+    FileDownload, FileUpload, IDataRange, ImmutableData, MDataChanges, MDataEncryptionKey,
+    MutableData, OwnerAction, PermissionSet, SessionPacketValidator, TypeTagValidator,
+    TypeTagValidators, UnpubImmutableData, User, Value, APPENDABLE_DATA_FORMAT_VERSION,
+    IDATA_BLOCK_SIZE_IN_BYTES, IMMUTABLE_DATA_FORMAT_VERSION, MAX_APPENDABLE_DATA_ENTRIES,
+    MAX_APPENDABLE_DATA_SIZE_IN_BYTES, MAX_CHUNK_REQUESTS_IN_FLIGHT, MAX_CHUNK_RETRIES,
+    MAX_CHUNK_SIZE_IN_BYTES, MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, MAX_KEY_PREFIX_LEN,
+    MAX_MUTABLE_DATA_ENTRIES, MAX_MUTABLE_DATA_ENTRY_HISTORY_LEN, MAX_MUTABLE_DATA_SIZE_IN_BYTES,
+    MUTABLE_DATA_FORMAT_VERSION, NO_OWNER_PUB_KEY, UNPUB_IMMUTABLE_DATA_FORMAT_VERSION,
 };
+pub use crate::disk_cache::DiskCache;
 pub use crate::error::{InterfaceError, RoutingError};
 pub use crate::event::Event;
 pub use crate::event_stream::EventStream;
 pub use crate::id::{FullId, PublicId};
-pub use crate::messages::{AccountInfo, Request, Response};
+pub use crate::messages::{AccountInfo, MDataNotification, Request, Response, Transaction};
 #[cfg(feature = "use-mock-crust")]
 pub use crate::mock_crust::crust;
 pub use crate::node::{Node, NodeBuilder};
@@ -258,6 +282,7 @@ pub use crate::node::{Node, NodeBuilder};
 pub use crate::peer_manager::test_consts;
 #[cfg(feature = "use-mock-crust")]
 pub use crate::rate_limiter::rate_limiter_consts;
+pub use crate::routing_snapshot::RoutingSnapshot;
 #[cfg(any(test, feature = "use-mock-crust"))]
 pub use crate::routing_table::verify_network_invariant;
 pub use crate::routing_table::Error as RoutingTableError;
@@ -10,7 +10,7 @@ mod request;
 mod response;
 
 pub use self::request::Request;
-pub use self::response::{AccountInfo, Response};
+pub use self::response::{AccountInfo, MDataNotification, Response, Transaction};
 use super::{QUORUM_DENOMINATOR, QUORUM_NUMERATOR};
 use crate::ack_manager::Ack;
 use crate::data::MAX_IMMUTABLE_DATA_SIZE_IN_BYTES;
@@ -634,7 +634,7 @@ pub enum MessageContent {
         part_index: u32,
         /// The message priority.
         priority: u8,
-        /// Is the message cacheable?
+        /// Is the message cacheable, or does it invalidate cached responses?
         cacheable: bool,
         /// The `part_index`-th part of the serialised user message.
         payload: Vec<u8>,
@@ -923,10 +923,18 @@ impl UserMessage {
         }
     }
 
+    // Whether relaying nodes need to look at the message for their response cache: either it is
+    // cacheable, it mutates MutableData, so that its response can be recognised, or it reports
+    // a change which makes cached responses stale.
     fn is_cacheable(&self) -> bool {
         match *self {
-            UserMessage::Request(ref request) => request.is_cacheable(),
-            UserMessage::Response(ref response) => response.is_cacheable(),
+            UserMessage::Request(ref request) => {
+                request.is_cacheable() || !request.mutated_mdata().is_empty()
+            }
+            UserMessage::Response(Response::MDataChanged { .. }) => true,
+            UserMessage::Response(ref response) => {
+                response.is_cacheable() || response.is_mdata_mutation_success()
+            }
         }
     }
 }
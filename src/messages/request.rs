@@ -6,7 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::data::{EntryAction, ImmutableData, MutableData, PermissionSet, User};
+use crate::data::{
+    AppendableData, EntriesRange, EntryAction, ImmutableData, MutableData, PermissionSet,
+    UnpubImmutableData, User,
+};
 use crate::rust_sodium::crypto::sign;
 use crate::types::MessageId as MsgId;
 use crate::xor_name::XorName;
@@ -75,36 +78,45 @@ pub enum Request {
         tag: u64,
         /// Unique message identifier
         msg_id: MsgId,
+        /// If true, the request may be answered from the caches of relaying nodes, at the risk of
+        /// a stale response
+        allow_cached: bool,
     },
 
     // Data Actions
-    /// Fetches a list of entries (keys + values).
+    /// Fetches a page of entries (keys + values).
     /// Note: responses to this request are unlikely to accumulate during churn.
     ListMDataEntries {
         /// Network identifier of MutableData
         name: XorName,
         /// Type tag
         tag: u64,
+        /// Range of keys to list
+        range: EntriesRange,
         /// Unique message identifier
         msg_id: MsgId,
     },
-    /// Fetches a list of keys in MutableData.
+    /// Fetches a page of keys in MutableData.
     /// Note: responses to this request are unlikely to accumulate during churn.
     ListMDataKeys {
         /// Network identifier of MutableData
         name: XorName,
         /// Type tag
         tag: u64,
+        /// Range of keys to list
+        range: EntriesRange,
         /// Unique message identifier
         msg_id: MsgId,
     },
-    /// Fetches a list of values in MutableData.
+    /// Fetches a page of values in MutableData.
     /// Note: responses to this request are unlikely to accumulate during churn.
     ListMDataValues {
         /// Network identifier of MutableData
         name: XorName,
         /// Type tag
         tag: u64,
+        /// Range of keys to list
+        range: EntriesRange,
         /// Unique message identifier
         msg_id: MsgId,
     },
@@ -118,6 +130,9 @@ pub enum Request {
         key: Vec<u8>,
         /// Unique message identifier
         msg_id: MsgId,
+        /// If true, the request may be answered from the caches of relaying nodes, at the risk of
+        /// a stale response
+        allow_cached: bool,
     },
     /// Updates MutableData entries in bulk.
     MutateMDataEntries {
@@ -163,7 +178,8 @@ pub enum Request {
         tag: u64,
         /// A user identifier used to set permissions
         user: User,
-        /// Permissions to be set for a user
+        /// Permissions to be set for a user, optionally scoped to a key prefix and expiring at a
+        /// data version
         permissions: PermissionSet,
         /// Incremented version of MutableData
         version: u64,
@@ -171,6 +187,9 @@ pub enum Request {
         msg_id: MsgId,
         /// Requester public key
         requester: sign::PublicKey,
+        /// Owner signatures of the corresponding `OwnerAction`. Required if MutableData needs
+        /// more than one owner signature, otherwise the permissions of `requester` are checked.
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     },
     /// Deletes a list of permissions for a particular User in the given MutableData.
     DelMDataUserPermissions {
@@ -186,10 +205,14 @@ pub enum Request {
         msg_id: MsgId,
         /// Requester public key
         requester: sign::PublicKey,
+        /// Owner signatures of the corresponding `OwnerAction`. Required if MutableData needs
+        /// more than one owner signature, otherwise the permissions of `requester` are checked.
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     },
 
     // Ownership Actions
-    /// Changes an owner of the given MutableData. Only the current owner can perform this action.
+    /// Changes the owners of the given MutableData. Only the current owners can perform this
+    /// action.
     ChangeMDataOwner {
         /// Network identifier of MutableData
         name: XorName,
@@ -197,10 +220,15 @@ pub enum Request {
         tag: u64,
         /// A list of new owners
         new_owners: BTreeSet<sign::PublicKey>,
+        /// Number of owner signatures the new owners will need to change permissions or owners
+        owners_threshold: u64,
         /// Incremented version of MutableData
         version: u64,
         /// Unique message identifier
         msg_id: MsgId,
+        /// Signatures of the current owners of the corresponding `OwnerAction`. Required if
+        /// MutableData needs more than one owner signature.
+        signatures: BTreeMap<sign::PublicKey, sign::Signature>,
     },
 
     // --- Client (Owner) to MM ---
@@ -225,6 +253,221 @@ pub enum Request {
         /// Unique message identifier
         msg_id: MsgId,
     },
+
+    // --- Credit ---
+    // ==========================
+    /// Transfers credit from the requester's account to the account `to`. Sent by a client to its
+    /// own MaidManager, which forwards it to the MaidManager of `to`. Transfers are applied at
+    /// most once per message identifier.
+    TransferCredit {
+        /// Name of the account to be credited
+        to: XorName,
+        /// Amount to be transferred
+        amount: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Fetches the current balance of the requester's account from MaidManager.
+    GetBalance(MsgId),
+    /// Fetches a page of the credit transfers in and out of the requester's account from
+    /// MaidManager, oldest first.
+    GetBalanceHistory {
+        /// Sequence number of the first transfer to list
+        start: u64,
+        /// Maximum number of transfers in the page. Zero means no limit.
+        limit: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- Invitations ---
+    // ==========================
+    /// Issues a new invitation code. Only keys authorised by the section can issue invitations.
+    IssueInvitation {
+        /// Invitation code to be issued
+        code: String,
+        /// Unique message identifier
+        msg_id: MsgId,
+        /// Requester public key
+        requester: sign::PublicKey,
+    },
+    /// Claims the invitation code contained in a session packet for the account that packet
+    /// registers. The packet isn't stored: that is done by a separate `PutMData` to its own
+    /// section once the claim succeeded.
+    ClaimInvitation {
+        /// Session packet containing an `AccountPacket::WithInvitation`
+        data: MutableData,
+        /// Unique message identifier
+        msg_id: MsgId,
+        /// Requester public key
+        requester: sign::PublicKey,
+    },
+
+    // --- AppendableData ---
+    // ==========================
+    /// Creates a new AppendableData in the network.
+    PutAData {
+        /// AppendableData to be stored
+        data: AppendableData,
+        /// Unique message identifier
+        msg_id: MsgId,
+        /// Requester public key
+        requester: sign::PublicKey,
+    },
+    /// Fetches whole AppendableData from the network.
+    /// Note: responses to this request are unlikely to accumulate during churn.
+    GetAData {
+        /// Network identifier of AppendableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Fetches the index the next entry will be appended at.
+    GetADataIndex {
+        /// Network identifier of AppendableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Fetches the entries in the half-open index range `[start, end)`.
+    /// Note: responses to this request are unlikely to accumulate during churn.
+    GetADataRange {
+        /// Network identifier of AppendableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Index of the first entry to be fetched
+        start: u64,
+        /// Index past the last entry to be fetched
+        end: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Appends entries to AppendableData.
+    AppendToAData {
+        /// Network identifier of AppendableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Entries to be appended
+        entries: Vec<Vec<u8>>,
+        /// Current index of AppendableData, at which the first entry is to be appended
+        index: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+        /// Requester public key
+        requester: sign::PublicKey,
+    },
+
+    // --- MutableData entry history ---
+    // ==========================
+    /// Fetches the previous values of a single entry in MutableData.
+    GetMDataEntryHistory {
+        /// Network identifier of MutableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Key of an entry whose history is to be fetched
+        key: Vec<u8>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData batches ---
+    // ==========================
+    /// Updates entries of several MutableData in bulk. Either all the mutations succeed or none is
+    /// applied. All the MutableData must be managed by the destination section, otherwise the
+    /// section refuses the batch with `ClientError::InvalidOperation`.
+    MutateMDataBatch {
+        /// Mutations (inserts, updates, or deletes) to be performed on each MutableData, keyed by
+        /// its network identifier and type tag.
+        mutations: BTreeMap<(XorName, u64), BTreeMap<Vec<u8>, EntryAction>>,
+        /// Unique message identifier
+        msg_id: MsgId,
+        /// Requester public key
+        requester: sign::PublicKey,
+    },
+
+    // --- MutableData changes ---
+    // ==========================
+    /// Fetches the entries of MutableData inserted, updated or deleted after a data version.
+    GetMDataChangesSince {
+        /// Network identifier of MutableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Data version the requester has already seen
+        since: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- Unpublished ImmutableData ---
+    // ==========================
+    /// Puts unpublished ImmutableData to the network.
+    PutUnpubIData {
+        /// UnpubImmutableData to be stored
+        data: UnpubImmutableData,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Fetches unpublished ImmutableData from the network by the given name.
+    GetUnpubIData {
+        /// Network identifier of UnpubImmutableData
+        name: XorName,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Deletes unpublished ImmutableData from the network.
+    DeleteUnpubIData {
+        /// Network identifier of UnpubImmutableData
+        name: XorName,
+        /// Owner's signature of `UnpubImmutableData::deletion_signing_data(name, nonce)`
+        signature: sign::Signature,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- ImmutableData ranges ---
+    // ==========================
+    /// Fetches a byte range of ImmutableData, together with a Merkle proof of its inclusion.
+    GetIDataRange {
+        /// Network identifier of ImmutableData
+        name: XorName,
+        /// Offset of the first byte to fetch
+        offset: u64,
+        /// Number of bytes to fetch
+        len: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData subscriptions ---
+    // ==========================
+    /// Subscribes the requester to mutations of the given MutableData. Until the subscription is
+    /// cancelled, the data's NaeManager sends a `Response::MDataChanged` to the client whenever
+    /// the data changes.
+    SubscribeMData {
+        /// Network identifier of MutableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Cancels a subscription made with `SubscribeMData`.
+    UnsubscribeMData {
+        /// Network identifier of MutableData
+        name: XorName,
+        /// Type tag
+        tag: u64,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
 }
 
 impl Request {
@@ -236,6 +479,10 @@ impl Request {
             | GetAccountInfo(ref msg_id)
             | PutIData { ref msg_id, .. }
             | GetIData { ref msg_id, .. }
+            | GetIDataRange { ref msg_id, .. }
+            | PutUnpubIData { ref msg_id, .. }
+            | GetUnpubIData { ref msg_id, .. }
+            | DeleteUnpubIData { ref msg_id, .. }
             | GetMData { ref msg_id, .. }
             | PutMData { ref msg_id, .. }
             | GetMDataVersion { ref msg_id, .. }
@@ -244,24 +491,53 @@ impl Request {
             | ListMDataKeys { ref msg_id, .. }
             | ListMDataValues { ref msg_id, .. }
             | GetMDataValue { ref msg_id, .. }
+            | GetMDataEntryHistory { ref msg_id, .. }
+            | GetMDataChangesSince { ref msg_id, .. }
             | MutateMDataEntries { ref msg_id, .. }
+            | MutateMDataBatch { ref msg_id, .. }
             | ListMDataPermissions { ref msg_id, .. }
             | ListMDataUserPermissions { ref msg_id, .. }
             | SetMDataUserPermissions { ref msg_id, .. }
             | DelMDataUserPermissions { ref msg_id, .. }
             | ChangeMDataOwner { ref msg_id, .. }
+            | SubscribeMData { ref msg_id, .. }
+            | UnsubscribeMData { ref msg_id, .. }
+            | PutAData { ref msg_id, .. }
+            | GetAData { ref msg_id, .. }
+            | GetADataIndex { ref msg_id, .. }
+            | GetADataRange { ref msg_id, .. }
+            | AppendToAData { ref msg_id, .. }
             | ListAuthKeysAndVersion(ref msg_id)
             | InsAuthKey { ref msg_id, .. }
-            | DelAuthKey { ref msg_id, .. } => msg_id,
+            | DelAuthKey { ref msg_id, .. }
+            | TransferCredit { ref msg_id, .. }
+            | GetBalance(ref msg_id)
+            | GetBalanceHistory { ref msg_id, .. }
+            | IssueInvitation { ref msg_id, .. }
+            | ClaimInvitation { ref msg_id, .. } => msg_id,
         }
     }
 
     /// Is the response corresponding to this request cacheable?
     pub fn is_cacheable(&self) -> bool {
-        if let Request::GetIData { .. } = *self {
-            true
-        } else {
-            false
+        match *self {
+            Request::GetIData { .. } => true,
+            Request::GetMDataShell { allow_cached, .. }
+            | Request::GetMDataValue { allow_cached, .. } => allow_cached,
+            _ => false,
+        }
+    }
+
+    /// Returns the name and type tag of every MutableData this request mutates, whose cached
+    /// responses become stale.
+    pub(crate) fn mutated_mdata(&self) -> Vec<(XorName, u64)> {
+        match *self {
+            Request::MutateMDataEntries { name, tag, .. }
+            | Request::SetMDataUserPermissions { name, tag, .. }
+            | Request::DelMDataUserPermissions { name, tag, .. }
+            | Request::ChangeMDataOwner { name, tag, .. } => vec![(name, tag)],
+            Request::MutateMDataBatch { ref mutations, .. } => mutations.keys().cloned().collect(),
+            _ => Vec::new(),
         }
     }
 }
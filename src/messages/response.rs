@@ -7,9 +7,13 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use crate::client_error::ClientError;
-use crate::data::{ImmutableData, MutableData, PermissionSet, User, Value};
+use crate::data::{
+    AppendableData, IDataRange, ImmutableData, MDataChanges, MutableData, PermissionSet,
+    UnpubImmutableData, User, Value,
+};
 use crate::rust_sodium::crypto::sign;
 use crate::types::MessageId as MsgId;
+use crate::xor_name::XorName;
 use std::collections::{BTreeMap, BTreeSet};
 
 /// Response message types
@@ -72,24 +76,27 @@ pub enum Response {
     },
 
     // Data Actions
-    /// Returns a complete list of entries in MutableData or an error in case of failure.
+    /// Returns a page of entries in MutableData or an error in case of failure.
     ListMDataEntries {
-        /// Result of getting a list of entries in MutableData
-        res: Result<BTreeMap<Vec<u8>, Value>, ClientError>,
+        /// Result of getting a page of entries in MutableData, together with the key the next
+        /// page starts at, if any
+        res: Result<(BTreeMap<Vec<u8>, Value>, Option<Vec<u8>>), ClientError>,
         /// Unique message identifier
         msg_id: MsgId,
     },
-    /// Returns a list of keys in MutableData or an error in case of failure.
+    /// Returns a page of keys in MutableData or an error in case of failure.
     ListMDataKeys {
-        /// Result of getting a list of keys in MutableData
-        res: Result<BTreeSet<Vec<u8>>, ClientError>,
+        /// Result of getting a page of keys in MutableData, together with the key the next page
+        /// starts at, if any
+        res: Result<(BTreeSet<Vec<u8>>, Option<Vec<u8>>), ClientError>,
         /// Unique message identifier
         msg_id: MsgId,
     },
-    /// Returns a list of values in MutableData or an error in case of failure.
+    /// Returns a page of values in MutableData or an error in case of failure.
     ListMDataValues {
-        /// Result of getting a list of values in MutableData
-        res: Result<Vec<Value>, ClientError>,
+        /// Result of getting a page of values in MutableData, together with the key the next
+        /// page starts at, if any
+        res: Result<(Vec<Value>, Option<Vec<u8>>), ClientError>,
         /// Unique message identifier
         msg_id: MsgId,
     },
@@ -109,11 +116,11 @@ pub enum Response {
     },
 
     // Permission Actions
-    /// Returns a complete list of MutableData permissions stored on the network
-    /// or an error in case of failure.
+    /// Returns a complete list of MutableData permissions stored on the network, together with
+    /// the users whose permissions have expired, or an error in case of failure.
     ListMDataPermissions {
-        /// Result of getting a list of permissions in MutableData
-        res: Result<BTreeMap<User, PermissionSet>, ClientError>,
+        /// Result of getting a list of permissions and the expired users in MutableData
+        res: Result<(BTreeMap<User, PermissionSet>, BTreeSet<User>), ClientError>,
         /// Unique message identifier
         msg_id: MsgId,
     },
@@ -174,13 +181,186 @@ pub enum Response {
         /// Unique message identifier
         msg_id: MsgId,
     },
+
+    // --- Credit ---
+    // ==========================
+    /// Returns a success or failure status of transferring credit.
+    TransferCredit {
+        /// Result of transferring credit
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns the current balance of an account.
+    GetBalance {
+        /// Result of fetching the balance
+        res: Result<u64, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a page of the credit transfers in and out of an account, oldest first.
+    GetBalanceHistory {
+        /// Result of fetching a page of the balance history, together with the sequence number
+        /// the next page starts at, if any
+        res: Result<(Vec<Transaction>, Option<u64>), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- Invitations ---
+    // ==========================
+    /// Returns a success or failure status of issuing an invitation code.
+    IssueInvitation {
+        /// Result of issuing an invitation code
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a success or failure status of claiming an invitation code.
+    ClaimInvitation {
+        /// Result of claiming an invitation code and storing the session packet
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- AppendableData ---
+    // ==========================
+    /// Returns a success or failure status of putting AppendableData to the network.
+    PutAData {
+        /// Result of putting AppendableData to the network.
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a result of fetching AppendableData from the network.
+    GetAData {
+        /// Result of fetching AppendableData from the network.
+        res: Result<AppendableData, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns the index the next entry will be appended at.
+    GetADataIndex {
+        /// Result of getting the index of AppendableData
+        res: Result<u64, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a range of entries in AppendableData or an error in case of failure.
+    GetADataRange {
+        /// Result of getting a range of entries in AppendableData
+        res: Result<Vec<Vec<u8>>, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a success or failure status of appending to AppendableData in the network.
+    AppendToAData {
+        /// Result of appending entries to AppendableData
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData entry history ---
+    // ==========================
+    /// Returns the previous values of an entry in MutableData, oldest first, or an error in case
+    /// of failure.
+    GetMDataEntryHistory {
+        /// Result of getting the history of an entry in MutableData
+        res: Result<Vec<Value>, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData batches ---
+    // ==========================
+    /// Returns a success or failure status of mutating several MutableData in the network.
+    MutateMDataBatch {
+        /// Result of mutating the entries of several MutableData
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData changes ---
+    // ==========================
+    /// Returns the changes to the entries of MutableData since a data version or an error in case
+    /// of failure.
+    GetMDataChangesSince {
+        /// Result of getting the changes to the entries of MutableData
+        res: Result<MDataChanges, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- Unpublished ImmutableData ---
+    // ==========================
+    /// Returns a success or failure status of putting unpublished ImmutableData to the network.
+    PutUnpubIData {
+        /// Result of putting UnpubImmutableData to the network.
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a result of fetching unpublished ImmutableData from the network.
+    GetUnpubIData {
+        /// Result of fetching UnpubImmutableData from the network.
+        res: Result<UnpubImmutableData, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a success or failure status of deleting unpublished ImmutableData.
+    DeleteUnpubIData {
+        /// Result of deleting UnpubImmutableData from the network.
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- ImmutableData ranges ---
+    // ==========================
+    /// Returns a result of fetching a byte range of ImmutableData.
+    GetIDataRange {
+        /// Result of fetching the range, including its Merkle proof.
+        res: Result<IDataRange, ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+
+    // --- MutableData subscriptions ---
+    // ==========================
+    /// Returns a success or failure status of subscribing to mutations of MutableData.
+    SubscribeMData {
+        /// Result of subscribing to MutableData
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Returns a success or failure status of cancelling a subscription to MutableData.
+    UnsubscribeMData {
+        /// Result of unsubscribing from MutableData
+        res: Result<(), ClientError>,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
+    /// Notifies a subscribed client of a mutation of MutableData. This isn't a reply to any
+    /// request of the client, so the message identifier is derived from that of the request which
+    /// mutated the data.
+    MDataChanged {
+        /// The mutated data and the changed entries
+        notification: MDataNotification,
+        /// Unique message identifier
+        msg_id: MsgId,
+    },
 }
 
 impl Response {
     /// The priority Crust should send this message with.
     pub fn priority(&self) -> u8 {
         match *self {
-            Response::GetIData { res: Ok(_), .. } => 5,
+            Response::GetIData { res: Ok(_), .. }
+            | Response::GetUnpubIData { res: Ok(_), .. } => 5,
             Response::GetMDataValue { res: Ok(_), .. }
             | Response::GetMDataShell { res: Ok(_), .. } => 4,
             _ => 3,
@@ -194,6 +374,10 @@ impl Response {
             GetAccountInfo { ref msg_id, .. }
             | PutIData { ref msg_id, .. }
             | GetIData { ref msg_id, .. }
+            | GetIDataRange { ref msg_id, .. }
+            | PutUnpubIData { ref msg_id, .. }
+            | GetUnpubIData { ref msg_id, .. }
+            | DeleteUnpubIData { ref msg_id, .. }
             | PutMData { ref msg_id, .. }
             | GetMData { ref msg_id, .. }
             | GetMDataVersion { ref msg_id, .. }
@@ -202,24 +386,56 @@ impl Response {
             | ListMDataKeys { ref msg_id, .. }
             | ListMDataValues { ref msg_id, .. }
             | GetMDataValue { ref msg_id, .. }
+            | GetMDataEntryHistory { ref msg_id, .. }
+            | GetMDataChangesSince { ref msg_id, .. }
             | MutateMDataEntries { ref msg_id, .. }
+            | MutateMDataBatch { ref msg_id, .. }
             | ListMDataPermissions { ref msg_id, .. }
             | ListMDataUserPermissions { ref msg_id, .. }
             | SetMDataUserPermissions { ref msg_id, .. }
             | DelMDataUserPermissions { ref msg_id, .. }
             | ChangeMDataOwner { ref msg_id, .. }
+            | SubscribeMData { ref msg_id, .. }
+            | UnsubscribeMData { ref msg_id, .. }
+            | MDataChanged { ref msg_id, .. }
+            | PutAData { ref msg_id, .. }
+            | GetAData { ref msg_id, .. }
+            | GetADataIndex { ref msg_id, .. }
+            | GetADataRange { ref msg_id, .. }
+            | AppendToAData { ref msg_id, .. }
             | ListAuthKeysAndVersion { ref msg_id, .. }
             | InsAuthKey { ref msg_id, .. }
-            | DelAuthKey { ref msg_id, .. } => msg_id,
+            | DelAuthKey { ref msg_id, .. }
+            | TransferCredit { ref msg_id, .. }
+            | GetBalance { ref msg_id, .. }
+            | GetBalanceHistory { ref msg_id, .. }
+            | IssueInvitation { ref msg_id, .. }
+            | ClaimInvitation { ref msg_id, .. } => msg_id,
         }
     }
 
     /// Is this response cacheable?
     pub fn is_cacheable(&self) -> bool {
-        if let Response::GetIData { .. } = *self {
-            true
-        } else {
-            false
+        match *self {
+            Response::GetIData { .. } => true,
+            // Only cached together with the request, which identifies the data, and until a
+            // newer version of the data is seen.
+            Response::GetMDataShell { res: Ok(_), .. }
+            | Response::GetMDataValue { res: Ok(_), .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether this response reports a successful mutation of MutableData, which makes the
+    /// cached responses about it stale.
+    pub(crate) fn is_mdata_mutation_success(&self) -> bool {
+        match *self {
+            Response::MutateMDataEntries { res: Ok(()), .. }
+            | Response::MutateMDataBatch { res: Ok(()), .. }
+            | Response::SetMDataUserPermissions { res: Ok(()), .. }
+            | Response::DelMDataUserPermissions { res: Ok(()), .. }
+            | Response::ChangeMDataOwner { res: Ok(()), .. } => true,
+            _ => false,
         }
     }
 }
@@ -232,3 +448,54 @@ pub struct AccountInfo {
     /// Number of mutate operations remaining for the account.
     pub mutations_available: u64,
 }
+
+/// A credit transfer, as recorded in the balance history of both accounts involved.
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct Transaction {
+    /// Identifier of the `TransferCredit` request.
+    pub msg_id: MsgId,
+    /// Name of the debited account.
+    pub from: XorName,
+    /// Name of the credited account.
+    pub to: XorName,
+    /// Amount transferred.
+    pub amount: u64,
+    /// Balance of the account this record belongs to, after the transfer.
+    pub balance: u64,
+}
+
+/// A mutation of MutableData, as sent to the clients subscribed to it.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize, Debug)]
+pub struct MDataNotification {
+    /// Network identifier of the data.
+    pub name: XorName,
+    /// Type tag of the data.
+    pub tag: u64,
+    /// Version of the data after the mutation.
+    pub version: u64,
+    /// Data version after the mutation, which the entry changes can be fetched up to.
+    pub data_version: u64,
+    /// Keys of the inserted, updated and deleted entries. Empty if only the permissions or the
+    /// owners changed.
+    pub keys: BTreeSet<Vec<u8>>,
+}
+
+impl MDataNotification {
+    /// Creates the notification of a mutation which left `data` in its current state and changed
+    /// the entries under `keys`.
+    pub fn new(data: &MutableData, keys: BTreeSet<Vec<u8>>) -> MDataNotification {
+        MDataNotification {
+            name: *data.name(),
+            tag: data.tag(),
+            version: data.version(),
+            data_version: data.data_version(),
+            keys,
+        }
+    }
+
+    /// Returns the data version below which cached responses about the data are stale. Changes of
+    /// the permissions or the owners bump the data version too, so this holds for them as well.
+    pub(crate) fn stale_below(&self) -> u64 {
+        self.data_version
+    }
+}
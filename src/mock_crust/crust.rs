@@ -7,7 +7,7 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 pub use super::support::Config;
-use super::support::{Endpoint, Network, ServiceHandle, ServiceImpl};
+use super::support::{self, Endpoint, Network, ServiceHandle, ServiceImpl};
 use maidsafe_utilities::event_sender;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
@@ -21,9 +21,10 @@ use std::{fmt, thread};
 /// TCP listener port
 pub const LISTENER_PORT: u16 = 5485;
 
-/// Mock version of config reader
+/// Mock version of config reader. Returns the config of the `ServiceHandle` set with
+/// `make_current`, if any.
 pub fn read_config_file() -> Result<Config, CrustError> {
-    Ok(Config::new())
+    Ok(support::current_config().unwrap_or_else(Config::new))
 }
 
 /// Mock version of `crust::Service`
@@ -42,13 +43,14 @@ impl<UID: Uid> Service<UID> {
     }
 
     /// Create a new mock `Service` using the make_current/get_current mechanism to get the
-    /// associated `ServiceHandle`. Ignores configuration.
+    /// associated `ServiceHandle`. The configuration replaces the one of the handle.
     pub fn with_config(
         handle: ServiceHandle<UID>,
         event_sender: CrustEventSender<UID>,
-        _config: Config,
+        config: Config,
         uid: UID,
     ) -> Result<Self, CrustError> {
+        handle.0.borrow_mut().config = config;
         Self::with_handle(&handle, event_sender, uid)
     }
 
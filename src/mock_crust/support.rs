@@ -299,6 +299,11 @@ impl<UID: Uid> ServiceHandle<UID> {
         self.0.borrow().endpoint
     }
 
+    /// Contacts the `Service` bound to this handle bootstraps against, in the order they are tried.
+    pub fn bootstrap_contacts(&self) -> Vec<Endpoint> {
+        self.0.borrow().config.hard_coded_contacts.clone()
+    }
+
     /// Returns `true` if this service is connected to the given one.
     pub fn is_connected(&self, handle: &Self) -> bool {
         self.0
@@ -322,7 +327,7 @@ pub struct ServiceImpl<UID: Uid> {
     pub network: Network<UID>,
     endpoint: Endpoint,
     pub uid: Option<UID>,
-    config: Config,
+    pub config: Config,
     pub accept_bootstrap: bool,
     pub listening_tcp: bool,
     event_sender: Option<CrustEventSender<UID>>,
@@ -722,6 +727,16 @@ pub fn take_current() -> ServiceHandle<PublicId> {
     })
 }
 
+/// Returns the config of the `ServiceHandle` set with `make_current`, if any.
+pub(super) fn current_config() -> Option<Config> {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map(|handle| handle.0.borrow().config.clone())
+    })
+}
+
 /// Invokes the given lambda with a reference to the `ServiceHandle` set with `make_current`.
 pub fn with_current<F, R>(f: F) -> R
 where
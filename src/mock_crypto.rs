@@ -46,6 +46,8 @@ pub mod rust_sodium {
             pub const SECRETKEYBYTES: usize = 32;
             /// Number of bytes in a `Signature`.
             pub const SIGNATUREBYTES: usize = 32;
+            /// Number of bytes in a `Seed`.
+            pub const SEEDBYTES: usize = 32;
 
             /// Mock signing public key.
             #[derive(
@@ -76,6 +78,10 @@ pub mod rust_sodium {
                 }
             }
 
+            /// Mock seed for deterministic key generation.
+            #[derive(Clone, Debug, Eq, PartialEq)]
+            pub struct Seed(pub [u8; SEEDBYTES]);
+
             /// Generate mock public and corresponding secret key.
             pub fn gen_keypair() -> (PublicKey, SecretKey) {
                 with_rng(|rng| {
@@ -84,6 +90,11 @@ pub mod rust_sodium {
                 })
             }
 
+            /// Generate mock public and corresponding secret key from the given seed.
+            pub fn keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
+                (PublicKey(seed.0), SecretKey(seed.0))
+            }
+
             /// Sign a message using the mock secret key.
             pub fn sign_detached(m: &[u8], sk: &SecretKey) -> Signature {
                 let mut temp = m.to_vec();
@@ -127,6 +138,7 @@ pub mod rust_sodium {
             pub struct SecretKey(pub [u8; SECRETKEYBYTES]);
 
             /// Mock nonce for asymmetric encryption/decryption.
+            #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
             pub struct Nonce(pub [u8; NONCEBYTES]);
 
             /// Generate mock public and corresponding secret key.
@@ -181,6 +193,111 @@ pub mod rust_sodium {
                 Ok(c[n + p + s..].to_vec())
             }
         }
+
+        /// Mock symmetric encryption.
+        pub mod secretbox {
+            use super::super::with_rng;
+            use rand::Rng;
+
+            /// Number of bytes in a `Key`.
+            pub const KEYBYTES: usize = 32;
+            /// Number of bytes in a `Nonce`.
+            pub const NONCEBYTES: usize = 4;
+
+            /// Mock key for symmetric encryption/decryption.
+            #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+            pub struct Key(pub [u8; KEYBYTES]);
+
+            /// Mock nonce for symmetric encryption/decryption.
+            #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+            pub struct Nonce(pub [u8; NONCEBYTES]);
+
+            impl Nonce {
+                /// Construct a mock nonce from a slice of exactly `NONCEBYTES` bytes.
+                pub fn from_slice(bs: &[u8]) -> Option<Nonce> {
+                    if bs.len() != NONCEBYTES {
+                        return None;
+                    }
+                    let mut nonce = [0; NONCEBYTES];
+                    nonce.copy_from_slice(bs);
+                    Some(Nonce(nonce))
+                }
+            }
+
+            /// Generate mock key.
+            pub fn gen_key() -> Key {
+                with_rng(|rng| Key(rng.gen()))
+            }
+
+            /// Generate mock nonce.
+            pub fn gen_nonce() -> Nonce {
+                with_rng(|rng| Nonce(rng.gen()))
+            }
+
+            /// Perform mock encryption of the given message using the key and nonce.
+            pub fn seal(m: &[u8], nonce: &Nonce, key: &Key) -> Vec<u8> {
+                let mut result = Vec::with_capacity(m.len() + nonce.0.len() + key.0.len());
+                result.extend(&nonce.0);
+                result.extend(&key.0);
+                result.extend(m);
+                result
+            }
+
+            /// Perform mock decryption of the given ciphertext using the key and nonce.
+            pub fn open(c: &[u8], nonce: &Nonce, key: &Key) -> Result<Vec<u8>, ()> {
+                let n = nonce.0.len();
+                let k = key.0.len();
+
+                if c.len() < n + k || c[0..n] != nonce.0 || c[n..n + k] != key.0 {
+                    return Err(());
+                }
+
+                Ok(c[n + k..].to_vec())
+            }
+        }
+
+        /// Mock password hashing.
+        pub mod pwhash {
+            use tiny_keccak::sha3_256;
+
+            /// Number of bytes in a `Salt`.
+            pub const SALTBYTES: usize = 32;
+            /// Mock operations limit for interactive use.
+            pub const OPSLIMIT_INTERACTIVE: OpsLimit = OpsLimit(1);
+            /// Mock memory limit for interactive use.
+            pub const MEMLIMIT_INTERACTIVE: MemLimit = MemLimit(1);
+
+            /// Mock salt for password hashing.
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub struct Salt(pub [u8; SALTBYTES]);
+
+            /// Mock number of computations to perform when hashing.
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub struct OpsLimit(pub usize);
+
+            /// Mock amount of memory to use when hashing.
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub struct MemLimit(pub usize);
+
+            /// Derive a mock key of `key.len()` bytes from the password and salt. The limits are
+            /// ignored.
+            pub fn derive_key<'a>(
+                key: &'a mut [u8],
+                passwd: &[u8],
+                salt: &Salt,
+                _opslimit: OpsLimit,
+                _memlimit: MemLimit,
+            ) -> Result<&'a [u8], ()> {
+                let mut preimage = salt.0.to_vec();
+                preimage.extend_from_slice(passwd);
+                let mut hash = sha3_256(&preimage);
+                for chunk in key.chunks_mut(hash.len()) {
+                    chunk.copy_from_slice(&hash[..chunk.len()]);
+                    hash = sha3_256(&hash);
+                }
+                Ok(key)
+            }
+        }
     }
 
     fn with_rng<F, R>(f: F) -> R
@@ -193,7 +310,7 @@ pub mod rust_sodium {
 
 #[cfg(test)]
 mod tests {
-    use super::rust_sodium::crypto::{box_, sign};
+    use super::rust_sodium::crypto::{box_, secretbox, sign};
     use rand::{self, Rng};
 
     #[test]
@@ -236,4 +353,19 @@ mod tests {
         assert!(box_::open(&encrypted, &nonce0, &pk0, &sk1).is_err());
         assert!(box_::open(&encrypted, &nonce0, &pk1, &sk1).is_err());
     }
+
+    #[test]
+    fn secretbox_seal_and_open() {
+        let key0 = secretbox::gen_key();
+        let key1 = secretbox::gen_key();
+        let nonce0 = secretbox::gen_nonce();
+
+        let original: Vec<_> = rand::thread_rng().gen_iter().take(10).collect();
+        let encrypted = secretbox::seal(&original, &nonce0, &key0);
+        let decrypted = unwrap!(secretbox::open(&encrypted, &nonce0, &key0));
+        assert_eq!(decrypted, original);
+
+        assert!(secretbox::open(&encrypted, &nonce0, &key1).is_err());
+        assert!(secretbox::open(&encrypted[..3], &nonce0, &key0).is_err());
+    }
 }
@@ -10,16 +10,20 @@ use crate::action::Action;
 use crate::cache::{Cache, NullCache};
 use crate::client_error::ClientError;
 use crate::config_handler::{self, Config};
-use crate::data::{EntryAction, ImmutableData, MutableData, PermissionSet, User, Value};
+use crate::data::{
+    AppendableData, EntryAction, IDataRange, ImmutableData, MDataChanges, MutableData,
+    PermissionSet, TypeTagValidator, TypeTagValidators, UnpubImmutableData, User, Value,
+};
 use crate::error::{InterfaceError, RoutingError};
 use crate::event::Event;
 use crate::event_stream::{EventStepper, EventStream};
 use crate::id::{FullId, PublicId};
 use crate::messages::{
-    AccountInfo, Request, Response, UserMessage, CLIENT_GET_PRIORITY, DEFAULT_PRIORITY,
-    RELOCATE_PRIORITY,
+    AccountInfo, MDataNotification, Request, Response, Transaction, UserMessage,
+    CLIENT_GET_PRIORITY, DEFAULT_PRIORITY, RELOCATE_PRIORITY,
 };
 use crate::outbox::{EventBox, EventBuf};
+use crate::routing_snapshot::RoutingSnapshot;
 #[cfg(feature = "use-mock-crust")]
 use crate::routing_table::Prefix;
 use crate::routing_table::{Authority, RoutingTable};
@@ -34,6 +38,7 @@ use rust_sodium;
 use std::collections::{BTreeMap, BTreeSet};
 #[cfg(feature = "use-mock-crust")]
 use std::fmt::{self, Debug, Formatter};
+use std::mem;
 #[cfg(feature = "use-mock-crust")]
 use std::net::IpAddr;
 use std::sync::mpsc::{channel, Receiver, RecvError, Sender, TryRecvError};
@@ -59,6 +64,27 @@ macro_rules! impl_request {
     ($method:ident, $message:ident { $($pname:ident : $ptype:ty),* }, $priority:expr) => {
         impl_request!($method, $message { $($pname:$ptype),*, }, $priority);
     };
+
+    // Like the above, but also sets the fields listed in the second braces to fixed values.
+    ($method:ident,
+     $message:ident { $($pname:ident : $ptype:ty),*, },
+     { $($fname:ident : $fvalue:expr),* },
+     $priority:expr) => {
+        #[allow(missing_docs)]
+        #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+        pub fn $method(&mut self,
+                       src: Authority<XorName>,
+                       dst: Authority<XorName>,
+                       $($pname: $ptype),*)
+                       -> Result<(), InterfaceError> {
+            let msg = UserMessage::Request(Request::$message {
+                $($pname: $pname),*,
+                $($fname: $fvalue),*
+            });
+
+            self.send_action(src, dst, msg, $priority)
+        }
+    };
 }
 
 // Helper macro to implement response sending methods.
@@ -85,10 +111,13 @@ pub struct NodeBuilder {
     cache: Box<Cache>,
     first: bool,
     config: Option<Config>,
+    validators: TypeTagValidators,
+    snapshot: Option<RoutingSnapshot>,
 }
 
 impl NodeBuilder {
-    /// Configures the node to use the given request cache.
+    /// Configures the node to use the given request cache, e.g. an `LruCache` or a `DiskCache`.
+    /// Without one, nothing is cached.
     pub fn cache(self, cache: Box<Cache>) -> NodeBuilder {
         NodeBuilder { cache, ..self }
     }
@@ -106,13 +135,34 @@ impl NodeBuilder {
         }
     }
 
+    /// Configures the node to check `MutableData` with the type tag `tag` against `validator`,
+    /// replacing any validator for it. The built-in type tags are validated by default. The
+    /// validators are available to the vaults through `Node::type_tag_validators`.
+    pub fn type_tag_validator(mut self, tag: u64, validator: Box<TypeTagValidator>) -> NodeBuilder {
+        self.validators.insert(tag, validator);
+        self
+    }
+
+    /// Restarts the node with the routing table it had before, as returned by
+    /// `Node::routing_snapshot`. The node bootstraps against the remembered peers before the
+    /// configured contacts. It still joins under a new name, but connects to the remembered peers
+    /// the network confirms first.
+    pub fn snapshot(self, snapshot: RoutingSnapshot) -> NodeBuilder {
+        NodeBuilder {
+            snapshot: Some(snapshot),
+            ..self
+        }
+    }
+
     /// Creates new `Node`.
     ///
     /// It will automatically connect to the network in the same way a client does, but then
     /// request a new name and integrate itself into the network using the new name.
     ///
     /// The initial `Node` object will have newly generated keys.
-    pub fn create(self) -> Result<Node, RoutingError> {
+    pub fn create(mut self) -> Result<Node, RoutingError> {
+        let validators = mem::replace(&mut self.validators, TypeTagValidators::new());
+
         // If we're not in a test environment where we might want to manually seed the crypto RNG
         // then seed randomly.
         #[cfg(not(feature = "use-mock-crust"))]
@@ -129,6 +179,7 @@ impl NodeBuilder {
             interface_result_rx: rx,
             machine,
             event_buffer: ev_buffer,
+            validators,
         })
     }
 
@@ -138,6 +189,10 @@ impl NodeBuilder {
         let config = self.config.unwrap_or_else(config_handler::get_config);
         let dev_config = config.dev.unwrap_or_default();
         let min_section_size = dev_config.min_section_size.unwrap_or(MIN_SECTION_SIZE);
+        let bootstrap_config = self
+            .snapshot
+            .as_ref()
+            .map(RoutingSnapshot::read_bootstrap_config);
 
         StateMachine::new(
             move |action_sender, crust_service, timer, outbox2| {
@@ -164,7 +219,9 @@ impl NodeBuilder {
                     Bootstrapping::new(
                         action_sender,
                         self.cache,
-                        BootstrappingTargetState::JoiningNode,
+                        BootstrappingTargetState::JoiningNode {
+                            snapshot: self.snapshot,
+                        },
                         crust_service,
                         full_id,
                         min_section_size,
@@ -174,7 +231,7 @@ impl NodeBuilder {
                 }
             },
             pub_id,
-            None,
+            bootstrap_config,
             outbox,
         )
     }
@@ -192,6 +249,7 @@ pub struct Node {
     interface_result_rx: Receiver<Result<(), InterfaceError>>,
     machine: StateMachine,
     event_buffer: EventBuf,
+    validators: TypeTagValidators,
 }
 
 impl Node {
@@ -201,6 +259,8 @@ impl Node {
             cache: Box::new(NullCache),
             first: false,
             config: None,
+            validators: TypeTagValidators::new(),
+            snapshot: None,
         }
     }
 
@@ -224,6 +284,26 @@ impl Node {
         DEFAULT_PRIORITY
     );
 
+    /// Send a `GetUnpubIData` request to `dst` to retrieve data from the network.
+    impl_request!(
+        send_get_unpub_idata_request,
+        GetUnpubIData {
+            name: XorName,
+            msg_id: MessageId,
+        },
+        RELOCATE_PRIORITY
+    );
+
+    /// Send a `PutUnpubIData` request to `dst` to store data on the network.
+    impl_request!(
+        send_put_unpub_idata_request,
+        PutUnpubIData {
+            data: UnpubImmutableData,
+            msg_id: MessageId,
+        },
+        DEFAULT_PRIORITY
+    );
+
     /// Send a `GetMData` request to `dst` to retrieve data from the network.
     /// Note: responses to this request are unlikely to accumulate during churn.
     impl_request!(
@@ -258,6 +338,15 @@ impl Node {
                   },
                   DEFAULT_PRIORITY);
 
+    /// Send a `MutateMDataBatch` request.
+    impl_request!(send_mutate_mdata_batch_request,
+                  MutateMDataBatch {
+                      mutations: BTreeMap<(XorName, u64), BTreeMap<Vec<u8>, EntryAction>>,
+                      msg_id: MessageId,
+                      requester: sign::PublicKey,
+                  },
+                  DEFAULT_PRIORITY);
+
     /// Send a `GetMDataShell` request.
     impl_request!(
         send_get_mdata_shell_request,
@@ -266,6 +355,7 @@ impl Node {
             tag: u64,
             msg_id: MessageId,
         },
+        { allow_cached: false },
         RELOCATE_PRIORITY
     );
 
@@ -277,8 +367,21 @@ impl Node {
                       key: Vec<u8>,
                       msg_id: MessageId,
                   },
+                  { allow_cached: false },
                   RELOCATE_PRIORITY);
 
+    /// Send a `GetMDataChangesSince` request.
+    impl_request!(
+        send_get_mdata_changes_since_request,
+        GetMDataChangesSince {
+            name: XorName,
+            tag: u64,
+            since: u64,
+            msg_id: MessageId,
+        },
+        RELOCATE_PRIORITY
+    );
+
     /// Send a `SetMDataUserPermissions` request.
     impl_request!(
         send_set_mdata_user_permissions_request,
@@ -291,6 +394,23 @@ impl Node {
             msg_id: MessageId,
             requester: sign::PublicKey,
         },
+        { signatures: BTreeMap::new() },
+        DEFAULT_PRIORITY
+    );
+
+    /// Send a `SetMDataUserPermissions` request signed by the owners.
+    impl_request!(
+        send_set_mdata_user_permissions_by_owners_request,
+        SetMDataUserPermissions {
+            name: XorName,
+            tag: u64,
+            user: User,
+            permissions: PermissionSet,
+            version: u64,
+            msg_id: MessageId,
+            requester: sign::PublicKey,
+            signatures: BTreeMap<sign::PublicKey, sign::Signature>,
+        },
         DEFAULT_PRIORITY
     );
 
@@ -305,6 +425,22 @@ impl Node {
             msg_id: MessageId,
             requester: sign::PublicKey,
         },
+        { signatures: BTreeMap::new() },
+        DEFAULT_PRIORITY
+    );
+
+    /// Send a `DelMDataUserPermissions` request signed by the owners.
+    impl_request!(
+        send_del_mdata_user_permissions_by_owners_request,
+        DelMDataUserPermissions {
+            name: XorName,
+            tag: u64,
+            user: User,
+            version: u64,
+            msg_id: MessageId,
+            requester: sign::PublicKey,
+            signatures: BTreeMap<sign::PublicKey, sign::Signature>,
+        },
         DEFAULT_PRIORITY
     );
 
@@ -316,8 +452,95 @@ impl Node {
                       new_owners: BTreeSet<sign::PublicKey>,
                       version: u64,
                       msg_id: MessageId,
+                  },
+                  { owners_threshold: 1, signatures: BTreeMap::new() },
+                  DEFAULT_PRIORITY);
+
+    /// Send a `ChangeMDataOwner` request replacing the owners and the owners threshold, signed by
+    /// the current owners.
+    impl_request!(send_change_mdata_owners_request,
+                  ChangeMDataOwner {
+                      name: XorName,
+                      tag: u64,
+                      new_owners: BTreeSet<sign::PublicKey>,
+                      owners_threshold: u64,
+                      version: u64,
+                      msg_id: MessageId,
+                      signatures: BTreeMap<sign::PublicKey, sign::Signature>,
                   }, DEFAULT_PRIORITY);
 
+    /// Send a `PutAData` request.
+    impl_request!(
+        send_put_adata_request,
+        PutAData {
+            data: AppendableData,
+            msg_id: MessageId,
+            requester: sign::PublicKey,
+        },
+        DEFAULT_PRIORITY
+    );
+
+    /// Send a `GetAData` request to `dst` to retrieve data from the network.
+    /// Note: responses to this request are unlikely to accumulate during churn.
+    impl_request!(
+        send_get_adata_request,
+        GetAData {
+            name: XorName,
+            tag: u64,
+            msg_id: MessageId,
+        },
+        RELOCATE_PRIORITY
+    );
+
+    /// Send a `GetADataIndex` request.
+    impl_request!(
+        send_get_adata_index_request,
+        GetADataIndex {
+            name: XorName,
+            tag: u64,
+            msg_id: MessageId,
+        },
+        RELOCATE_PRIORITY
+    );
+
+    /// Send a `GetADataRange` request.
+    /// Note: responses to this request are unlikely to accumulate during churn.
+    impl_request!(
+        send_get_adata_range_request,
+        GetADataRange {
+            name: XorName,
+            tag: u64,
+            start: u64,
+            end: u64,
+            msg_id: MessageId,
+        },
+        RELOCATE_PRIORITY
+    );
+
+    /// Send an `AppendToAData` request.
+    impl_request!(send_append_to_adata_request,
+                  AppendToAData {
+                      name: XorName,
+                      tag: u64,
+                      entries: Vec<Vec<u8>>,
+                      index: u64,
+                      msg_id: MessageId,
+                      requester: sign::PublicKey,
+                  },
+                  DEFAULT_PRIORITY);
+
+    /// Send a `TransferCredit` request, e.g. from the debited account's `ClientManager` to the
+    /// credited one's.
+    impl_request!(
+        send_transfer_credit_request,
+        TransferCredit {
+            to: XorName,
+            amount: u64,
+            msg_id: MessageId,
+        },
+        DEFAULT_PRIORITY
+    );
+
     /// Send a `Refresh` request from `src` to `dst` to trigger churn.
     pub fn send_refresh_request(
         &mut self,
@@ -355,6 +578,44 @@ impl Node {
     /// Respond to a `PutIData` request.
     impl_response!(send_put_idata_response, PutIData, (), DEFAULT_PRIORITY);
 
+    /// Respond to a `GetIDataRange` request.
+    impl_response!(
+        send_get_idata_range_response,
+        GetIDataRange,
+        IDataRange,
+        CLIENT_GET_PRIORITY
+    );
+
+    /// Respond to a `GetUnpubIData` request.
+    pub fn send_get_unpub_idata_response(
+        &mut self,
+        src: Authority<XorName>,
+        dst: Authority<XorName>,
+        res: Result<UnpubImmutableData, ClientError>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let msg = UserMessage::Response(Response::GetUnpubIData { res, msg_id });
+
+        let priority = relocate_priority(&dst);
+        self.send_action(src, dst, msg, priority)
+    }
+
+    /// Respond to a `PutUnpubIData` request.
+    impl_response!(
+        send_put_unpub_idata_response,
+        PutUnpubIData,
+        (),
+        DEFAULT_PRIORITY
+    );
+
+    /// Respond to a `DeleteUnpubIData` request.
+    impl_response!(
+        send_delete_unpub_idata_response,
+        DeleteUnpubIData,
+        (),
+        DEFAULT_PRIORITY
+    );
+
     /// Respond to a `GetMData` request.
     /// Note: this response is unlikely to accumulate during churn.
     pub fn send_get_mdata_response(
@@ -400,7 +661,7 @@ impl Node {
     impl_response!(
         send_list_mdata_entries_response,
         ListMDataEntries,
-        BTreeMap<Vec<u8>, Value>,
+        (BTreeMap<Vec<u8>, Value>, Option<Vec<u8>>),
         CLIENT_GET_PRIORITY
     );
 
@@ -409,7 +670,7 @@ impl Node {
     impl_response!(
         send_list_mdata_keys_response,
         ListMDataKeys,
-        BTreeSet<Vec<u8>>,
+        (BTreeSet<Vec<u8>>, Option<Vec<u8>>),
         CLIENT_GET_PRIORITY
     );
 
@@ -418,7 +679,7 @@ impl Node {
     impl_response!(
         send_list_mdata_values_response,
         ListMDataValues,
-        Vec<Value>,
+        (Vec<Value>, Option<Vec<u8>>),
         CLIENT_GET_PRIORITY
     );
 
@@ -436,6 +697,22 @@ impl Node {
         self.send_action(src, dst, msg, priority)
     }
 
+    /// Respond to a `GetMDataEntryHistory` request.
+    impl_response!(
+        send_get_mdata_entry_history_response,
+        GetMDataEntryHistory,
+        Vec<Value>,
+        CLIENT_GET_PRIORITY
+    );
+
+    /// Respond to a `GetMDataChangesSince` request.
+    impl_response!(
+        send_get_mdata_changes_since_response,
+        GetMDataChangesSince,
+        MDataChanges,
+        CLIENT_GET_PRIORITY
+    );
+
     /// Respond to a `MutateMDataEntries` request.
     impl_response!(
         send_mutate_mdata_entries_response,
@@ -444,10 +721,18 @@ impl Node {
         DEFAULT_PRIORITY
     );
 
+    /// Respond to a `MutateMDataBatch` request.
+    impl_response!(
+        send_mutate_mdata_batch_response,
+        MutateMDataBatch,
+        (),
+        DEFAULT_PRIORITY
+    );
+
     /// Respond to a `ListMDataPermissions` request.
     impl_response!(send_list_mdata_permissions_response,
                    ListMDataPermissions,
-                   BTreeMap<User, PermissionSet>,
+                   (BTreeMap<User, PermissionSet>, BTreeSet<User>),
                    CLIENT_GET_PRIORITY);
 
     /// Respond to a `ListMDataUserPermissions` request.
@@ -480,6 +765,26 @@ impl Node {
     /// Respond to a `DelAuthKey` request.
     impl_response!(send_del_auth_key_response, DelAuthKey, (), DEFAULT_PRIORITY);
 
+    /// Respond to a `TransferCredit` request.
+    impl_response!(send_transfer_credit_response, TransferCredit, (), DEFAULT_PRIORITY);
+
+    /// Respond to a `GetBalance` request.
+    impl_response!(send_get_balance_response, GetBalance, u64, CLIENT_GET_PRIORITY);
+
+    /// Respond to a `GetBalanceHistory` request.
+    impl_response!(
+        send_get_balance_history_response,
+        GetBalanceHistory,
+        (Vec<Transaction>, Option<u64>),
+        CLIENT_GET_PRIORITY
+    );
+
+    /// Respond to an `IssueInvitation` request.
+    impl_response!(send_issue_invitation_response, IssueInvitation, (), DEFAULT_PRIORITY);
+
+    /// Respond to a `ClaimInvitation` request.
+    impl_response!(send_claim_invitation_response, ClaimInvitation, (), DEFAULT_PRIORITY);
+
     /// Respond to a `DelMDataUserPermissions` request.
     impl_response!(
         send_del_mdata_user_permissions_response,
@@ -496,6 +801,83 @@ impl Node {
         DEFAULT_PRIORITY
     );
 
+    /// Respond to a `SubscribeMData` request.
+    impl_response!(send_subscribe_mdata_response, SubscribeMData, (), DEFAULT_PRIORITY);
+
+    /// Respond to a `UnsubscribeMData` request.
+    impl_response!(
+        send_unsubscribe_mdata_response,
+        UnsubscribeMData,
+        (),
+        DEFAULT_PRIORITY
+    );
+
+    /// Notify the `subscribers` of a `MutableData` of a mutation. `msg_id` is the identifier of
+    /// the request which caused the mutation, so every node of the section sends the same
+    /// notifications and they accumulate at the subscribers' proxies.
+    pub fn send_mdata_notifications(
+        &mut self,
+        src: Authority<XorName>,
+        subscribers: Vec<Authority<XorName>>,
+        notification: &MDataNotification,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        // Don't reuse the request's identifier: the requester may be a subscriber itself, and
+        // would then drop either the response or the notification as a duplicate.
+        let msg_id = MessageId::from_reverse(&msg_id);
+        for dst in subscribers {
+            let msg = UserMessage::Response(Response::MDataChanged {
+                notification: notification.clone(),
+                msg_id,
+            });
+            self.send_action(src, dst, msg, DEFAULT_PRIORITY)?;
+        }
+        Ok(())
+    }
+
+    /// Respond to a `PutAData` request.
+    impl_response!(send_put_adata_response, PutAData, (), DEFAULT_PRIORITY);
+
+    /// Respond to a `GetAData` request.
+    /// Note: this response is unlikely to accumulate during churn.
+    pub fn send_get_adata_response(
+        &mut self,
+        src: Authority<XorName>,
+        dst: Authority<XorName>,
+        res: Result<AppendableData, ClientError>,
+        msg_id: MessageId,
+    ) -> Result<(), InterfaceError> {
+        let msg = UserMessage::Response(Response::GetAData { res, msg_id });
+
+        let priority = relocate_priority(&dst);
+        self.send_action(src, dst, msg, priority)
+    }
+
+    /// Respond to a `GetADataIndex` request.
+    impl_response!(
+        send_get_adata_index_response,
+        GetADataIndex,
+        u64,
+        CLIENT_GET_PRIORITY
+    );
+
+    /// Respond to a `GetADataRange` request.
+    /// Note: this response is unlikely to accumulate during churn.
+    impl_response!(
+        send_get_adata_range_response,
+        GetADataRange,
+        Vec<Vec<u8>>,
+        CLIENT_GET_PRIORITY
+    );
+
+    /// Respond to an `AppendToAData` request.
+    impl_response!(
+        send_append_to_adata_response,
+        AppendToAData,
+        (),
+        DEFAULT_PRIORITY
+    );
+
     /// Returns the first `count` names of the nodes in the routing table which are closest
     /// to the given one.
     pub fn close_group(&self, name: XorName, count: usize) -> Option<Vec<XorName>> {
@@ -512,11 +894,27 @@ impl Node {
         self.machine.routing_table().ok_or(RoutingError::Terminated)
     }
 
+    /// Returns a snapshot of the routing table of this node, to be saved and passed to
+    /// `NodeBuilder::snapshot` when the node is restarted.
+    pub fn routing_snapshot(&self) -> Result<RoutingSnapshot, RoutingError> {
+        self.machine
+            .routing_snapshot()
+            .ok_or(RoutingError::Terminated)
+    }
+
     /// Returns the minimum section size this vault is using.
     pub fn min_section_size(&self) -> usize {
         self.machine.min_section_size()
     }
 
+    /// Returns the validators for the built-in type tags and any configured with
+    /// `NodeBuilder::type_tag_validator`. The `MutableData` this vault stores should be checked
+    /// with `MutableData::validate_with_validators` and mutated with
+    /// `MutableData::mutate_entries_with_validators`.
+    pub fn type_tag_validators(&self) -> &TypeTagValidators {
+        &self.validators
+    }
+
     fn send_action(
         &mut self,
         src: Authority<XorName>,
@@ -6,7 +6,10 @@
 // KIND, either express or implied. Please review the Licences for the specific language governing
 // permissions and limitations relating to use of the SAFE Network Software.
 
-use crate::data::{MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, MAX_MUTABLE_DATA_SIZE_IN_BYTES};
+use crate::data::{
+    EntriesRange, IDATA_BLOCK_SIZE_IN_BYTES, MAX_APPENDABLE_DATA_SIZE_IN_BYTES,
+    MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, MAX_MUTABLE_DATA_ENTRIES, MAX_MUTABLE_DATA_SIZE_IN_BYTES,
+};
 use crate::error::RoutingError;
 use crate::messages::{UserMessage, MAX_PART_LEN};
 use crate::sha3::Digest256;
@@ -42,6 +45,15 @@ const SOFT_CAPACITY: u64 = 8 * 1024 * 1024;
 /// rejections. This must be at least `2 * MIN_CLIENT_CAPACITY` for the multi-client tests to work.
 #[cfg(feature = "use-mock-crust")]
 const SOFT_CAPACITY: u64 = 2 * MIN_CLIENT_CAPACITY;
+/// Upper bound on the bytes a `GetIDataRange` response carries beyond the requested range: the
+/// partial blocks at either end of the range, the Merkle proof and the envelope.
+const IDATA_RANGE_OVERHEAD: u64 = 2 * IDATA_BLOCK_SIZE_IN_BYTES + 1024;
+/// Upfront charge per entry of a limited `ListMDataEntries`, `ListMDataKeys` or `ListMDataValues`
+/// page: the average entry size of a full `MutableData`. Larger pages are charged the difference
+/// when the response is sent.
+const MDATA_PAGE_ENTRY_CHARGE: u64 = MAX_MUTABLE_DATA_SIZE_IN_BYTES / MAX_MUTABLE_DATA_ENTRIES;
+/// Bytes a paged listing response carries besides its entries: the next key and the envelope.
+const MDATA_PAGE_OVERHEAD: u64 = 1024;
 /// Duration for which entries are kept in the `overcharged` cache, in seconds.
 const OVERCHARGED_TIMEOUT_SECS: u64 = 300;
 
@@ -83,11 +95,11 @@ impl RateLimiter {
     }
 
     /// Try to add a message. If the message is a form of get request,
-    /// `MAX_IMMUTABLE_DATA_SIZE_IN_BYTES` or `MAX_MUTABLE_DATA_SIZE_IN_BYTES` bytes will be used,
-    /// otherwise the actual length of the `payload` will be used. If adding that amount will cause
-    /// the client to exceed its capacity, then `Err(ExceedsRateLimit)` is returned. If the
-    /// message is invalid, `Err(InvalidMessage)` is returned (this probably indicates malicious
-    /// behaviour).
+    /// `MAX_IMMUTABLE_DATA_SIZE_IN_BYTES`, `MAX_MUTABLE_DATA_SIZE_IN_BYTES` or
+    /// `MAX_APPENDABLE_DATA_SIZE_IN_BYTES` bytes will be used, otherwise the actual length of the
+    /// `payload` will be used. If adding that amount will cause the client to exceed its capacity,
+    /// then `Err(ExceedsRateLimit)` is returned. If the message is invalid, `Err(InvalidMessage)`
+    /// is returned (this probably indicates malicious behaviour).
     pub fn add_message(
         &mut self,
         client_ip: &IpAddr,
@@ -106,26 +118,52 @@ impl RateLimiter {
                         return Err(RoutingError::InvalidMessage);
                     }
                     match request {
-                        GetIData { .. } => (MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, true),
+                        GetIData { .. } | GetUnpubIData { .. } => {
+                            (MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, true)
+                        }
+                        GetIDataRange { len, .. } => (
+                            cmp::min(
+                                len.saturating_add(IDATA_RANGE_OVERHEAD),
+                                MAX_IMMUTABLE_DATA_SIZE_IN_BYTES,
+                            ),
+                            true,
+                        ),
+                        ListMDataEntries { ref range, .. }
+                        | ListMDataKeys { ref range, .. }
+                        | ListMDataValues { ref range, .. } => (page_charge(range), true),
                         GetAccountInfo { .. }
                         | GetMData { .. }
                         | GetMDataVersion { .. }
                         | GetMDataShell { .. }
-                        | ListMDataEntries { .. }
-                        | ListMDataKeys { .. }
-                        | ListMDataValues { .. }
                         | GetMDataValue { .. }
+                        | GetMDataEntryHistory { .. }
+                        | GetMDataChangesSince { .. }
                         | ListMDataPermissions { .. }
                         | ListMDataUserPermissions { .. }
-                        | ListAuthKeysAndVersion { .. } => (MAX_MUTABLE_DATA_SIZE_IN_BYTES, true),
+                        | ListAuthKeysAndVersion { .. }
+                        | GetBalance { .. }
+                        | GetBalanceHistory { .. } => (MAX_MUTABLE_DATA_SIZE_IN_BYTES, true),
+                        GetAData { .. } | GetADataIndex { .. } | GetADataRange { .. } => {
+                            (MAX_APPENDABLE_DATA_SIZE_IN_BYTES, true)
+                        }
                         PutIData { .. }
+                        | PutUnpubIData { .. }
+                        | DeleteUnpubIData { .. }
                         | PutMData { .. }
                         | MutateMDataEntries { .. }
+                        | MutateMDataBatch { .. }
                         | SetMDataUserPermissions { .. }
                         | DelMDataUserPermissions { .. }
                         | ChangeMDataOwner { .. }
+                        | SubscribeMData { .. }
+                        | UnsubscribeMData { .. }
+                        | PutAData { .. }
+                        | AppendToAData { .. }
                         | InsAuthKey { .. }
-                        | DelAuthKey { .. } => (payload.len() as u64, false),
+                        | DelAuthKey { .. }
+                        | TransferCredit { .. }
+                        | IssueInvitation { .. }
+                        | ClaimInvitation { .. } => (payload.len() as u64, false),
                         Refresh(..) => return Err(RoutingError::InvalidMessage),
                     }
                 }
@@ -182,7 +220,8 @@ impl RateLimiter {
     ///
     /// When a request is made, clients are charged at the maximum size of the data being requested.
     /// This method compensates the client for the over-counting by crediting them the difference
-    /// between the maximum and the actual size of the response.
+    /// between the maximum and the actual size of the response. Paged listings are charged by
+    /// their page size instead, so a response larger than that charge is billed the surplus.
     pub fn apply_refund_for_response(
         &mut self,
         client_ip: &IpAddr,
@@ -207,6 +246,8 @@ impl RateLimiter {
                     match response {
                         // We overcharged for these, so we let them through.
                         GetIData { .. }
+                        | GetIDataRange { .. }
+                        | GetUnpubIData { .. }
                         | GetAccountInfo { .. }
                         | GetMData { .. }
                         | GetMDataVersion { .. }
@@ -215,19 +256,37 @@ impl RateLimiter {
                         | ListMDataKeys { .. }
                         | ListMDataValues { .. }
                         | GetMDataValue { .. }
+                        | GetMDataEntryHistory { .. }
+                        | GetMDataChangesSince { .. }
                         | ListMDataPermissions { .. }
                         | ListMDataUserPermissions { .. }
-                        | ListAuthKeysAndVersion { .. } => (),
+                        | GetAData { .. }
+                        | GetADataIndex { .. }
+                        | GetADataRange { .. }
+                        | ListAuthKeysAndVersion { .. }
+                        | GetBalance { .. }
+                        | GetBalanceHistory { .. } => (),
                         // These are responses to requests we didn't overcharge for. All these
                         // responses *should* fit in a single part.
                         PutIData { .. }
+                        | PutUnpubIData { .. }
+                        | DeleteUnpubIData { .. }
                         | PutMData { .. }
                         | MutateMDataEntries { .. }
+                        | MutateMDataBatch { .. }
                         | SetMDataUserPermissions { .. }
                         | DelMDataUserPermissions { .. }
                         | ChangeMDataOwner { .. }
+                        | SubscribeMData { .. }
+                        | UnsubscribeMData { .. }
+                        | MDataChanged { .. }
+                        | PutAData { .. }
+                        | AppendToAData { .. }
                         | InsAuthKey { .. }
-                        | DelAuthKey { .. } => return None,
+                        | DelAuthKey { .. }
+                        | TransferCredit { .. }
+                        | IssueInvitation { .. }
+                        | ClaimInvitation { .. } => return None,
                     }
                 }
                 _ => return None,
@@ -239,7 +298,13 @@ impl RateLimiter {
             None => return None,
         };
 
-        let deduction = amount_charged.saturating_sub(u64::from(part_count) * MAX_PART_LEN as u64);
+        let response_len = u64::from(part_count) * MAX_PART_LEN as u64;
+        if response_len > amount_charged {
+            let used = self.used.entry(*client_ip).or_insert(0);
+            *used = used.saturating_add(response_len - amount_charged);
+            return Some(0);
+        }
+        let deduction = amount_charged - response_len;
 
         self.used.get_mut(client_ip).map(|used| {
             *used = used.saturating_sub(deduction);
@@ -284,10 +349,25 @@ impl RateLimiter {
     }
 }
 
+/// Upfront charge for a paged listing: the whole `MutableData` unless the page is limited.
+fn page_charge(range: &EntriesRange) -> u64 {
+    range
+        .limit
+        .filter(|&limit| limit > 0)
+        .map_or(MAX_MUTABLE_DATA_SIZE_IN_BYTES, |limit| {
+            cmp::min(
+                limit
+                    .saturating_mul(MDATA_PAGE_ENTRY_CHARGE)
+                    .saturating_add(MDATA_PAGE_OVERHEAD),
+                MAX_MUTABLE_DATA_SIZE_IN_BYTES,
+            )
+        })
+}
+
 #[cfg(all(test, feature = "use-mock-crust"))]
 mod tests {
     use super::*;
-    use crate::data::ImmutableData;
+    use crate::data::{ImmutableData, Value};
     use crate::messages::{MessageContent, Request, Response};
     use crate::types::MessageId;
     use crate::xor_name::{XorName, XOR_NAME_LEN};
@@ -488,6 +568,83 @@ mod tests {
         assert_eq!(single_deduction, Some(expected_refund));
     }
 
+    #[test]
+    fn idata_range_charge() {
+        let mut rate_limiter = RateLimiter::new(false);
+        let client = IpAddr::from([0, 0, 0, 0]);
+
+        // A small range is only charged for its own length plus the proof overhead.
+        let request = UserMessage::Request(Request::GetIDataRange {
+            name: rand::random(),
+            offset: 0,
+            len: 4096,
+            msg_id: MessageId::new(),
+        });
+        let request_parts = unwrap!(request.to_parts(0));
+        let charge = add_user_msg_part(&mut rate_limiter, &client, &request_parts[0]);
+        assert_eq!(unwrap!(charge), 4096 + IDATA_RANGE_OVERHEAD);
+
+        // A huge range is charged no more than the whole chunk.
+        let request = UserMessage::Request(Request::GetIDataRange {
+            name: rand::random(),
+            offset: 0,
+            len: u64::max_value(),
+            msg_id: MessageId::new(),
+        });
+        let request_parts = unwrap!(request.to_parts(0));
+        let charge = add_user_msg_part(&mut rate_limiter, &client, &request_parts[0]);
+        assert_eq!(unwrap!(charge), MAX_IMMUTABLE_DATA_SIZE_IN_BYTES);
+    }
+
+    #[test]
+    fn mdata_page_charge() {
+        let mut rate_limiter = RateLimiter::new(false);
+        let client = IpAddr::from([0, 0, 0, 0]);
+
+        // An unlimited page, or one with a zero limit, is charged the whole `MutableData`.
+        for range in vec![EntriesRange::new(), EntriesRange::new().limit(0)] {
+            let request = UserMessage::Request(Request::ListMDataKeys {
+                name: rand::random(),
+                tag: 0,
+                range,
+                msg_id: MessageId::new(),
+            });
+            let request_parts = unwrap!(request.to_parts(0));
+            let charge = add_user_msg_part(&mut rate_limiter, &client, &request_parts[0]);
+            assert_eq!(unwrap!(charge), MAX_MUTABLE_DATA_SIZE_IN_BYTES);
+        }
+        rate_limiter.used.clear();
+
+        // A limited page is charged by its size.
+        let msg_id = MessageId::new();
+        let request = UserMessage::Request(Request::ListMDataValues {
+            name: rand::random(),
+            tag: 0,
+            range: EntriesRange::new().limit(1),
+            msg_id,
+        });
+        let request_parts = unwrap!(request.to_parts(0));
+        let charge = add_user_msg_part(&mut rate_limiter, &client, &request_parts[0]);
+        let charge = unwrap!(charge);
+        assert_eq!(charge, MDATA_PAGE_ENTRY_CHARGE + MDATA_PAGE_OVERHEAD);
+
+        // A response larger than the page charge is billed the surplus.
+        let value = Value {
+            content: vec![0; MAX_PART_LEN * 2],
+            entry_version: 0,
+        };
+        let response = UserMessage::Response(Response::ListMDataValues {
+            res: Ok((vec![value], None)),
+            msg_id,
+        });
+        let response_parts = unwrap!(response.to_parts(0));
+        for part in &response_parts {
+            let _ = refund_user_msg_part(&mut rate_limiter, &client, part);
+        }
+        let response_len = (response_parts.len() * MAX_PART_LEN) as u64;
+        assert_eq!(rate_limiter.used.get(&client), Some(&response_len));
+    }
+
     #[test]
     fn prevent_msg_id_reuse_attack() {
         let mut rate_limiter = RateLimiter::new(false);
@@ -0,0 +1,299 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use crate::crust;
+use crate::disk_cache;
+use crate::error::RoutingError;
+use crate::id::PublicId;
+#[cfg(feature = "use-mock-crust")]
+use crate::mock_crust::Endpoint;
+use crate::peer_manager::SectionMap;
+use crate::routing_table::{Prefix, RoutingTable};
+use crate::xor_name::XorName;
+use crate::{BootstrapConfig, PubConnectionInfo};
+use itertools::Itertools;
+use maidsafe_utilities::serialisation;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+#[cfg(not(feature = "use-mock-crust"))]
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// An address a peer accepts connections on, as listed in the bootstrap config.
+#[cfg(not(feature = "use-mock-crust"))]
+pub(crate) type Contact = SocketAddr;
+/// An address a peer accepts connections on, as listed in the bootstrap config.
+#[cfg(feature = "use-mock-crust")]
+pub(crate) type Contact = Endpoint;
+
+/// Returns the addresses in the connection info of a peer a restarted node can bootstrap against.
+#[cfg(not(feature = "use-mock-crust"))]
+pub(crate) fn direct_contacts(info: &PubConnectionInfo) -> Vec<Contact> {
+    info.for_direct.clone()
+}
+
+/// Returns the addresses in the connection info of a peer a restarted node can bootstrap against.
+#[cfg(feature = "use-mock-crust")]
+pub(crate) fn direct_contacts(info: &PubConnectionInfo) -> Vec<Contact> {
+    vec![info.endpoint]
+}
+
+/// The sections a node knew about, with their versions, the `PublicId`s of their members and the
+/// addresses those accept connections on, to be saved before the node stops and passed to
+/// `NodeBuilder::snapshot` when it restarts.
+///
+/// A restarted node bootstraps against the remembered peers before the configured contacts. It
+/// still has to join the network under a new name, and once it is approved it connects to the
+/// remembered peers before the others. The snapshot may be arbitrarily old, so none of its entries
+/// are trusted until they are confirmed by the sections received in the `NodeApproval`: peers
+/// which aren't listed there anymore, and sections whose remembered version is newer than the one
+/// the network reports, are ignored.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RoutingSnapshot {
+    /// Name of the node when the snapshot was taken
+    name: XorName,
+    /// Prefix of the node's section when the snapshot was taken
+    our_prefix: Prefix<XorName>,
+    /// Version and members of each known section, including ours
+    sections: BTreeMap<Prefix<XorName>, (u64, BTreeSet<PublicId>)>,
+    /// Addresses the members accept connections on, where known
+    addresses: BTreeMap<PublicId, Vec<Contact>>,
+}
+
+impl RoutingSnapshot {
+    /// Takes a snapshot of `table`. Members of the table whose `PublicId` isn't in `public_ids`
+    /// are left out. `addresses` holds the known addresses of the members.
+    pub(crate) fn new(
+        table: &RoutingTable<XorName>,
+        public_ids: &BTreeSet<PublicId>,
+        addresses: &BTreeMap<PublicId, Vec<Contact>>,
+    ) -> Self {
+        let public_ids: BTreeMap<_, _> = public_ids
+            .iter()
+            .map(|pub_id| (*pub_id.name(), *pub_id))
+            .collect();
+        let sections = table
+            .all_sections()
+            .into_iter()
+            .map(|(prefix, (version, names))| {
+                let members = names
+                    .iter()
+                    .filter_map(|name| public_ids.get(name))
+                    .cloned()
+                    .collect();
+                (prefix, (version, members))
+            })
+            .collect();
+
+        let addresses = public_ids
+            .values()
+            .filter_map(|pub_id| Some((*pub_id, addresses.get(pub_id)?.clone())))
+            .collect();
+
+        RoutingSnapshot {
+            name: *table.our_name(),
+            our_prefix: *table.our_prefix(),
+            sections,
+            addresses,
+        }
+    }
+
+    /// Reads a snapshot saved with `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RoutingSnapshot, RoutingError> {
+        let bytes = fs::read(path)?;
+        Ok(serialisation::deserialise(&bytes)?)
+    }
+
+    /// Writes the snapshot to `path`. It is written to a temporary file first, which is only
+    /// renamed once it has been synced, so a crash never leaves a partially written snapshot.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RoutingError> {
+        let bytes = serialisation::serialise(self)?;
+        Ok(disk_cache::write_atomically(path.as_ref(), &bytes)?)
+    }
+
+    /// Returns the name the node had when the snapshot was taken.
+    pub fn name(&self) -> &XorName {
+        &self.name
+    }
+
+    /// Returns the prefix of the node's section when the snapshot was taken.
+    pub fn our_prefix(&self) -> &Prefix<XorName> {
+        &self.our_prefix
+    }
+
+    /// Returns the version of the section with the given prefix when the snapshot was taken.
+    pub fn section_version(&self, prefix: &Prefix<XorName>) -> Option<u64> {
+        self.sections.get(prefix).map(|&(version, _)| version)
+    }
+
+    /// Returns the remembered peers: the members of our section first, then those of the other
+    /// sections.
+    pub fn contacts(&self) -> Vec<PublicId> {
+        self.sections_ours_first()
+            .flat_map(|(_, &(_, ref members))| members.iter().cloned())
+            .collect()
+    }
+
+    /// Returns `config` with the addresses of the remembered peers, in the order of `contacts`,
+    /// in front of its own contacts.
+    fn bootstrap_config(&self, mut config: BootstrapConfig) -> BootstrapConfig {
+        let configured = config.hard_coded_contacts;
+        config.hard_coded_contacts = self
+            .contacts()
+            .iter()
+            .filter_map(|pub_id| self.addresses.get(pub_id))
+            .flat_map(|addresses| addresses.iter().cloned())
+            .chain(configured)
+            .unique()
+            .collect();
+        config
+    }
+
+    /// Returns the bootstrap config read from the config file, with the remembered peers in front.
+    pub(crate) fn read_bootstrap_config(&self) -> BootstrapConfig {
+        let config = crust::read_config_file().unwrap_or_else(|error| {
+            debug!("Failed to read the bootstrap config: {:?}", error);
+            BootstrapConfig::default()
+        });
+        self.bootstrap_config(config)
+    }
+
+    /// Returns the remembered peers which are still members of one of the given sections, in the
+    /// order of `contacts`.
+    ///
+    /// A remembered peer is only trusted if a section whose prefix is compatible with the one it
+    /// was remembered in lists it. If the network reports an older version for a remembered
+    /// section than the snapshot does, the snapshot wasn't taken from this network's history and
+    /// none of that section's peers are trusted.
+    pub(crate) fn validate(&self, sections: &SectionMap) -> Vec<PublicId> {
+        let mut trusted = Vec::new();
+        let mut stale = 0;
+        for (prefix, &(version, ref members)) in self.sections_ours_first() {
+            let forged = sections
+                .keys()
+                .any(|ver_pfx| ver_pfx.prefix() == prefix && ver_pfx.version() < version);
+            if forged {
+                debug!(
+                    "Ignoring section {:?} of the routing snapshot: its version {} is newer than \
+                     the current one.",
+                    prefix, version
+                );
+                stale += members.len();
+                continue;
+            }
+            let confirmed: BTreeSet<_> = sections
+                .iter()
+                .filter(|&(ver_pfx, _)| ver_pfx.prefix().is_compatible(prefix))
+                .flat_map(|(_, members)| members)
+                .collect();
+            for pub_id in members {
+                if confirmed.contains(pub_id) {
+                    trusted.push(*pub_id);
+                } else {
+                    stale += 1;
+                }
+            }
+        }
+        if stale > 0 {
+            debug!("Ignoring {} stale peers of the routing snapshot.", stale);
+        }
+        trusted
+    }
+
+    fn sections_ours_first<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a Prefix<XorName>, &'a (u64, BTreeSet<PublicId>))> + 'a {
+        let (ours, others): (Vec<_>, Vec<_>) = self
+            .sections
+            .iter()
+            .partition(|&(prefix, _)| *prefix == self.our_prefix);
+        ours.into_iter().chain(others)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_cache::TMP_EXTENSION;
+    use crate::id::FullId;
+    use rand;
+    use std::env;
+    #[cfg(not(feature = "use-mock-crust"))]
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[cfg(not(feature = "use-mock-crust"))]
+    fn contact(i: usize) -> Contact {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 5000 + i as u16)
+    }
+
+    #[cfg(feature = "use-mock-crust")]
+    fn contact(i: usize) -> Contact {
+        Endpoint(i)
+    }
+
+    #[test]
+    fn save_and_validate() {
+        let ids: Vec<_> = (0..4).map(|_| *FullId::new().public_id()).collect();
+        let our_name = *ids[0].name();
+        let mut table = RoutingTable::new(our_name, 8);
+        for pub_id in &ids[1..] {
+            unwrap!(table.add(*pub_id.name()));
+        }
+
+        // Members without a known `PublicId` are left out.
+        let addresses = ids[1..]
+            .iter()
+            .enumerate()
+            .map(|(i, pub_id)| (*pub_id, vec![contact(i)]))
+            .collect();
+        let public_ids = ids[1..3].iter().cloned().collect();
+        let snapshot = RoutingSnapshot::new(&table, &public_ids, &addresses);
+        assert_eq!(snapshot.name(), &our_name);
+        assert_eq!(snapshot.contacts().len(), 2);
+        assert_eq!(snapshot.addresses.len(), 2);
+
+        let file_name = format!("routing_snapshot_{:016x}", rand::random::<u64>());
+        let path = env::temp_dir().join(file_name);
+        unwrap!(snapshot.save(&path));
+        assert_eq!(unwrap!(RoutingSnapshot::load(&path)), snapshot);
+        assert!(!path.with_extension(TMP_EXTENSION).exists());
+        unwrap!(fs::remove_file(&path));
+
+        // The remembered peers are tried before the configured contacts.
+        let config = snapshot.bootstrap_config(BootstrapConfig::with_contacts(&[contact(9)]));
+        let expected: Vec<_> = snapshot
+            .contacts()
+            .iter()
+            .map(|pub_id| unwrap!(snapshot.addresses.get(pub_id))[0])
+            .chain(Some(contact(9)))
+            .collect();
+        assert_eq!(config.hard_coded_contacts, expected);
+
+        // Only the peers still listed by the network are trusted.
+        let mut sections = SectionMap::new();
+        let _ = sections.insert(
+            Prefix::default().with_version(1),
+            vec![ids[0], ids[2], ids[3]].into_iter().collect(),
+        );
+        assert_eq!(snapshot.validate(&sections), vec![ids[2]]);
+
+        // Peers are trusted if a section compatible with the remembered one lists them.
+        let mut split = SectionMap::new();
+        for bit in vec![false, true] {
+            let prefix = Prefix::default().pushed(bit);
+            let members = ids.iter().filter(|id| prefix.matches(id.name())).cloned();
+            let _ = split.insert(prefix.with_version(1), members.collect());
+        }
+        assert_eq!(snapshot.validate(&split), snapshot.contacts());
+
+        // A remembered section newer than the one the network reports isn't trusted at all.
+        let mut forged = snapshot.clone();
+        unwrap!(forged.sections.get_mut(&Prefix::default())).0 = 2;
+        assert!(forged.validate(&sections).is_empty());
+    }
+}
@@ -11,6 +11,7 @@ use crate::id::{FullId, PublicId};
 #[cfg(feature = "use-mock-crust")]
 use crate::mock_crust;
 use crate::outbox::EventBox;
+use crate::routing_snapshot::RoutingSnapshot;
 use crate::routing_table::{Prefix, RoutingTable};
 #[cfg(feature = "use-mock-crust")]
 use crate::rust_sodium::crypto::sign;
@@ -79,7 +80,7 @@ impl State {
     pub fn handle_action(&mut self, action: Action, outbox: &mut EventBox) -> Transition {
         match *self {
             State::Bootstrapping(ref mut state) => state.handle_action(action),
-            State::Client(ref mut state) => state.handle_action(action),
+            State::Client(ref mut state) => state.handle_action(action, outbox),
             State::JoiningNode(ref mut state) => state.handle_action(action, outbox),
             State::Node(ref mut state) => state.handle_action(action, outbox),
             State::Terminated => Transition::Terminate,
@@ -111,6 +112,13 @@ impl State {
         }
     }
 
+    fn routing_snapshot(&self) -> Option<RoutingSnapshot> {
+        match *self {
+            State::Node(ref state) => Some(state.routing_snapshot()),
+            _ => None,
+        }
+    }
+
     fn close_group(&self, name: XorName, count: usize) -> Option<Vec<XorName>> {
         self.base_state()
             .and_then(|state| state.close_group(name, count))
@@ -508,6 +516,10 @@ impl StateMachine {
         self.state.routing_table()
     }
 
+    pub fn routing_snapshot(&self) -> Option<RoutingSnapshot> {
+        self.state.routing_snapshot()
+    }
+
     pub fn close_group(&self, name: XorName, count: usize) -> Option<Vec<XorName>> {
         self.state.close_group(name, count)
     }
@@ -16,6 +16,7 @@ use crate::event::Event;
 use crate::id::{FullId, PublicId};
 use crate::messages::{DirectMessage, Message};
 use crate::outbox::EventBox;
+use crate::routing_snapshot::RoutingSnapshot;
 use crate::routing_table::{Authority, Prefix};
 use crate::rust_sodium::crypto::sign;
 use crate::state_machine::{State, Transition};
@@ -40,10 +41,13 @@ pub enum TargetState {
     Client {
         msg_expiry_dur: Duration,
     },
-    JoiningNode,
+    JoiningNode {
+        snapshot: Option<RoutingSnapshot>,
+    },
     Node {
         old_full_id: FullId,
         our_section: (Prefix<XorName>, BTreeSet<PublicId>),
+        snapshot: Option<RoutingSnapshot>,
     },
 }
 
@@ -76,7 +80,7 @@ impl Bootstrapping {
             TargetState::Client { .. } => {
                 let _ = crust_service.start_bootstrap(HashSet::new(), CrustUser::Client);
             }
-            TargetState::JoiningNode | TargetState::Node { .. } => {
+            TargetState::JoiningNode { .. } | TargetState::Node { .. } => {
                 if let Err(error) = crust_service.start_listening_tcp() {
                     error!("Failed to start listening: {:?}", error);
                     return None;
@@ -175,6 +179,7 @@ impl Bootstrapping {
     pub fn into_target_state(self, proxy_public_id: PublicId, outbox: &mut EventBox) -> State {
         match self.target_state {
             TargetState::Client { msg_expiry_dur } => State::Client(Client::from_bootstrapping(
+                self.cache,
                 self.crust_service,
                 self.full_id,
                 self.min_section_size,
@@ -184,7 +189,7 @@ impl Bootstrapping {
                 msg_expiry_dur,
                 outbox,
             )),
-            TargetState::JoiningNode => {
+            TargetState::JoiningNode { snapshot } => {
                 if let Some(joining_node) = JoiningNode::from_bootstrapping(
                     self.action_sender,
                     self.cache,
@@ -192,6 +197,7 @@ impl Bootstrapping {
                     self.full_id,
                     self.min_section_size,
                     proxy_public_id,
+                    snapshot,
                     self.stats,
                     self.timer,
                 ) {
@@ -204,7 +210,7 @@ impl Bootstrapping {
             TargetState::Node {
                 old_full_id,
                 our_section,
-                ..
+                snapshot,
             } => State::Node(Node::from_bootstrapping(
                 our_section,
                 self.action_sender,
@@ -214,6 +220,7 @@ impl Bootstrapping {
                 self.full_id,
                 self.min_section_size,
                 proxy_public_id,
+                snapshot,
                 self.stats,
                 self.timer,
             )),
@@ -223,7 +230,7 @@ impl Bootstrapping {
     fn client_restriction(&self) -> bool {
         match self.target_state {
             TargetState::Client { .. } => true,
-            TargetState::JoiningNode | TargetState::Node { .. } => false,
+            TargetState::JoiningNode { .. } | TargetState::Node { .. } => false,
         }
     }
 
@@ -9,12 +9,13 @@
 use super::common::{Base, Bootstrapped, USER_MSG_CACHE_EXPIRY_DURATION_SECS};
 use crate::ack_manager::{Ack, AckManager, UnacknowledgedMessage};
 use crate::action::Action;
+use crate::cache::Cache;
 use crate::error::{InterfaceError, RoutingError};
 use crate::event::Event;
 use crate::id::{FullId, PublicId};
 use crate::messages::{
-    DirectMessage, HopMessage, Message, MessageContent, RoutingMessage, SignedMessage, UserMessage,
-    UserMessageCache,
+    DirectMessage, HopMessage, Message, MessageContent, Request, Response, RoutingMessage,
+    SignedMessage, UserMessage, UserMessageCache,
 };
 use crate::outbox::EventBox;
 use crate::routing_message_filter::{FilteringResult, RoutingMessageFilter};
@@ -22,10 +23,12 @@ use crate::routing_table::Authority;
 use crate::state_machine::Transition;
 use crate::stats::Stats;
 use crate::timer::Timer;
+use crate::types::MessageId;
 use crate::xor_name::XorName;
 use crate::{CrustEvent, Service};
 #[cfg(feature = "use-mock-crust")]
 use fake_clock::FakeClock as Instant;
+use lru_time_cache::LruCache;
 use maidsafe_utilities::serialisation;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Debug, Formatter};
@@ -41,10 +44,15 @@ pub const RATE_EXCEED_RETRY_MS: u64 = 800;
 /// Each client has a _proxy_: a node through which all requests are routed.
 pub struct Client {
     ack_mgr: AckManager,
+    /// Cacheable requests not answered from the cache, with their destination, so the responses
+    /// to them can be cached once they arrive.
+    cacheable_requests: LruCache<MessageId, (Request, Authority<XorName>)>,
+    mdata_mutations: LruCache<MessageId, Vec<(XorName, u64)>>,
     crust_service: Service,
     full_id: FullId,
     min_section_size: usize,
     proxy_pub_id: PublicId,
+    response_cache: Box<Cache>,
     routing_msg_filter: RoutingMessageFilter,
     stats: Stats,
     timer: Timer,
@@ -56,6 +64,7 @@ pub struct Client {
 impl Client {
     #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
     pub fn from_bootstrapping(
+        cache: Box<Cache>,
         crust_service: Service,
         full_id: FullId,
         min_section_size: usize,
@@ -67,10 +76,13 @@ impl Client {
     ) -> Self {
         let client = Client {
             ack_mgr: AckManager::new(),
+            cacheable_requests: LruCache::with_expiry_duration(msg_expiry_dur),
+            mdata_mutations: LruCache::with_expiry_duration(msg_expiry_dur),
             crust_service,
             full_id,
             min_section_size,
             proxy_pub_id,
+            response_cache: cache,
             routing_msg_filter: RoutingMessageFilter::new(),
             stats,
             timer,
@@ -87,7 +99,7 @@ impl Client {
         client
     }
 
-    pub fn handle_action(&mut self, action: Action) -> Transition {
+    pub fn handle_action(&mut self, action: Action, outbox: &mut EventBox) -> Transition {
         match action {
             Action::ClientSendRequest {
                 content,
@@ -100,10 +112,20 @@ impl Client {
                     proxy_node_name: *self.proxy_pub_id.name(),
                 };
 
-                let user_msg = UserMessage::Request(content);
-                let result = match self.send_user_message(src, dst, user_msg, priority) {
-                    Err(RoutingError::Interface(err)) => Err(err),
-                    Err(_) | Ok(_) => Ok(()),
+                let result = if let Some(response) = self.respond_from_cache(&content, dst) {
+                    debug!("{:?} Found cached response to {:?}", self, content);
+                    outbox.send_event(Event::Response {
+                        response,
+                        src: dst,
+                        dst: src,
+                    });
+                    Ok(())
+                } else {
+                    let user_msg = UserMessage::Request(content);
+                    match self.send_user_message(src, dst, user_msg, priority) {
+                        Err(RoutingError::Interface(err)) => Err(err),
+                        Err(_) | Ok(_) => Ok(()),
+                    }
                 };
 
                 let _ = result_tx.send(result);
@@ -293,6 +315,9 @@ impl Client {
                     .add(hash, part_count, part_index, payload)
                 {
                     self.stats().count_user_message(&msg);
+                    if let UserMessage::Response(ref response) = msg {
+                        self.put_in_cache(response, &routing_msg.src);
+                    }
                     outbox.send_event(msg.into_event(routing_msg.src, routing_msg.dst));
                 }
                 Transition::Stay
@@ -307,6 +332,100 @@ impl Client {
         }
     }
 
+    /// Returns the cached response to `request`, if any. Otherwise remembers the request, so the
+    /// response from `dst` can be cached, or make cached responses stale, once it arrives.
+    fn respond_from_cache(
+        &mut self,
+        request: &Request,
+        dst: Authority<XorName>,
+    ) -> Option<Response> {
+        let mutated = request.mutated_mdata();
+        if !mutated.is_empty() {
+            let _ = self.mdata_mutations.insert(*request.message_id(), mutated);
+        }
+        if !request.is_cacheable() {
+            return None;
+        }
+
+        if let Some(response) = self.response_cache.get(request) {
+            self.stats.count_cache_hit();
+            return Some(response);
+        }
+        self.stats.count_cache_miss();
+        let _ = self
+            .cacheable_requests
+            .insert(*request.message_id(), (request.clone(), dst));
+        None
+    }
+
+    /// Caches the response if it answers a cacheable request we sent, or drops the cached
+    /// responses it makes stale if it is a change notification or reports a successful mutation.
+    fn put_in_cache(&mut self, response: &Response, src: &Authority<XorName>) {
+        if let Response::MDataChanged {
+            ref notification, ..
+        } = *response
+        {
+            self.response_cache.invalidate_mdata(
+                &notification.name,
+                notification.tag,
+                Some(notification.stale_below()),
+            );
+            return;
+        }
+        if let Some(mutated) = self.mdata_mutations.remove(response.message_id()) {
+            if response.is_mdata_mutation_success() {
+                for (name, tag) in mutated {
+                    self.response_cache.invalidate_mdata(&name, tag, None);
+                }
+            }
+            return;
+        }
+        if !response.is_cacheable() {
+            return;
+        }
+
+        match self.cacheable_requests.remove(response.message_id()) {
+            Some((ref request, ref dst)) if self.is_trusted_source(request, response, dst, src) => {
+                self.response_cache
+                    .put_response_to(request, response.clone())
+            }
+            Some(_) => debug!(
+                "{:?} Not caching {:?} from {:?}, which isn't trusted to answer it",
+                self, response, src
+            ),
+            None => (),
+        }
+    }
+
+    /// Returns whether a response from `src` to `request`, which was sent to `dst`, can be cached.
+    ///
+    /// MutableData can only be vouched for by the section authority the request was sent to. Our
+    /// proxy may answer from its own cache too, but only with ImmutableData whose name matches the
+    /// request, as that is verified by recomputing it from the value.
+    fn is_trusted_source(
+        &self,
+        request: &Request,
+        response: &Response,
+        dst: &Authority<XorName>,
+        src: &Authority<XorName>,
+    ) -> bool {
+        if dst == src && src.is_multiple() {
+            return true;
+        }
+        if *src != Authority::ManagedNode(*self.proxy_pub_id.name()) {
+            return false;
+        }
+        match (request, response) {
+            (
+                &Request::GetIData { ref name, .. },
+                &Response::GetIData {
+                    res: Ok(ref data), ..
+                },
+            ) => data.name() == name,
+            _ => false,
+        }
+    }
+
     /// Sends the given message, possibly splitting it up into smaller parts.
     fn send_user_message(
         &mut self,
@@ -18,6 +18,7 @@ use crate::messages::{HopMessage, Message, MessageContent, RoutingMessage, Signe
 use crate::outbox::EventBox;
 use crate::resource_prover::RESOURCE_PROOF_DURATION_SECS;
 use crate::routing_message_filter::{FilteringResult, RoutingMessageFilter};
+use crate::routing_snapshot::RoutingSnapshot;
 use crate::routing_table::{Authority, Prefix};
 use crate::state_machine::{State, Transition};
 use crate::stats::Stats;
@@ -48,6 +49,8 @@ pub struct JoiningNode {
     cache: Box<Cache>,
     min_section_size: usize,
     proxy_pub_id: PublicId,
+    /// Only held here to be passed eventually to the `Node` state.
+    snapshot: Option<RoutingSnapshot>,
     /// The queue of routing messages addressed to us. These do not themselves need forwarding,
     /// although they may wrap a message which needs forwarding.
     routing_msg_filter: RoutingMessageFilter,
@@ -65,6 +68,7 @@ impl JoiningNode {
         full_id: FullId,
         min_section_size: usize,
         proxy_pub_id: PublicId,
+        snapshot: Option<RoutingSnapshot>,
         stats: Stats,
         timer: Timer,
     ) -> Option<Self> {
@@ -78,6 +82,7 @@ impl JoiningNode {
             cache,
             min_section_size,
             proxy_pub_id,
+            snapshot,
             routing_msg_filter: RoutingMessageFilter::new(),
             stats,
             relocation_timer_token,
@@ -149,6 +154,7 @@ impl JoiningNode {
         let target_state = BootstrappingTargetState::Node {
             old_full_id: self.full_id,
             our_section,
+            snapshot: self.snapshot,
         };
         if let Some(bootstrapping) = Bootstrapping::new(
             self.action_sender,
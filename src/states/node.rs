@@ -10,6 +10,7 @@ use super::common::{Base, Bootstrapped, USER_MSG_CACHE_EXPIRY_DURATION_SECS};
 use crate::ack_manager::{Ack, AckManager};
 use crate::action::Action;
 use crate::cache::Cache;
+use crate::client_error::ClientError;
 use crate::config_handler;
 use crate::crust::{ConnectionInfoResult, CrustError, CrustUser};
 use crate::cumulative_own_section_merge::CumulativeOwnSectionMerge;
@@ -17,8 +18,9 @@ use crate::error::{BootstrapResponseError, InterfaceError, RoutingError};
 use crate::event::Event;
 use crate::id::{FullId, PublicId};
 use crate::messages::{
-    DirectMessage, HopMessage, Message, MessageContent, RoutingMessage, SectionList, SignedMessage,
-    UserMessage, UserMessageCache, DEFAULT_PRIORITY, MAX_PARTS, MAX_PART_LEN,
+    DirectMessage, HopMessage, Message, MessageContent, Request, Response, RoutingMessage,
+    SectionList, SignedMessage, UserMessage, UserMessageCache, DEFAULT_PRIORITY, MAX_PARTS,
+    MAX_PART_LEN,
 };
 use crate::outbox::{EventBox, EventBuf};
 use crate::peer_manager::{
@@ -28,6 +30,7 @@ use crate::peer_manager::{
 use crate::rate_limiter::RateLimiter;
 use crate::resource_prover::{ResourceProver, RESOURCE_PROOF_DURATION_SECS};
 use crate::routing_message_filter::{FilteringResult, RoutingMessageFilter};
+use crate::routing_snapshot::{self, Contact, RoutingSnapshot};
 use crate::routing_table::Error as RoutingTableError;
 use crate::routing_table::{
     Authority, OwnMergeState, Prefix, RemovalDetails, RoutingTable, VersionedPrefix, Xorable,
@@ -86,6 +89,12 @@ const DROPPED_CLIENT_TIMEOUT_SECS: u64 = 2 * 60 * 60;
 pub struct Node {
     ack_mgr: AckManager,
     cacheable_user_msg_cache: UserMessageCache,
+    /// Cacheable requests relayed without a cached response, with their destination, so the
+    /// responses to them can be cached on their way back.
+    cacheable_requests: LruCache<MessageId, (Request, Authority<XorName>)>,
+    /// The MutableData mutated by the requests relayed, whose cached responses are dropped once
+    /// the mutation is reported successful.
+    mdata_mutations: LruCache<MessageId, Vec<(XorName, u64)>>,
     crust_service: Service,
     /// ID from before relocating.
     old_full_id: FullId,
@@ -140,6 +149,10 @@ pub struct Node {
     proxy_load_amount: u64,
     /// Whether resource proof is disabled.
     disable_resource_proof: bool,
+    /// The routing table we had before restarting, if any. Consumed on `NodeApproval`.
+    snapshot: Option<RoutingSnapshot>,
+    /// Addresses our peers accept connections on, as sent in their connection info.
+    peer_addresses: BTreeMap<PublicId, Vec<Contact>>,
 }
 
 impl Node {
@@ -185,6 +198,7 @@ impl Node {
         new_full_id: FullId,
         min_section_size: usize,
         proxy_pub_id: PublicId,
+        snapshot: Option<RoutingSnapshot>,
         stats: Stats,
         timer: Timer,
     ) -> Self {
@@ -201,6 +215,7 @@ impl Node {
             our_section.1.len(),
         );
         node.joining_prefix = our_section.0;
+        node.snapshot = snapshot;
         node.peer_mgr.insert_peer(Peer::new(
             proxy_pub_id,
             PeerState::Proxy,
@@ -235,6 +250,8 @@ impl Node {
             cacheable_user_msg_cache: UserMessageCache::with_expiry_duration(
                 user_msg_cache_duration,
             ),
+            cacheable_requests: LruCache::with_expiry_duration(user_msg_cache_duration),
+            mdata_mutations: LruCache::with_expiry_duration(user_msg_cache_duration),
             crust_service,
             old_full_id,
             full_id: new_full_id,
@@ -273,6 +290,8 @@ impl Node {
             )),
             proxy_load_amount: 0,
             disable_resource_proof: dev_config.disable_resource_proof,
+            snapshot: None,
+            peer_addresses: BTreeMap::new(),
         }
     }
 
@@ -487,6 +506,24 @@ impl Node {
         self.peer_mgr.routing_table()
     }
 
+    /// Snapshot of the routing table of this node, with the `PublicId`s of its members.
+    pub fn routing_snapshot(&self) -> RoutingSnapshot {
+        let names = self.routing_table().iter().cloned().collect();
+        RoutingSnapshot::new(
+            self.routing_table(),
+            &self.peer_mgr.get_pub_ids(&names),
+            &self.peer_addresses,
+        )
+    }
+
+    /// Remembers the addresses in the connection info of a peer, to be included in the routing
+    /// snapshot, and forgets those of peers we don't know anymore.
+    fn record_peer_addresses(&mut self, info: &PubConnectionInfo) {
+        let peer_mgr = &self.peer_mgr;
+        self.peer_addresses.retain(|pub_id, _| peer_mgr.get_peer(pub_id).is_some());
+        let _ = self.peer_addresses.insert(info.id(), routing_snapshot::direct_contacts(info));
+    }
+
     fn handle_routing_messages(&mut self, outbox: &mut EventBox) {
         while let Some(routing_msg) = self.msg_queue.pop_front() {
             if self.in_authority(&routing_msg.dst) {
@@ -1261,7 +1298,9 @@ impl Node {
                     .add(hash, part_count, part_index, payload)
                 {
                     self.stats().count_user_message(&msg);
-                    outbox.send_event(msg.into_event(src, dst));
+                    if !self.reject_cross_section_batch(&msg, src, dst)? {
+                        outbox.send_event(msg.into_event(src, dst));
+                    }
                 }
                 Ok(())
             }
@@ -1275,6 +1314,43 @@ impl Node {
         }
     }
 
+    // A section can only apply a `MutateMDataBatch` atomically if it manages all the targets, so
+    // a batch which also targets data managed elsewhere is refused instead of raised as an event.
+    // Returns true if the batch was refused.
+    fn reject_cross_section_batch(
+        &mut self,
+        msg: &UserMessage,
+        src: Authority<XorName>,
+        dst: Authority<XorName>,
+    ) -> Result<bool, RoutingError> {
+        let (mutations, msg_id) = match *msg {
+            UserMessage::Request(Request::MutateMDataBatch {
+                ref mutations,
+                msg_id,
+                ..
+            }) => (mutations, msg_id),
+            _ => return Ok(false),
+        };
+        if mutations
+            .keys()
+            .all(|&(name, _)| self.our_prefix().matches(&name))
+        {
+            return Ok(false);
+        }
+
+        debug!(
+            "{:?} Refusing MutateMDataBatch {:?} with targets outside of our section.",
+            self, msg_id
+        );
+        let response = Response::MutateMDataBatch {
+            res: Err(ClientError::InvalidOperation),
+            msg_id,
+        };
+        let priority = response.priority();
+        self.send_user_message(dst, src, UserMessage::Response(response), priority)?;
+        Ok(true)
+    }
+
     fn handle_candidate_approval(
         &mut self,
         new_pub_id: PublicId,
@@ -1400,27 +1476,36 @@ impl Node {
         let our_prefix = *self.our_prefix();
         self.send_section_list_signature(our_prefix, None);
 
-        for section in sections.values() {
-            for pub_id in section.iter() {
-                if !self.routing_table().has(pub_id.name()) {
+        // Peers remembered from before a restart are contacted first, but only those the approving
+        // section still lists.
+        let remembered = self
+            .snapshot
+            .take()
+            .map_or_else(Vec::new, |snapshot| snapshot.validate(sections));
+        let pub_ids: Vec<_> = remembered
+            .into_iter()
+            .chain(sections.values().flat_map(|section| section.iter().cloned()))
+            .unique()
+            .collect();
+        for pub_id in pub_ids {
+            if !self.routing_table().has(pub_id.name()) {
+                debug!(
+                    "{:?} Sending connection info to {:?} on NodeApproval.",
+                    self, pub_id
+                );
+                let src = Authority::ManagedNode(*self.name());
+                let node_auth = Authority::ManagedNode(*pub_id.name());
+                if let Err(error) = self.send_connection_info_request(
+                    pub_id,
+                    src,
+                    node_auth,
+                    outbox,
+                    ReconnectingPeer::False,
+                ) {
                     debug!(
-                        "{:?} Sending connection info to {:?} on NodeApproval.",
-                        self, pub_id
+                        "{:?} - Failed to send connection info to {:?}: {:?}",
+                        self, pub_id, error
                     );
-                    let src = Authority::ManagedNode(*self.name());
-                    let node_auth = Authority::ManagedNode(*pub_id.name());
-                    if let Err(error) = self.send_connection_info_request(
-                        *pub_id,
-                        src,
-                        node_auth,
-                        outbox,
-                        ReconnectingPeer::False,
-                    ) {
-                        debug!(
-                            "{:?} - Failed to send connection info to {:?}: {:?}",
-                            self, pub_id, error
-                        );
-                    }
                 }
             }
         }
@@ -1604,6 +1689,18 @@ impl Node {
                 .add(hash, part_count, part_index, payload.clone())
             {
                 Some(UserMessage::Request(request)) => {
+                    // The mutation may still fail, so only remember what it would make stale.
+                    let mutated = request.mutated_mdata();
+                    if !mutated.is_empty() {
+                        let _ = self.mdata_mutations.insert(*request.message_id(), mutated);
+                    }
+
+                    // The `cacheable` flag is set by the sender, so double-check it against the
+                    // message itself before touching the cache.
+                    if !request.is_cacheable() {
+                        return Ok(false);
+                    }
+
                     if let Some(response) = self.response_cache.get(&request) {
                         debug!("{:?} Found cached response to {:?}", self, request);
 
@@ -1617,11 +1714,48 @@ impl Node {
 
                         return Ok(true);
                     }
+
+                    let _ = self
+                        .cacheable_requests
+                        .insert(*request.message_id(), (request, routing_msg.dst));
                 }
 
+                Some(UserMessage::Response(Response::MDataChanged {
+                    ref notification, ..
+                })) => {
+                    self.response_cache.invalidate_mdata(
+                        &notification.name,
+                        notification.tag,
+                        Some(notification.stale_below()),
+                    );
+                }
+                Some(UserMessage::Response(ref response))
+                    if response.is_mdata_mutation_success() =>
+                {
+                    let mutated = self.mdata_mutations.remove(response.message_id());
+                    for (name, tag) in mutated.into_iter().flatten() {
+                        self.response_cache.invalidate_mdata(&name, tag, None);
+                    }
+                }
+                Some(UserMessage::Response(ref response)) if !response.is_cacheable() => (),
                 Some(UserMessage::Response(response)) => {
-                    debug!("{:?} Putting {:?} in cache", self, response);
-                    self.response_cache.put(response);
+                    match self.cacheable_requests.remove(response.message_id()) {
+                        // Only the authority the request was sent to can answer it.
+                        Some((_, ref request_dst)) if *request_dst != routing_msg.src => {
+                            debug!(
+                                "{:?} Not caching {:?} from {:?}, expected from {:?}",
+                                self, response, routing_msg.src, request_dst
+                            );
+                        }
+                        Some((request, _)) => {
+                            debug!("{:?} Putting {:?} in cache", self, response);
+                            self.response_cache.put_response_to(&request, response);
+                        }
+                        None => {
+                            debug!("{:?} Putting {:?} in cache", self, response);
+                            self.response_cache.put(response);
+                        }
+                    }
                 }
 
                 None => (),
@@ -2134,6 +2268,7 @@ impl Node {
             );
             return Err(RoutingError::InvalidPeer);
         }
+        self.record_peer_addresses(&their_connection_info);
 
         use crate::peer_manager::ConnectionInfoReceivedResult::*;
         match self.peer_mgr.connection_info_received(
@@ -2211,6 +2346,7 @@ impl Node {
             );
             return Err(RoutingError::InvalidPeer);
         }
+        self.record_peer_addresses(&their_connection_info);
 
         use crate::peer_manager::ConnectionInfoReceivedResult::*;
         match self.peer_mgr.connection_info_received(
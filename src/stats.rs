@@ -60,6 +60,10 @@ pub struct Stats {
     pub msg_user_parts: u64,
     msg_put_idata: UserMessageStats,
     msg_get_idata: UserMessageStats,
+    msg_get_idata_range: UserMessageStats,
+    msg_put_unpub_idata: UserMessageStats,
+    msg_get_unpub_idata: UserMessageStats,
+    msg_delete_unpub_idata: UserMessageStats,
     msg_get_mdata: UserMessageStats,
     msg_put_mdata: UserMessageStats,
     msg_get_mdata_version: UserMessageStats,
@@ -68,19 +72,39 @@ pub struct Stats {
     msg_list_mdata_keys: UserMessageStats,
     msg_list_mdata_values: UserMessageStats,
     msg_get_mdata_value: UserMessageStats,
+    msg_get_mdata_entry_history: UserMessageStats,
+    msg_get_mdata_changes_since: UserMessageStats,
     msg_mutate_mdata_entries: UserMessageStats,
+    msg_mutate_mdata_batch: UserMessageStats,
     msg_list_mdata_permissions: UserMessageStats,
     msg_list_mdata_user_permissions: UserMessageStats,
     msg_set_mdata_user_permissions: UserMessageStats,
     msg_del_mdata_user_permissions: UserMessageStats,
     msg_change_mdata_owner: UserMessageStats,
+    msg_subscribe_mdata: UserMessageStats,
+    msg_unsubscribe_mdata: UserMessageStats,
+    msg_mdata_changed: usize,
+    msg_put_adata: UserMessageStats,
+    msg_get_adata: UserMessageStats,
+    msg_get_adata_index: UserMessageStats,
+    msg_get_adata_range: UserMessageStats,
+    msg_append_to_adata: UserMessageStats,
     msg_list_auth_keys_and_version: UserMessageStats,
     msg_ins_auth_key: UserMessageStats,
     msg_del_auth_key: UserMessageStats,
+    msg_transfer_credit: UserMessageStats,
+    msg_get_balance: UserMessageStats,
+    msg_get_balance_history: UserMessageStats,
+    msg_issue_invitation: UserMessageStats,
+    msg_claim_invitation: UserMessageStats,
     msg_get_account_info: UserMessageStats,
 
     msg_other: usize,
 
+    /// Requests answered from the response cache, and cacheable requests which weren't.
+    cache_hits: usize,
+    cache_misses: usize,
+
     msg_total: usize,
     msg_total_bytes: u64,
 
@@ -105,6 +129,14 @@ impl Stats {
         self.routes[route] += 1;
     }
 
+    pub fn count_cache_hit(&mut self) {
+        self.cache_hits += 1;
+    }
+
+    pub fn count_cache_miss(&mut self) {
+        self.cache_misses += 1;
+    }
+
     pub fn increase_user_msg_part(&mut self) {
         self.msg_user_parts = self.msg_user_parts.wrapping_add(1);
     }
@@ -115,6 +147,10 @@ impl Stats {
             UserMessage::Request(ref request) => match *request {
                 Request::PutIData { .. } => self.msg_put_idata.inc_request(),
                 Request::GetIData { .. } => self.msg_get_idata.inc_request(),
+                Request::GetIDataRange { .. } => self.msg_get_idata_range.inc_request(),
+                Request::PutUnpubIData { .. } => self.msg_put_unpub_idata.inc_request(),
+                Request::GetUnpubIData { .. } => self.msg_get_unpub_idata.inc_request(),
+                Request::DeleteUnpubIData { .. } => self.msg_delete_unpub_idata.inc_request(),
                 Request::GetMData { .. } => self.msg_get_mdata.inc_request(),
                 Request::PutMData { .. } => self.msg_put_mdata.inc_request(),
                 Request::GetMDataVersion { .. } => self.msg_get_mdata_version.inc_request(),
@@ -123,7 +159,14 @@ impl Stats {
                 Request::ListMDataValues { .. } => self.msg_list_mdata_values.inc_request(),
                 Request::ListMDataEntries { .. } => self.msg_list_mdata_entries.inc_request(),
                 Request::GetMDataValue { .. } => self.msg_get_mdata_value.inc_request(),
+                Request::GetMDataEntryHistory { .. } => {
+                    self.msg_get_mdata_entry_history.inc_request()
+                }
+                Request::GetMDataChangesSince { .. } => {
+                    self.msg_get_mdata_changes_since.inc_request()
+                }
                 Request::MutateMDataEntries { .. } => self.msg_mutate_mdata_entries.inc_request(),
+                Request::MutateMDataBatch { .. } => self.msg_mutate_mdata_batch.inc_request(),
                 Request::ListMDataPermissions { .. } => {
                     self.msg_list_mdata_permissions.inc_request()
                 }
@@ -137,17 +180,41 @@ impl Stats {
                     self.msg_del_mdata_user_permissions.inc_request()
                 }
                 Request::ChangeMDataOwner { .. } => self.msg_change_mdata_owner.inc_request(),
+                Request::SubscribeMData { .. } => self.msg_subscribe_mdata.inc_request(),
+                Request::UnsubscribeMData { .. } => self.msg_unsubscribe_mdata.inc_request(),
+                Request::PutAData { .. } => self.msg_put_adata.inc_request(),
+                Request::GetAData { .. } => self.msg_get_adata.inc_request(),
+                Request::GetADataIndex { .. } => self.msg_get_adata_index.inc_request(),
+                Request::GetADataRange { .. } => self.msg_get_adata_range.inc_request(),
+                Request::AppendToAData { .. } => self.msg_append_to_adata.inc_request(),
                 Request::ListAuthKeysAndVersion { .. } => {
                     self.msg_list_auth_keys_and_version.inc_request()
                 }
                 Request::InsAuthKey { .. } => self.msg_ins_auth_key.inc_request(),
                 Request::DelAuthKey { .. } => self.msg_del_auth_key.inc_request(),
+                Request::TransferCredit { .. } => self.msg_transfer_credit.inc_request(),
+                Request::GetBalance(..) => self.msg_get_balance.inc_request(),
+                Request::GetBalanceHistory { .. } => self.msg_get_balance_history.inc_request(),
+                Request::IssueInvitation { .. } => self.msg_issue_invitation.inc_request(),
+                Request::ClaimInvitation { .. } => self.msg_claim_invitation.inc_request(),
                 Request::GetAccountInfo { .. } => self.msg_get_account_info.inc_request(),
                 Request::Refresh(..) => self.msg_refresh += 1,
             },
             UserMessage::Response(ref response) => match *response {
                 Response::PutIData { ref res, .. } => self.msg_put_idata.inc_response(res.is_ok()),
                 Response::GetIData { ref res, .. } => self.msg_get_idata.inc_response(res.is_ok()),
+                Response::GetIDataRange { ref res, .. } => {
+                    self.msg_get_idata_range.inc_response(res.is_ok())
+                }
+                Response::PutUnpubIData { ref res, .. } => {
+                    self.msg_put_unpub_idata.inc_response(res.is_ok())
+                }
+                Response::GetUnpubIData { ref res, .. } => {
+                    self.msg_get_unpub_idata.inc_response(res.is_ok())
+                }
+                Response::DeleteUnpubIData { ref res, .. } => {
+                    self.msg_delete_unpub_idata.inc_response(res.is_ok())
+                }
                 Response::PutMData { ref res, .. } => self.msg_put_mdata.inc_response(res.is_ok()),
                 Response::GetMData { ref res, .. } => self.msg_get_mdata.inc_response(res.is_ok()),
                 Response::GetMDataVersion { ref res, .. } => {
@@ -168,9 +235,18 @@ impl Stats {
                 Response::GetMDataValue { ref res, .. } => {
                     self.msg_get_mdata_value.inc_response(res.is_ok())
                 }
+                Response::GetMDataEntryHistory { ref res, .. } => {
+                    self.msg_get_mdata_entry_history.inc_response(res.is_ok())
+                }
+                Response::GetMDataChangesSince { ref res, .. } => {
+                    self.msg_get_mdata_changes_since.inc_response(res.is_ok())
+                }
                 Response::MutateMDataEntries { ref res, .. } => {
                     self.msg_mutate_mdata_entries.inc_response(res.is_ok())
                 }
+                Response::MutateMDataBatch { ref res, .. } => {
+                    self.msg_mutate_mdata_batch.inc_response(res.is_ok())
+                }
                 Response::ListMDataPermissions { ref res, .. } => {
                     self.msg_list_mdata_permissions.inc_response(res.is_ok())
                 }
@@ -186,6 +262,24 @@ impl Stats {
                 Response::ChangeMDataOwner { ref res, .. } => {
                     self.msg_change_mdata_owner.inc_response(res.is_ok())
                 }
+                Response::SubscribeMData { ref res, .. } => {
+                    self.msg_subscribe_mdata.inc_response(res.is_ok())
+                }
+                Response::UnsubscribeMData { ref res, .. } => {
+                    self.msg_unsubscribe_mdata.inc_response(res.is_ok())
+                }
+                Response::MDataChanged { .. } => self.msg_mdata_changed += 1,
+                Response::PutAData { ref res, .. } => self.msg_put_adata.inc_response(res.is_ok()),
+                Response::GetAData { ref res, .. } => self.msg_get_adata.inc_response(res.is_ok()),
+                Response::GetADataIndex { ref res, .. } => {
+                    self.msg_get_adata_index.inc_response(res.is_ok())
+                }
+                Response::GetADataRange { ref res, .. } => {
+                    self.msg_get_adata_range.inc_response(res.is_ok())
+                }
+                Response::AppendToAData { ref res, .. } => {
+                    self.msg_append_to_adata.inc_response(res.is_ok())
+                }
                 Response::ListAuthKeysAndVersion { ref res, .. } => self
                     .msg_list_auth_keys_and_version
                     .inc_response(res.is_ok()),
@@ -195,6 +289,21 @@ impl Stats {
                 Response::DelAuthKey { ref res, .. } => {
                     self.msg_del_auth_key.inc_response(res.is_ok())
                 }
+                Response::TransferCredit { ref res, .. } => {
+                    self.msg_transfer_credit.inc_response(res.is_ok())
+                }
+                Response::GetBalance { ref res, .. } => {
+                    self.msg_get_balance.inc_response(res.is_ok())
+                }
+                Response::GetBalanceHistory { ref res, .. } => {
+                    self.msg_get_balance_history.inc_response(res.is_ok())
+                }
+                Response::IssueInvitation { ref res, .. } => {
+                    self.msg_issue_invitation.inc_response(res.is_ok())
+                }
+                Response::ClaimInvitation { ref res, .. } => {
+                    self.msg_claim_invitation.inc_response(res.is_ok())
+                }
                 Response::GetAccountInfo { ref res, .. } => {
                     self.msg_get_account_info.inc_response(res.is_ok())
                 }
@@ -263,6 +372,10 @@ impl Stats {
                   self.msg_other,
                   self.routes,
                   self.unacked_msgs);
+            info!(target: "routing_stats",
+                  "Stats - Cache - Hits: {}, Misses: {}",
+                  self.cache_hits,
+                  self.cache_misses);
             info!(target: "routing_stats",
                   "Stats - Direct - CandidateInfo: {}, MessageSignature: {}, \
                    ResourceProof: {}/{}/{}, SectionListSignature: {}, ProxyRateLimitExceeded: {}",
@@ -295,6 +408,10 @@ impl Stats {
                   "Stats - User (total parts: {}) (Request/Success/Failure) - \
                    PutIData: {}, \
                    GetIData: {}, \
+                   GetIDataRange: {}, \
+                   PutUnpubIData: {}, \
+                   GetUnpubIData: {}, \
+                   DeleteUnpubIData: {}, \
                    PutMData: {}, \
                    GetMDataVersion: {}, \
                    GetMDataShell: {}, \
@@ -302,20 +419,40 @@ impl Stats {
                    ListMDataValues: {}, \
                    ListMDataEntries: {}, \
                    GetMDataValue: {}, \
+                   GetMDataEntryHistory: {}, \
+                   GetMDataChangesSince: {}, \
                    MutateMDataEntries: {}, \
+                   MutateMDataBatch: {}, \
                    ListMDataPermissions: {}, \
                    ListMDataUserPermissions: {}, \
                    SetMDataUserPermissions: {}, \
                    DelMDataUserPermissions: {}, \
                    ChangeMDataOwner: {}, \
+                   SubscribeMData: {}, \
+                   UnsubscribeMData: {}, \
+                   MDataChanged: {}, \
+                   PutAData: {}, \
+                   GetAData: {}, \
+                   GetADataIndex: {}, \
+                   GetADataRange: {}, \
+                   AppendToAData: {}, \
                    ListAuthKeysAndVersion: {}, \
                    InsAuthKey: {}, \
                    DelAuthKey: {}, \
+                   TransferCredit: {}, \
+                   GetBalance: {}, \
+                   GetBalanceHistory: {}, \
+                   IssueInvitation: {}, \
+                   ClaimInvitation: {}, \
                    GetAccountInfo: {}, \
                    Refresh: {}",
                   self.msg_user_parts,
                   self.msg_put_idata,
                   self.msg_get_idata,
+                  self.msg_get_idata_range,
+                  self.msg_put_unpub_idata,
+                  self.msg_get_unpub_idata,
+                  self.msg_delete_unpub_idata,
                   self.msg_put_mdata,
                   self.msg_get_mdata_version,
                   self.msg_get_mdata_shell,
@@ -323,15 +460,31 @@ impl Stats {
                   self.msg_list_mdata_values,
                   self.msg_list_mdata_entries,
                   self.msg_get_mdata_value,
+                  self.msg_get_mdata_entry_history,
+                  self.msg_get_mdata_changes_since,
                   self.msg_mutate_mdata_entries,
+                  self.msg_mutate_mdata_batch,
                   self.msg_list_mdata_permissions,
                   self.msg_list_mdata_user_permissions,
                   self.msg_set_mdata_user_permissions,
                   self.msg_del_mdata_user_permissions,
                   self.msg_change_mdata_owner,
+                  self.msg_subscribe_mdata,
+                  self.msg_unsubscribe_mdata,
+                  self.msg_mdata_changed,
+                  self.msg_put_adata,
+                  self.msg_get_adata,
+                  self.msg_get_adata_index,
+                  self.msg_get_adata_range,
+                  self.msg_append_to_adata,
                   self.msg_list_auth_keys_and_version,
                   self.msg_ins_auth_key,
                   self.msg_del_auth_key,
+                  self.msg_transfer_credit,
+                  self.msg_get_balance,
+                  self.msg_get_balance_history,
+                  self.msg_issue_invitation,
+                  self.msg_claim_invitation,
                   self.msg_get_account_info,
                   self.msg_refresh);
         }
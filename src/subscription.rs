@@ -0,0 +1,241 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+//! Subscriptions to mutations of `MutableData`.
+//!
+//! A client subscribes with `Request::SubscribeMData`, sent to the `NaeManager` section of the
+//! data, which records the subscription together with the client's proxy. Whenever the data
+//! mutates, the section sends `Response::MDataChanged` to every subscriber, and the proxy relays
+//! it to the client like any other response. A client which reconnects through a different proxy
+//! has to subscribe again.
+//!
+//! Subscriptions are part of the section's state for the data: whenever the data is handed over
+//! to another node, e.g. in the content of a `Request::Refresh` on churn, the subscriptions
+//! returned by `Subscriptions::for_data` have to be sent along with it and merged into the
+//! receiver's subscriptions with `Subscriptions::merge`.
+
+use crate::client_error::ClientError;
+use crate::id::PublicId;
+use crate::routing_table::Authority;
+use crate::xor_name::XorName;
+use std::collections::BTreeMap;
+
+/// Maximum number of clients which can subscribe to a single `MutableData`.
+pub const MAX_SUBSCRIBERS: usize = 100;
+
+/// The subscriptions to `MutableData` held by a section.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Subscriptions {
+    /// Subscribed clients by name and type tag of the data, each with the name of its proxy
+    subscribers: BTreeMap<(XorName, u64), BTreeMap<PublicId, XorName>>,
+}
+
+impl Subscriptions {
+    /// Creates an empty set of subscriptions.
+    pub fn new() -> Subscriptions {
+        Default::default()
+    }
+
+    /// Subscribes the client `src` to mutations of the data with the given name and type tag.
+    /// Subscribing again only updates the client's proxy.
+    pub fn subscribe(
+        &mut self,
+        name: XorName,
+        tag: u64,
+        src: &Authority<XorName>,
+    ) -> Result<(), ClientError> {
+        let (client_id, proxy_node_name) = client(src)?;
+        let subscribers = self
+            .subscribers
+            .entry((name, tag))
+            .or_insert_with(BTreeMap::new);
+        if subscribers.len() >= MAX_SUBSCRIBERS && !subscribers.contains_key(&client_id) {
+            return Err(ClientError::TooManySubscribers);
+        }
+        let _ = subscribers.insert(client_id, proxy_node_name);
+        Ok(())
+    }
+
+    /// Cancels the subscription of the client `src`. Succeeds even if there was none, so a
+    /// retried request isn't refused.
+    pub fn unsubscribe(
+        &mut self,
+        name: XorName,
+        tag: u64,
+        src: &Authority<XorName>,
+    ) -> Result<(), ClientError> {
+        let (client_id, _) = client(src)?;
+        let is_empty = match self.subscribers.get_mut(&(name, tag)) {
+            Some(subscribers) => {
+                let _ = subscribers.remove(&client_id);
+                subscribers.is_empty()
+            }
+            None => return Ok(()),
+        };
+        if is_empty {
+            let _ = self.subscribers.remove(&(name, tag));
+        }
+        Ok(())
+    }
+
+    /// Returns the authorities of the clients subscribed to the given data, to send
+    /// `Response::MDataChanged` to.
+    pub fn subscribers(&self, name: &XorName, tag: u64) -> Vec<Authority<XorName>> {
+        self.subscribers
+            .get(&(*name, tag))
+            .into_iter()
+            .flat_map(|subscribers| subscribers.iter())
+            .map(|(client_id, proxy_node_name)| Authority::Client {
+                client_id: *client_id,
+                proxy_node_name: *proxy_node_name,
+            })
+            .collect()
+    }
+
+    /// Drops all subscriptions to the given data, e.g. when it is deleted or the section stops
+    /// being responsible for it.
+    pub fn remove_data(&mut self, name: &XorName, tag: u64) {
+        let _ = self.subscribers.remove(&(*name, tag));
+    }
+
+    /// Returns the subscriptions to the given data, to hand over together with the data.
+    pub fn for_data(&self, name: &XorName, tag: u64) -> Subscriptions {
+        Subscriptions {
+            subscribers: self
+                .subscribers
+                .get(&(*name, tag))
+                .map(|subscribers| ((*name, tag), subscribers.clone()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Merges subscriptions handed over by another node. Known subscribers take the received
+    /// proxy, new ones are added as long as the data has fewer than `MAX_SUBSCRIBERS`.
+    pub fn merge(&mut self, other: Subscriptions) {
+        for (data_id, received) in other.subscribers {
+            let subscribers = self
+                .subscribers
+                .entry(data_id)
+                .or_insert_with(BTreeMap::new);
+            for (client_id, proxy_node_name) in received {
+                if subscribers.len() < MAX_SUBSCRIBERS || subscribers.contains_key(&client_id) {
+                    let _ = subscribers.insert(client_id, proxy_node_name);
+                }
+            }
+        }
+    }
+}
+
+fn client(src: &Authority<XorName>) -> Result<(PublicId, XorName), ClientError> {
+    match *src {
+        Authority::Client {
+            client_id,
+            proxy_node_name,
+        } => Ok((client_id, proxy_node_name)),
+        _ => Err(ClientError::InvalidOperation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::id::FullId;
+    use maidsafe_utilities::serialisation;
+    use rand;
+
+    fn client_authority(proxy_node_name: XorName) -> Authority<XorName> {
+        Authority::Client {
+            client_id: *FullId::new().public_id(),
+            proxy_node_name,
+        }
+    }
+
+    #[test]
+    fn subscribe_and_unsubscribe() {
+        let mut subscriptions = Subscriptions::new();
+        let name = rand::random();
+        let alice = client_authority(rand::random());
+        let bob = client_authority(rand::random());
+
+        unwrap!(subscriptions.subscribe(name, 10_000, &alice));
+        unwrap!(subscriptions.subscribe(name, 10_000, &bob));
+        assert_eq!(subscriptions.subscribers(&name, 10_000).len(), 2);
+        assert!(subscriptions.subscribers(&name, 10_001).is_empty());
+
+        // Only clients can subscribe.
+        assert_eq!(
+            subscriptions.subscribe(name, 10_000, &Authority::NaeManager(name)),
+            Err(ClientError::InvalidOperation)
+        );
+
+        // Subscribing through a new proxy replaces the old one.
+        let alice_moved = match alice {
+            Authority::Client { client_id, .. } => Authority::Client {
+                client_id,
+                proxy_node_name: rand::random(),
+            },
+            _ => unreachable!(),
+        };
+        unwrap!(subscriptions.subscribe(name, 10_000, &alice_moved));
+        let subscribers = subscriptions.subscribers(&name, 10_000);
+        assert_eq!(subscribers.len(), 2);
+        assert!(subscribers.contains(&alice_moved));
+        assert!(!subscribers.contains(&alice));
+
+        unwrap!(subscriptions.unsubscribe(name, 10_000, &alice));
+        unwrap!(subscriptions.unsubscribe(name, 10_000, &alice));
+        assert_eq!(subscriptions.subscribers(&name, 10_000), vec![bob]);
+
+        subscriptions.remove_data(&name, 10_000);
+        assert!(subscriptions.subscribers(&name, 10_000).is_empty());
+    }
+
+    #[test]
+    fn subscribers_limit() {
+        let mut subscriptions = Subscriptions::new();
+        let name = rand::random();
+        let clients: Vec<_> = (0..MAX_SUBSCRIBERS)
+            .map(|_| client_authority(rand::random()))
+            .collect();
+        for client in &clients {
+            unwrap!(subscriptions.subscribe(name, 10_000, client));
+        }
+
+        assert_eq!(
+            subscriptions.subscribe(name, 10_000, &client_authority(rand::random())),
+            Err(ClientError::TooManySubscribers)
+        );
+        // Existing subscribers can still renew their subscriptions.
+        unwrap!(subscriptions.subscribe(name, 10_000, &clients[0]));
+    }
+
+    #[test]
+    fn hand_over() {
+        let mut subscriptions = Subscriptions::new();
+        let name = rand::random();
+        let other_name = rand::random();
+        let alice = client_authority(rand::random());
+        let bob = client_authority(rand::random());
+        unwrap!(subscriptions.subscribe(name, 10_000, &alice));
+        unwrap!(subscriptions.subscribe(other_name, 10_000, &bob));
+
+        // Only the subscriptions to the handed over data are sent.
+        let serialised = unwrap!(serialisation::serialise(&subscriptions.for_data(&name, 10_000)));
+        let handed_over: Subscriptions = unwrap!(serialisation::deserialise(&serialised));
+
+        let mut receiver = Subscriptions::new();
+        unwrap!(receiver.subscribe(name, 10_000, &bob));
+        receiver.merge(handed_over);
+        let subscribers = receiver.subscribers(&name, 10_000);
+        assert_eq!(subscribers.len(), 2);
+        assert!(subscribers.contains(&alice));
+        assert!(subscribers.contains(&bob));
+        assert!(receiver.subscribers(&other_name, 10_000).is_empty());
+    }
+}
@@ -7,16 +7,16 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    create_connected_clients, create_connected_nodes_until_split, gen_immutable_data, poll_all,
-    TestNode,
+    add_connected_nodes_until_split, create_connected_clients, create_connected_nodes_until_split,
+    gen_immutable_data, poll_all, Nodes, TestClient, TestNode,
 };
 use fake_clock::FakeClock;
 use rand::Rng;
-use routing::mock_crust::Network;
+use routing::mock_crust::{Endpoint, Network};
 use routing::rate_limiter_consts::RATE;
 use routing::{
-    Authority, Event, EventStream, ImmutableData, MessageId, Prefix, Request, Response,
-    MAX_IMMUTABLE_DATA_SIZE_IN_BYTES,
+    Authority, BootstrapConfig, Event, EventStream, ImmutableData, LruCache, MessageId, Prefix,
+    Request, Response, MAX_IMMUTABLE_DATA_SIZE_IN_BYTES,
 };
 use std::sync::mpsc;
 
@@ -142,3 +142,118 @@ fn response_caching() {
         expect_no_event!(node);
     }
 }
+
+// Fetches `data` with the first client, letting the nodes of its section respond if the request
+// reaches them, then advances the clock so the rate limiter permits another request.
+fn fetch(nodes: &mut [TestNode], clients: &mut [TestClient], data: &ImmutableData) {
+    let message_id = MessageId::new();
+    let dst = Authority::NaeManager(*data.name());
+    unwrap!(clients[0].inner.get_idata(dst, *data.name(), message_id));
+    let _ = poll_all(nodes, clients);
+
+    for node in nodes.iter_mut() {
+        while let Ok(event) = node.try_next_ev() {
+            if let Event::Request {
+                request: Request::GetIData { name, msg_id },
+                src,
+                dst,
+            } = event
+            {
+                if name == *data.name() && msg_id == message_id {
+                    unwrap!(node
+                        .inner
+                        .send_get_idata_response(dst, src, Ok(data.clone()), msg_id));
+                }
+            }
+        }
+    }
+    let _ = poll_all(nodes, clients);
+
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::GetIData { res: Ok(ref res_data), msg_id: res_message_id },
+            ..
+        } if res_data == data && res_message_id == message_id
+    );
+    while clients[0].inner.try_next_ev().is_ok() {}
+    FakeClock::advance_time(MAX_IMMUTABLE_DATA_SIZE_IN_BYTES * 1000 / RATE as u64 + 1);
+}
+
+#[test]
+fn lru_cache_counters() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut rng = network.new_rng();
+
+    // The proxy node caches a single response.
+    let cache = LruCache::new(MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, 1);
+    let stats = cache.stats();
+    let mut nodes = vec![TestNode::new(
+        &network,
+        true,
+        None,
+        Some(Endpoint(0)),
+        Box::new(cache),
+    )];
+    let _ = nodes[0].poll();
+    add_connected_nodes_until_split(&network, &mut nodes, vec![1, 1], false);
+    let mut nodes = Nodes(nodes);
+    let mut clients = create_connected_clients(&network, &mut nodes, 1);
+
+    let data0 = gen_immutable_data_not_in_first_node_section(&mut rng, &nodes);
+    let data1 = gen_immutable_data_not_in_first_node_section(&mut rng, &nodes);
+
+    fetch(&mut nodes, &mut clients, &data0);
+    assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (0, 1, 0));
+    fetch(&mut nodes, &mut clients, &data0);
+    assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (1, 1, 0));
+
+    // Caching the second data evicts the first, and the other way round.
+    fetch(&mut nodes, &mut clients, &data1);
+    fetch(&mut nodes, &mut clients, &data0);
+    assert_eq!((stats.hits(), stats.misses(), stats.evictions()), (1, 3, 2));
+}
+
+#[test]
+fn client_cache() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut rng = network.new_rng();
+    let mut nodes = create_connected_nodes_until_split(&network, vec![1, 1], true);
+
+    let cache = LruCache::new(MAX_IMMUTABLE_DATA_SIZE_IN_BYTES, 10);
+    let stats = cache.stats();
+    let contact = nodes[0].handle.endpoint();
+    let mut clients = vec![TestClient::new_with_cache(
+        &network,
+        Some(BootstrapConfig::with_contacts(&[contact])),
+        None,
+        Box::new(cache),
+    )];
+    let _ = poll_all(&mut nodes, &mut clients);
+    expect_next_event!(clients[0], Event::Connected);
+
+    let data = gen_immutable_data_not_in_first_node_section(&mut rng, &nodes);
+    fetch(&mut nodes, &mut clients, &data);
+    assert_eq!((stats.hits(), stats.misses()), (0, 1));
+
+    // The client answers the second request itself, as if the data's section had responded.
+    let message_id = MessageId::new();
+    let dst = Authority::NaeManager(*data.name());
+    unwrap!(clients[0].inner.get_idata(dst, *data.name(), message_id));
+    let _ = poll_all(&mut nodes, &mut clients);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::GetIData { res: Ok(ref res_data), msg_id: res_message_id },
+            src,
+            ..
+        } if *res_data == data && res_message_id == message_id && src == dst
+    );
+    expect_no_event!(clients[0]);
+    for node in nodes.iter_mut() {
+        expect_no_event!(node);
+    }
+    assert_eq!((stats.hits(), stats.misses()), (1, 1));
+}
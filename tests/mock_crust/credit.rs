@@ -0,0 +1,194 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{create_connected_clients, create_connected_nodes, poll_all, TestClient, TestNode};
+use routing::credit::Balance;
+use routing::mock_crust::Network;
+use routing::{Authority, ClientError, Event, EventStream, MessageId, Request, Response, XorName};
+use std::collections::BTreeMap;
+
+// Plays the role of the vaults managing the accounts: every recipient applies the transfers to its
+// own copy of the balances, forwarding debited transfers to the credited account's manager.
+fn handle_requests(nodes: &mut [TestNode], balances: &mut [BTreeMap<XorName, Balance>]) {
+    for (node, balances) in nodes.iter_mut().zip(balances.iter_mut()) {
+        while let Ok(event) = node.try_next_ev() {
+            let (request, src, dst) = match event {
+                Event::Request { request, src, dst } => (request, src, dst),
+                _ => continue,
+            };
+            let name = match dst {
+                Authority::ClientManager(name) => name,
+                _ => continue,
+            };
+
+            let result = match (request, src) {
+                (
+                    Request::TransferCredit { amount, msg_id, .. },
+                    Authority::ClientManager(from),
+                ) => {
+                    let res = balances
+                        .entry(name)
+                        .or_insert_with(|| Balance::new(name, 0))
+                        .credit(msg_id, from, amount);
+                    if let Err(err) = res {
+                        trace!("Failed to credit {:?}: {:?}", name, err);
+                    }
+                    continue;
+                }
+                (Request::TransferCredit { to, amount, msg_id }, _) => {
+                    let res = balances
+                        .get_mut(&name)
+                        .ok_or(ClientError::NoSuchAccount)
+                        .and_then(|balance| balance.debit(msg_id, to, amount));
+                    if res.is_ok() {
+                        let credited = Authority::ClientManager(to);
+                        if let Err(err) = node
+                            .inner
+                            .send_transfer_credit_request(dst, credited, to, amount, msg_id)
+                        {
+                            trace!("Failed to forward the transfer: {:?}", err);
+                        }
+                    }
+                    node.inner.send_transfer_credit_response(dst, src, res, msg_id)
+                }
+                (Request::GetBalance(msg_id), _) => {
+                    let res = balances
+                        .get(&name)
+                        .map(Balance::balance)
+                        .ok_or(ClientError::NoSuchAccount);
+                    node.inner.send_get_balance_response(dst, src, res, msg_id)
+                }
+                (
+                    Request::GetBalanceHistory {
+                        start,
+                        limit,
+                        msg_id,
+                    },
+                    _,
+                ) => {
+                    let res = balances
+                        .get(&name)
+                        .map(|balance| balance.history(start, limit))
+                        .ok_or(ClientError::NoSuchAccount);
+                    node.inner
+                        .send_get_balance_history_response(dst, src, res, msg_id)
+                }
+                _ => continue,
+            };
+
+            if let Err(err) = result {
+                trace!("Failed to send response: {:?}", err);
+            }
+        }
+    }
+}
+
+// Delivers the pending requests to the managers, handles them, including the forwarded transfers,
+// and delivers the responses.
+fn exchange(
+    nodes: &mut [TestNode],
+    clients: &mut [TestClient],
+    balances: &mut [BTreeMap<XorName, Balance>],
+) {
+    let _ = poll_all(nodes, clients);
+    handle_requests(nodes, balances);
+    let _ = poll_all(nodes, clients);
+    handle_requests(nodes, balances);
+    let _ = poll_all(nodes, clients);
+}
+
+#[test]
+fn transfer_credit() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 2);
+
+    let (alice, bob) = (clients[0].name(), clients[1].name());
+    let (alice_manager, bob_manager) = (
+        Authority::ClientManager(alice),
+        Authority::ClientManager(bob),
+    );
+    let initial: BTreeMap<_, _> = vec![(alice, Balance::new(alice, 100))]
+        .into_iter()
+        .collect();
+    let mut balances = vec![initial; nodes.len()];
+
+    // A transfer is debited at the sender's manager and credited at the recipient's.
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .transfer_credit(alice_manager, bob, 30, msg_id));
+    exchange(&mut nodes, &mut clients, &mut balances);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::TransferCredit { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    // Transfers of nothing are refused.
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .transfer_credit(alice_manager, bob, 0, msg_id));
+    exchange(&mut nodes, &mut clients, &mut balances);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::TransferCredit {
+                res: Err(ClientError::InvalidOperation),
+                msg_id: id,
+            },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0].inner.get_balance(alice_manager, msg_id));
+    exchange(&mut nodes, &mut clients, &mut balances);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::GetBalance { res: Ok(70), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[1].inner.get_balance(bob_manager, msg_id));
+    exchange(&mut nodes, &mut clients, &mut balances);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::GetBalance { res: Ok(30), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    // The history is paged.
+    let msg_id = MessageId::new();
+    unwrap!(clients[1]
+        .inner
+        .get_balance_history(bob_manager, 0, 1, msg_id));
+    exchange(&mut nodes, &mut clients, &mut balances);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::GetBalanceHistory {
+                res: Ok((ref history, None)),
+                msg_id: id,
+            },
+            ..
+        } if id == msg_id
+            && history.len() == 1
+            && history[0].from == alice
+            && history[0].amount == 30
+    );
+}
@@ -0,0 +1,145 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    create_connected_clients, create_connected_nodes, exchange_mdata_requests, MDataStore,
+};
+use rand::Rng;
+use routing::mock_crust::Network;
+use routing::{dns, Authority, ClientError, Event, EventStream, MessageId, Response, XorName};
+
+#[test]
+fn register_and_resolve() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 2);
+    let mut stores = vec![MDataStore::default(); nodes.len()];
+
+    let long_name = "example";
+    let manager = Authority::NaeManager(dns::packet_name(long_name));
+    let key0 = *clients[0].full_id.public_id().signing_public_key();
+    let key1 = *clients[1].full_id.public_id().signing_public_key();
+
+    // The first client to register a name becomes its owner.
+    let msg_id = MessageId::new();
+    let name = unwrap!(clients[0]
+        .inner
+        .register_name(manager, long_name, key0, msg_id));
+    assert_eq!(name, dns::packet_name(long_name));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::PutMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    let _ = unwrap!(clients[1]
+        .inner
+        .register_name(manager, long_name, key1, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response:
+                Response::PutMData {
+                    res: Err(ClientError::DataExists),
+                    msg_id: id,
+                },
+            ..
+        } if id == msg_id
+    );
+
+    // Only the owner can add services, which anyone can resolve.
+    let target: XorName = network.new_rng().gen();
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .add_service(manager, long_name, "www", &target, 0, msg_id, key0));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::MutateMDataEntries { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[1]
+        .inner
+        .add_service(manager, long_name, "blog", &target, 0, msg_id, key1));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response:
+                Response::MutateMDataEntries {
+                    res: Err(ClientError::AccessDenied),
+                    msg_id: id,
+                },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[1]
+        .inner
+        .resolve_name(manager, long_name, "www", msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::GetMDataValue { res: Ok(ref value), msg_id: id },
+            ..
+        } if id == msg_id && dns::decode_target(value) == Ok(target)
+    );
+
+    // After a transfer, the new owner can remove services.
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .transfer_name(manager, long_name, key1, 1, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::ChangeMDataOwner { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[1]
+        .inner
+        .remove_service(manager, long_name, "www", 1, msg_id, key1));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::MutateMDataEntries { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .resolve_name(manager, long_name, "www", msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::GetMDataValue { res: Ok(ref value), msg_id: id },
+            ..
+        } if id == msg_id && dns::decode_target(value) == Err(ClientError::NoSuchEntry)
+    );
+}
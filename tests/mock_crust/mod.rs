@@ -10,17 +10,22 @@ mod accumulate;
 mod cache;
 mod churn;
 mod client_restrictions;
+mod credit;
+mod dns;
 mod drop;
 mod merge;
 mod requests;
+mod snapshot;
+mod subscription;
 mod tunnel;
 mod utils;
 
 pub use self::utils::{
     add_connected_nodes_until_split, create_connected_clients, create_connected_nodes,
-    create_connected_nodes_until_split, gen_bytes, gen_immutable_data, gen_range, gen_range_except,
-    poll_all, poll_and_resend, remove_nodes_which_failed_to_connect, sort_nodes_by_distance_to,
-    verify_invariant_for_all_nodes, Nodes, TestClient, TestNode,
+    create_connected_nodes_until_split, exchange_mdata_requests, gen_bytes, gen_immutable_data,
+    gen_range, gen_range_except, handle_mdata_requests, poll_all, poll_and_resend,
+    remove_nodes_which_failed_to_connect, sort_nodes_by_distance_to,
+    verify_invariant_for_all_nodes, MDataStore, Nodes, TestClient, TestNode,
 };
 use fake_clock::FakeClock;
 use routing::mock_crust::{Endpoint, Network};
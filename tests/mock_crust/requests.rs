@@ -7,12 +7,18 @@
 // permissions and limitations relating to use of the SAFE Network Software.
 
 use super::{
-    create_connected_clients, create_connected_nodes, gen_bytes, gen_immutable_data, poll_all,
+    create_connected_clients, create_connected_nodes, create_connected_nodes_until_split,
+    exchange_mdata_requests, gen_bytes, gen_immutable_data, poll_all, MDataStore,
 };
+use rand::Rng;
 use routing::mock_crust::Network;
 use routing::{
-    Authority, ClientError, Event, EventStream, ImmutableData, MessageId, Request, Response,
+    Action, Authority, ClientError, EntryActions, Event, EventStream, FullId, ImmutableData,
+    MDataEncryptionKey, MessageId, MutableData, PermissionSet, Request, Response, User, Value,
+    XorName,
 };
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
 
 #[test]
 fn successful_put_request() {
@@ -302,3 +308,304 @@ fn disconnect_on_get_request() {
         }
     }
 }
+
+#[test]
+fn failed_mutate_mdata_batch() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut rng = network.new_rng();
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 1);
+
+    let owner = *clients[0].full_id.public_id().signing_public_key();
+    let owners: BTreeSet<_> = iter::once(owner).collect();
+    let index = unwrap!(MutableData::new(
+        rng.gen(),
+        0,
+        BTreeMap::new(),
+        BTreeMap::new(),
+        owners.clone(),
+    ));
+    let mut entries = BTreeMap::new();
+    let _ = entries.insert(
+        b"key".to_vec(),
+        Value {
+            content: gen_bytes(&mut rng, 8),
+            entry_version: 0,
+        },
+    );
+    let record = unwrap!(MutableData::new(
+        rng.gen(),
+        0,
+        BTreeMap::new(),
+        entries,
+        owners,
+    ));
+
+    // The insert into the index is valid, but the update of the record has a wrong version.
+    let index_id = (*index.name(), index.tag());
+    let record_id = (*record.name(), record.tag());
+    let mut mutations = BTreeMap::new();
+    let _ = mutations.insert(
+        index_id,
+        EntryActions::new()
+            .ins(b"record".to_vec(), record.name().0.to_vec(), 0)
+            .into(),
+    );
+    let _ = mutations.insert(
+        record_id,
+        EntryActions::new()
+            .update(b"key".to_vec(), gen_bytes(&mut rng, 8), 5)
+            .into(),
+    );
+
+    let dst = Authority::NaeManager(*index.name());
+    let message_id = MessageId::new();
+
+    unwrap!(clients[0]
+        .inner
+        .mutate_mdata_batch(dst, mutations, message_id, owner));
+
+    let _ = poll_all(&mut nodes, &mut clients);
+
+    let mut request_received_count = 0;
+
+    for node in nodes.iter_mut().filter(|n| n.is_recipient(&dst)) {
+        // Every recipient applies the batch to its own copies of the data.
+        let mut stored = vec![index.clone(), record.clone()];
+        loop {
+            match node.try_next_ev() {
+                Ok(Event::Request {
+                    request:
+                        Request::MutateMDataBatch {
+                            mutations: req_mutations,
+                            msg_id: req_message_id,
+                            requester,
+                        },
+                    src,
+                    dst,
+                }) => {
+                    request_received_count += 1;
+                    if message_id == req_message_id {
+                        let res = MutableData::mutate_batch(&mut stored, req_mutations, requester);
+                        assert_eq!(stored, vec![index.clone(), record.clone()]);
+                        if let Err(err) =
+                            node.inner
+                                .send_mutate_mdata_batch_response(dst, src, res, req_message_id)
+                        {
+                            trace!("Failed to send MutateMDataBatch response: {:?}", err);
+                        }
+                        break;
+                    }
+                }
+                Ok(_) => (),
+                _ => panic!("Event::Request not received"),
+            }
+        }
+    }
+
+    // TODO: Assert a quorum here.
+    assert!(2 * request_received_count > min_section_size);
+
+    let _ = poll_all(&mut nodes, &mut clients);
+
+    // Only the failed target is reported.
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response:
+                Response::MutateMDataBatch {
+                    res: Err(ClientError::InvalidBatchMutations(ref errors)),
+                    msg_id: ref res_message_id,
+                },
+            ..
+        } if *res_message_id == message_id
+            && errors.len() == 1
+            && errors.contains_key(&record_id)
+    );
+}
+
+#[test]
+fn cross_section_mutate_mdata_batch() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut rng = network.new_rng();
+    let mut nodes = create_connected_nodes_until_split(&network, vec![1, 1], false);
+    let mut clients = create_connected_clients(&network, &mut nodes, 1);
+
+    // The sections are [0, 1], so the targets are managed by different sections.
+    let mut name0: XorName = rng.gen();
+    name0.0[0] &= 0x7f;
+    let mut name1: XorName = rng.gen();
+    name1.0[0] |= 0x80;
+
+    let mut mutations = BTreeMap::new();
+    for name in &[name0, name1] {
+        let _ = mutations.insert(
+            (*name, 0),
+            EntryActions::new()
+                .ins(b"key".to_vec(), gen_bytes(&mut rng, 8), 0)
+                .into(),
+        );
+    }
+
+    let owner = *clients[0].full_id.public_id().signing_public_key();
+    let dst = Authority::NaeManager(name0);
+    let message_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .mutate_mdata_batch(dst, mutations, message_id, owner));
+
+    let _ = poll_all(&mut nodes, &mut clients);
+
+    // The section refuses the batch itself, without raising it to the vaults.
+    for node in nodes.iter_mut() {
+        while let Ok(event) = node.try_next_ev() {
+            if let Event::Request {
+                request: Request::MutateMDataBatch { .. },
+                ..
+            } = event
+            {
+                panic!("Unexpected MutateMDataBatch request at {:?}", node.name());
+            }
+        }
+    }
+
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response:
+                Response::MutateMDataBatch {
+                    res: Err(ClientError::InvalidOperation),
+                    msg_id: ref res_message_id,
+                },
+            ..
+        } if *res_message_id == message_id
+    );
+}
+
+#[test]
+fn read_private_mdata() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 2);
+    let mut stores = vec![MDataStore::default(); nodes.len()];
+
+    let owner = *clients[0].full_id.public_id().signing_public_key();
+    let enc_key = MDataEncryptionKey::new();
+    let key = enc_key.encrypt_key(b"key");
+    let value = Value {
+        content: enc_key.encrypt_content(b"value"),
+        entry_version: 0,
+    };
+    let data = unwrap!(MutableData::new(
+        network.new_rng().gen(),
+        10_000,
+        BTreeMap::new(),
+        iter::once((key.clone(), value.clone())).collect(),
+        iter::once(owner).collect(),
+    ))
+    .with_private_entries();
+    let (name, tag) = (*data.name(), data.tag());
+    let manager = Authority::NaeManager(name);
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0].inner.put_mdata(manager, data, msg_id, owner));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::PutMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    // Only clients allowed to read the entries get them.
+    let msg_id = MessageId::new();
+    unwrap!(clients[1]
+        .inner
+        .get_mdata_value(manager, name, tag, key.clone(), msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::GetMDataValue {
+                res: Err(ClientError::AccessDenied),
+                msg_id: id,
+            },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .get_mdata_value(manager, name, tag, key, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::GetMDataValue { res: Ok(ref received), msg_id: id },
+            ..
+        } if id == msg_id && *received == value
+    );
+}
+
+#[test]
+fn list_expired_permissions() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 1);
+    let mut stores = vec![MDataStore::default(); nodes.len()];
+
+    // One grant expires at the initial data version already, the other one never does.
+    let owner = *clients[0].full_id.public_id().signing_public_key();
+    let expired = User::Key(*FullId::new().public_id().signing_public_key());
+    let mut permissions = BTreeMap::new();
+    let _ = permissions.insert(
+        expired,
+        PermissionSet::new().allow(Action::Insert).expires_at(0),
+    );
+    let _ = permissions.insert(User::Anyone, PermissionSet::new().allow(Action::Insert));
+    let data = unwrap!(MutableData::new(
+        network.new_rng().gen(),
+        10_000,
+        permissions.clone(),
+        BTreeMap::new(),
+        iter::once(owner).collect(),
+    ));
+    let (name, tag) = (*data.name(), data.tag());
+    let manager = Authority::NaeManager(name);
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0].inner.put_mdata(manager, data, msg_id, owner));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::PutMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    // The expired grant is still listed, but reported as such.
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .list_mdata_permissions(manager, name, tag, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::ListMDataPermissions {
+                res: Ok((ref listed, ref expired_users)),
+                msg_id: id,
+            },
+            ..
+        } if id == msg_id
+            && *listed == permissions
+            && *expired_users == iter::once(expired).collect::<BTreeSet<_>>()
+    );
+}
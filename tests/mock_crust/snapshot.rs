@@ -0,0 +1,58 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{create_connected_nodes, poll_and_resend, verify_invariant_for_all_nodes, TestNode};
+use rand;
+use routing::mock_crust::{Endpoint, Network};
+use routing::{Event, EventStream, RoutingSnapshot};
+use std::env;
+use std::fs;
+
+#[test]
+fn rejoin_with_snapshot() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+
+    // Save the routing table of a node before stopping it.
+    let file_name = format!("routing_snapshot_{:016x}", rand::random::<u64>());
+    let path = env::temp_dir().join(file_name);
+    let node = nodes.remove(1);
+    let old_id = node.id();
+    unwrap!(unwrap!(node.inner.routing_snapshot()).save(&path));
+    drop(node);
+    poll_and_resend(&mut nodes, &mut []);
+
+    let snapshot = unwrap!(RoutingSnapshot::load(&path));
+    unwrap!(fs::remove_file(&path));
+    assert_eq!(snapshot.name(), old_id.name());
+    let contacts = snapshot.contacts();
+    assert!(nodes.iter().all(|node| contacts.contains(&node.id())));
+
+    // The restarted node has no configured contacts, so it can only bootstrap against the peers
+    // it remembers, which it tries in the order of the snapshot.
+    let remembered: Vec<_> = contacts
+        .iter()
+        .map(|pub_id| unwrap!(nodes.iter().find(|node| node.id() == *pub_id)).handle.endpoint())
+        .collect();
+    nodes.push(
+        TestNode::builder(&network)
+            .endpoint(Endpoint(min_section_size + 1))
+            .snapshot(snapshot)
+            .create(),
+    );
+    assert_eq!(unwrap!(nodes.last()).handle.bootstrap_contacts(), remembered);
+    poll_and_resend(&mut nodes, &mut []);
+
+    // It joins under a new name.
+    let new_node = unwrap!(nodes.last_mut());
+    expect_any_event!(new_node, Event::Connected);
+    assert_ne!(new_node.id(), old_id);
+    assert!(!new_node.routing_table().has(old_id.name()));
+    verify_invariant_for_all_nodes(&mut nodes);
+}
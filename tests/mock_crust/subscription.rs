@@ -0,0 +1,125 @@
+// Copyright 2018 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under The General Public License (GPL), version 3.
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied. Please review the Licences for the specific language governing
+// permissions and limitations relating to use of the SAFE Network Software.
+
+use super::{
+    create_connected_clients, create_connected_nodes, exchange_mdata_requests, MDataStore,
+};
+use rand::Rng;
+use routing::mock_crust::Network;
+use routing::{Authority, EntryActions, Event, EventStream, MessageId, MutableData, Response};
+use std::collections::BTreeMap;
+use std::iter;
+
+#[test]
+fn notify_subscribers() {
+    let min_section_size = 8;
+    let network = Network::new(min_section_size, None);
+    let mut nodes = create_connected_nodes(&network, min_section_size + 1);
+    let mut clients = create_connected_clients(&network, &mut nodes, 2);
+    let mut stores = vec![MDataStore::default(); nodes.len()];
+
+    let owner = *clients[0].full_id.public_id().signing_public_key();
+    let data = unwrap!(MutableData::new(
+        network.new_rng().gen(),
+        10_000,
+        BTreeMap::new(),
+        BTreeMap::new(),
+        iter::once(owner).collect(),
+    ));
+    let (name, tag) = (*data.name(), data.tag());
+    let manager = Authority::NaeManager(name);
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[0].inner.put_mdata(manager, data, msg_id, owner));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::PutMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let msg_id = MessageId::new();
+    unwrap!(clients[1].inner.subscribe_mdata(name, tag, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::SubscribeMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    // A node which lost its state gets the subscriptions handed over together with the data.
+    let new_manager = unwrap!(nodes.iter().position(|node| node.is_recipient(&manager)));
+    let content = stores[new_manager].refresh_content(&name);
+    stores[new_manager] = MDataStore::default();
+    let msg_id = MessageId::new();
+    for node in nodes.iter_mut().filter(|node| node.is_recipient(&manager)) {
+        unwrap!(node
+            .inner
+            .send_refresh_request(manager, manager, content.clone(), msg_id));
+    }
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    assert!(stores[new_manager].data.contains_key(&name));
+    assert_eq!(stores[new_manager].subscriptions.subscribers(&name, tag).len(), 1);
+
+    // A mutation by the owner is relayed to the subscriber by its proxy.
+    let actions = EntryActions::new().ins(b"key".to_vec(), b"value".to_vec(), 0);
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .mutate_mdata_entries(manager, name, tag, actions.into(), msg_id, owner));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::MDataChanged { ref notification, .. },
+            src: Authority::NaeManager(src_name),
+            ..
+        } if src_name == name
+            && notification.data_version == 1
+            && notification.keys.contains(&b"key"[..])
+    );
+
+    // Once unsubscribed, further mutations aren't reported.
+    let msg_id = MessageId::new();
+    unwrap!(clients[1].inner.unsubscribe_mdata(name, tag, msg_id));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[1],
+        Event::Response {
+            response: Response::UnsubscribeMData { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+
+    let actions = EntryActions::new().update(b"key".to_vec(), b"new value".to_vec(), 1);
+    let msg_id = MessageId::new();
+    unwrap!(clients[0]
+        .inner
+        .mutate_mdata_entries(manager, name, tag, actions.into(), msg_id, owner));
+    exchange_mdata_requests(&mut nodes, &mut clients, &mut stores, &manager);
+    expect_any_event!(
+        clients[0],
+        Event::Response {
+            response: Response::MutateMDataEntries { res: Ok(()), msg_id: id },
+            ..
+        } if id == msg_id
+    );
+    while let Ok(event) = clients[1].inner.try_next_ev() {
+        if let Event::Response {
+            response: Response::MDataChanged { .. },
+            ..
+        } = event
+        {
+            panic!("Unexpected notification after unsubscribing: {:?}", event);
+        }
+    }
+}
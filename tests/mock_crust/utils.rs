@@ -12,15 +12,18 @@
 use fake_clock::FakeClock;
 use itertools::Itertools;
 use rand::Rng;
+use maidsafe_utilities::serialisation;
 use routing::mock_crust::{self, Endpoint, Network, ServiceHandle};
+use routing::subscription::Subscriptions;
 use routing::test_consts::{ACK_TIMEOUT_SECS, CONNECTING_PEER_TIMEOUT_SECS};
 use routing::{
-    verify_network_invariant, Authority, BootstrapConfig, Cache, Client, Config, DevConfig, Event,
-    EventStream, FullId, ImmutableData, Node, NullCache, Prefix, PublicId, Request, Response,
-    RoutingTable, XorName, Xorable,
+    verify_network_invariant, Authority, BootstrapConfig, Cache, Client, ClientError, Config,
+    DevConfig, Event, EventStream, FullId, ImmutableData, MDataNotification, MutableData, Node,
+    NullCache, Prefix, PublicId, Request, Response, RoutingSnapshot, RoutingTable, XorName,
+    Xorable,
 };
 use std::cell::RefCell;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::IpAddr;
 use std::ops::{Deref, DerefMut};
 use std::sync::mpsc::{RecvError, TryRecvError};
@@ -130,6 +133,7 @@ impl TestNode {
             bootstrap_config: None,
             endpoint: None,
             cache: Box::new(NullCache),
+            snapshot: None,
         }
     }
 
@@ -139,15 +143,30 @@ impl TestNode {
         bootstrap_config: Option<BootstrapConfig>,
         endpoint: Option<Endpoint>,
         cache: Box<Cache>,
+    ) -> Self {
+        Self::new_impl(network, first_node, bootstrap_config, endpoint, cache, None)
+    }
+
+    fn new_impl(
+        network: &Network<PublicId>,
+        first_node: bool,
+        bootstrap_config: Option<BootstrapConfig>,
+        endpoint: Option<Endpoint>,
+        cache: Box<Cache>,
+        snapshot: Option<RoutingSnapshot>,
     ) -> Self {
         let handle = network.new_service_handle(bootstrap_config, endpoint);
         let config = create_config(network);
         let node = mock_crust::make_current(&handle, || {
-            unwrap!(Node::builder()
+            let builder = Node::builder()
                 .cache(cache)
                 .first(first_node)
-                .config(config)
-                .create())
+                .config(config);
+            let builder = match snapshot {
+                Some(snapshot) => builder.snapshot(snapshot),
+                None => builder,
+            };
+            unwrap!(builder.create())
         });
 
         TestNode {
@@ -186,6 +205,7 @@ pub struct TestNodeBuilder<'a> {
     bootstrap_config: Option<BootstrapConfig>,
     endpoint: Option<Endpoint>,
     cache: Box<Cache>,
+    snapshot: Option<RoutingSnapshot>,
 }
 
 impl<'a> TestNodeBuilder<'a> {
@@ -214,13 +234,19 @@ impl<'a> TestNodeBuilder<'a> {
         self
     }
 
+    pub fn snapshot(mut self, snapshot: RoutingSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
     pub fn create(self) -> TestNode {
-        TestNode::new(
+        TestNode::new_impl(
             self.network,
             self.first_node,
             self.bootstrap_config,
             self.endpoint,
             self.cache,
+            self.snapshot,
         )
     }
 }
@@ -263,6 +289,32 @@ impl TestClient {
         Self::new_impl(network, bootstrap_config, endpoint, full_id, duration)
     }
 
+    pub fn new_with_cache(
+        network: &Network<PublicId>,
+        bootstrap_config: Option<BootstrapConfig>,
+        endpoint: Option<Endpoint>,
+        cache: Box<Cache>,
+    ) -> Self {
+        let full_id = FullId::new();
+        let duration = Duration::from_secs(CLIENT_MSG_EXPIRY_DUR_SECS);
+        let handle = network.new_service_handle(bootstrap_config.clone(), endpoint);
+        let client = mock_crust::make_current(&handle, || {
+            unwrap!(Client::with_cache(
+                Some(full_id.clone()),
+                bootstrap_config,
+                create_config(network),
+                duration,
+                cache,
+            ))
+        });
+
+        TestClient {
+            handle: handle,
+            inner: client,
+            full_id: full_id,
+        }
+    }
+
     fn new_impl(
         network: &Network<PublicId>,
         bootstrap_config: Option<BootstrapConfig>,
@@ -326,6 +378,173 @@ impl Cache for TestCache {
     }
 }
 
+// -----  MDataStore  -----
+
+// The state kept by every node of a `MutableData`'s `NaeManager` in tests playing the vaults' role.
+#[derive(Clone, Default)]
+pub struct MDataStore {
+    pub data: BTreeMap<XorName, MutableData>,
+    pub subscriptions: Subscriptions,
+}
+
+impl MDataStore {
+    // The `Refresh` content handing the given data and its subscriptions over to another node.
+    pub fn refresh_content(&self, name: &XorName) -> Vec<u8> {
+        let data = unwrap!(self.data.get(name));
+        let subscriptions = self.subscriptions.for_data(name, data.tag());
+        unwrap!(serialisation::serialise(&(data, subscriptions)))
+    }
+}
+
+// Plays the role of the vaults managing `MutableData`: every recipient applies the requests it
+// received to its own store, responds to them and notifies the subscribers of mutations.
+pub fn handle_mdata_requests(
+    nodes: &mut [TestNode],
+    stores: &mut [MDataStore],
+    manager: &Authority<XorName>,
+) {
+    for (node, store) in nodes.iter_mut().zip(stores.iter_mut()) {
+        if !node.is_recipient(manager) {
+            continue;
+        }
+
+        while let Ok(event) = node.try_next_ev() {
+            let (request, src, dst) = match event {
+                Event::Request { request, src, dst } => (request, src, dst),
+                _ => continue,
+            };
+
+            let result = match request {
+                Request::Refresh(content, _) => {
+                    let (data, subscriptions): (MutableData, Subscriptions) =
+                        unwrap!(serialisation::deserialise(&content));
+                    let _ = store.data.entry(*data.name()).or_insert(data);
+                    store.subscriptions.merge(subscriptions);
+                    continue;
+                }
+                Request::PutMData { data, msg_id, .. } => {
+                    let res = if store.data.contains_key(data.name()) {
+                        Err(ClientError::DataExists)
+                    } else {
+                        data.validate_with_validators(node.inner.type_tag_validators())
+                            .map(|()| {
+                                let _ = store.data.insert(*data.name(), data);
+                            })
+                    };
+                    node.inner.send_put_mdata_response(dst, src, res, msg_id)
+                }
+                Request::GetMDataValue {
+                    name, key, msg_id, ..
+                } => {
+                    let res = store
+                        .data
+                        .get(&name)
+                        .ok_or(ClientError::NoSuchData)
+                        .and_then(|data| {
+                            data.check_read_permission(&src)?;
+                            data.get(&key).cloned().ok_or(ClientError::NoSuchEntry)
+                        });
+                    node.inner.send_get_mdata_value_response(dst, src, res, msg_id)
+                }
+                Request::ListMDataPermissions { name, msg_id, .. } => {
+                    let res = store
+                        .data
+                        .get(&name)
+                        .map(|data| (data.permissions().clone(), data.expired_permissions()))
+                        .ok_or(ClientError::NoSuchData);
+                    node.inner
+                        .send_list_mdata_permissions_response(dst, src, res, msg_id)
+                }
+                Request::SubscribeMData { name, tag, msg_id } => {
+                    let res = store.subscriptions.subscribe(name, tag, &src);
+                    node.inner.send_subscribe_mdata_response(dst, src, res, msg_id)
+                }
+                Request::UnsubscribeMData { name, tag, msg_id } => {
+                    let res = store.subscriptions.unsubscribe(name, tag, &src);
+                    node.inner
+                        .send_unsubscribe_mdata_response(dst, src, res, msg_id)
+                }
+                Request::MutateMDataEntries {
+                    name,
+                    tag,
+                    actions,
+                    msg_id,
+                    requester,
+                } => {
+                    let keys = actions.keys().cloned().collect();
+                    let res = store
+                        .data
+                        .get_mut(&name)
+                        .ok_or(ClientError::NoSuchData)
+                        .and_then(|data| {
+                            let validators = node.inner.type_tag_validators();
+                            data.mutate_entries_with_validators(validators, actions, requester)?;
+                            Ok(MDataNotification::new(data, keys))
+                        });
+                    if let Ok(ref notification) = res {
+                        let subscribers = store.subscriptions.subscribers(&name, tag);
+                        if let Err(err) = node.inner.send_mdata_notifications(
+                            dst,
+                            subscribers,
+                            notification,
+                            msg_id,
+                        ) {
+                            trace!("Failed to send notifications: {:?}", err);
+                        }
+                    }
+                    node.inner
+                        .send_mutate_mdata_entries_response(dst, src, res.map(|_| ()), msg_id)
+                }
+                Request::ChangeMDataOwner {
+                    name,
+                    new_owners,
+                    version,
+                    msg_id,
+                    ..
+                } => {
+                    let client_key = match src {
+                        Authority::Client { ref client_id, .. } => {
+                            Some(*client_id.signing_public_key())
+                        }
+                        _ => None,
+                    };
+                    let res = match store.data.get_mut(&name) {
+                        None => Err(ClientError::NoSuchData),
+                        Some(ref data)
+                            if client_key.map_or(true, |key| !data.owners().contains(&key)) =>
+                        {
+                            Err(ClientError::AccessDenied)
+                        }
+                        Some(data) => match new_owners.into_iter().next() {
+                            Some(new_owner) => data.change_owner(new_owner, version),
+                            None => Err(ClientError::InvalidOwners),
+                        },
+                    };
+                    node.inner
+                        .send_change_mdata_owner_response(dst, src, res, msg_id)
+                }
+                _ => continue,
+            };
+
+            if let Err(err) = result {
+                trace!("Failed to send response: {:?}", err);
+            }
+        }
+    }
+}
+
+// Delivers the pending requests to the managers, handles them and delivers the responses.
+pub fn exchange_mdata_requests(
+    nodes: &mut [TestNode],
+    clients: &mut [TestClient],
+    stores: &mut [MDataStore],
+    manager: &Authority<XorName>,
+) {
+    let _ = poll_all(nodes, clients);
+    handle_mdata_requests(nodes, stores, manager);
+    let _ = poll_all(nodes, clients);
+}
+
 // -----  poll_all, create_connected_...  -----
 
 /// Process all events. Returns whether there were any events.